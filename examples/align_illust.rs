@@ -4,7 +4,7 @@ use illustruct::{
         primitive::Primitive,
         composite::Composite,
     },
-    block_draw::{BlockDrawSpec, util::Vec2},
+    block_draw::BlockDrawSpec,
     mem_ribbon::MemRibbon,
 };
 
@@ -17,16 +17,7 @@ fn main() {
     let uint32_t = Kind::from(U32);
     let uint64_t = Kind::from(U64);
 
-    let spec = &BlockDrawSpec {
-        char_dims:  Vec2::new(10.0, 16.0),
-        text_pads:  Vec2::new( 4.0,  4.0),
-        label_pads: Vec2::new(10.0,  4.0),
-        union_xpad: 6.0,
-        fill_inset: 3.0,
-        prong_width: 12.0,
-        prong_xpad: 6.0,
-        chamfer_size: 12.0
-    };
+    let spec = &BlockDrawSpec::default();
 
     let align_rib = MemRibbon::new(0).span(
         "RAM_start",
@@ -0,0 +1,51 @@
+use illustruct::{
+    kind::{
+        Kind,
+        primitive::{Primitive, PrimValue},
+        composite::{Composite, Field},
+    },
+    block_draw::BlockDrawSpec,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+fn main() {
+    use Primitive::*;
+
+    let uint8_t  = Kind::from(U8);
+    let uint16_t = Kind::from(U16);
+    let uint32_t = Kind::from(U32);
+    let int32_t  = Kind::from(I32);
+
+    let spec = &BlockDrawSpec::default();
+
+    let header = Kind::from(Composite::product(
+        "Header",
+        vec![
+            Field::bitfield("version", &uint8_t, 4),
+            Field::bitfield("ihl", &uint8_t, 4),
+            Field::bitfield("flags", &uint16_t, 3),
+            Field::bitfield("offset", &uint16_t, 13),
+            Field::anon_bitfield(&uint32_t, 0),
+            Field::bitfield("delta", &int32_t, 6),
+            uint8_t.field_named("ttl"),
+        ],
+    ));
+
+    for target in [Target::ilp32(), Target::ppc32(), Target::llp64()] {
+        let file_name = format!("bitfield_{}.svg", target.name.to_lowercase());
+
+        let mut ribbon = MemRibbon::new(0)
+            .target(target)
+            .span("packet", vec![header.field_named("hdr")]);
+
+        ribbon.set("packet.hdr.version", PrimValue::U8(4)).unwrap();
+        ribbon.set("packet.hdr.ihl", PrimValue::U8(5)).unwrap();
+        ribbon.set("packet.hdr.flags", PrimValue::U16(2)).unwrap();
+        ribbon.set("packet.hdr.offset", PrimValue::U16(185)).unwrap();
+        ribbon.set("packet.hdr.delta", PrimValue::I32(-7)).unwrap();
+        ribbon.set("packet.hdr.ttl", PrimValue::U8(64)).unwrap();
+
+        ribbon.save_svg(file_name, spec, true, true);
+    }
+}
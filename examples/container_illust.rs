@@ -0,0 +1,46 @@
+use illustruct::{
+    kind::{
+        primitive::Primitive,
+        container::Container,
+    },
+    block_draw::BlockDrawSpec,
+    context::Context,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+fn main() {
+    let context = Context::new();
+    let uint32_t = context.add(Primitive::U32);
+    let int = context.add(Primitive::I32);
+
+    let numbers = Container::vec(&context, uint32_t);
+    let name = Container::string(&context);
+    let shared = Container::rc(&context, uint32_t);
+    let scores = Container::vector(&context, int);
+    let short = Container::cxx_string(&context);
+    let long = Container::cxx_string(&context);
+
+    let spec = &BlockDrawSpec::default();
+
+    let ribbon = MemRibbon::new(0x1000)
+        .target(Target::lp64())
+        .span("rust", vec![
+            numbers.kind.field_named("numbers"),
+            name.kind.field_named("name"),
+            shared.kind.field_named("shared"),
+        ])
+        .span("cxx", vec![
+            scores.kind.field_named("scores"),
+            short.kind.field_named("short"),
+            long.kind.field_named("long"),
+        ])
+        .heap("rust.numbers", &numbers, 3, 4).unwrap()
+        .heap("rust.name", &name, 5, 8).unwrap()
+        .heap("rust.shared", &shared, 1, 1).unwrap()
+        .heap("cxx.scores", &scores, 2, 4).unwrap()
+        .heap("cxx.short", &short, 5, 5).unwrap()
+        .heap("cxx.long", &long, 20, 30).unwrap();
+
+    ribbon.save_svg("containers.svg", spec, true, true);
+}
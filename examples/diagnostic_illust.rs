@@ -0,0 +1,39 @@
+use illustruct::{
+    kind::primitive::PrimValue,
+    block_draw::BlockDrawSpec,
+    context::Context,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+const HEADER: &str = "
+    struct node { int value; struct node *next; };
+    struct node pool[2];
+    struct node *nodes[3];
+";
+
+fn main() {
+    let _target = Target::lp64().enter();
+    let context = Context::new();
+
+    let spec = &BlockDrawSpec::default();
+
+    let mut ribbon = MemRibbon::new(0x1000)
+        .target(Target::lp64())
+        .span("span", context.parse_c(HEADER).unwrap());
+
+    ribbon.write_at(0x1030, PrimValue::Size(0x1010));
+    ribbon.set("span.pool[1].value", PrimValue::I32(7)).unwrap();
+
+    for access in ["span.nodes[2]->nxt", "span.nodes[2->next", "span.pool[4]", "sapn.pool"] {
+        if let Err(diagnostic) = ribbon.resolve(access) {
+            println!("{diagnostic}\n");
+        }
+    }
+
+    if let Err(err) = context.parse_c("struct broken {\n    int x\n};") {
+        println!("{err}");
+    }
+
+    ribbon.save_svg("diagnostic.svg", spec, true, true);
+}
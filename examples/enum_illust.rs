@@ -0,0 +1,52 @@
+use illustruct::{
+    kind::{
+        Kind,
+        primitive::{Primitive, PrimValue},
+        composite::{Composite, Field},
+        enumeration::{Enum, Variant},
+    },
+    block_draw::BlockDrawSpec,
+    mem_ribbon::MemRibbon,
+};
+
+fn main() {
+    use Primitive::*;
+
+    let uint16_t = Kind::from(U16);
+
+    let color = Kind::from(Enum::named("Color", U8, vec![
+        Variant::new("RED", 0),
+        Variant::new("GREEN", 1),
+        Variant::new("BLUE", 2),
+    ]));
+
+    let access = Kind::from(Enum::flags("Access", U8, vec![
+        Variant::new("READ", 1),
+        Variant::new("WRITE", 2),
+        Variant::new("EXEC", 4),
+    ]));
+
+    let spec = &BlockDrawSpec::default();
+
+    let pixel = Kind::from(Composite::product(
+        "Pixel",
+        vec![
+            color.field_named("fg"),
+            color.field_named("bg"),
+            Field::bitfield("mode", &access, 3),
+            Field::bitfield("spare", &access, 5),
+            uint16_t.field_named("count"),
+        ],
+    ));
+
+    let mut ribbon = MemRibbon::new(0)
+        .span("screen", vec![pixel.field_named("px")]);
+
+    ribbon.set("screen.px.fg", PrimValue::U8(2)).unwrap();
+    ribbon.set("screen.px.bg", PrimValue::U8(9)).unwrap();
+    ribbon.set("screen.px.mode", PrimValue::U8(3)).unwrap();
+    ribbon.set("screen.px.spare", PrimValue::U8(8)).unwrap();
+    ribbon.set("screen.px.count", PrimValue::U16(12)).unwrap();
+
+    ribbon.save_svg("enum.svg", spec, true, true);
+}
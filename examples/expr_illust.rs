@@ -0,0 +1,37 @@
+use illustruct::{
+    kind::primitive::PrimValue,
+    block_draw::BlockDrawSpec,
+    context::Context,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+const HEADER: &str = "
+    struct hdr { unsigned short len; unsigned char kind; unsigned char flags; };
+    struct packet { struct hdr *head; unsigned char buf[12]; } pkt;
+";
+
+fn main() {
+    let _target = Target::lp64().enter();
+    let context = Context::new();
+
+    let spec = &BlockDrawSpec::default();
+
+    let mut ribbon = MemRibbon::new(0x4000)
+        .target(Target::lp64())
+        .span("net", context.parse_c(HEADER).unwrap());
+
+    let buf = ribbon.eval(&context, "&net.pkt.buf[4]").unwrap().integer().unwrap();
+    ribbon.write_at(ribbon.address_of("net.pkt.head").unwrap(), PrimValue::Size(buf as u64));
+
+    let len = *ribbon.eval(&context, "net.pkt.head->len").unwrap().place().unwrap();
+    ribbon.write(&len, PrimValue::U16(12));
+    let kind = *ribbon.eval(&context, "(*(struct hdr *)&net.pkt.buf[4]).kind").unwrap().place().unwrap();
+    ribbon.write(&kind, PrimValue::U8(3));
+
+    for source in ["sizeof(struct hdr)", "_Alignof(struct packet)", "offsetof(struct packet, buf[4])", "sizeof *net.pkt.head"] {
+        println!("{source} = {}", ribbon.eval(&context, source).unwrap().integer().unwrap());
+    }
+
+    ribbon.save_svg("expr.svg", spec, true, true);
+}
@@ -0,0 +1,59 @@
+use illustruct::{
+    kind::{
+        Kind,
+        primitive::{Primitive, PrimValue},
+        array::Array,
+        composite::Composite,
+        reference::{Reference, Mode},
+    },
+    block_draw::BlockDrawSpec,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+fn main() {
+    use Primitive::*;
+
+    let uint32_t = Kind::from(U32);
+    let uint8_t = Kind::from(U8);
+    let circle = Kind::from(Composite::product("Circle", vec![uint32_t.field_named("r")]));
+
+    let items = Kind::from(Reference::slice(Mode::Ref, &uint32_t));
+    let name = Kind::from(Reference::str(Mode::Ref, &uint8_t));
+    let shape = Kind::from(Reference::dyn_trait(Mode::Box, "Shape", &circle));
+    let raw = Kind::from(Reference::slice(Mode::Ptr, &uint8_t));
+
+    let values = Kind::from(Array::new(&uint32_t, 3));
+    let text = Kind::from(Array::new(&uint8_t, 4));
+
+    let spec = &BlockDrawSpec::default();
+
+    let mut ribbon = MemRibbon::new(0x1000)
+        .target(Target::lp64())
+        .symbol(0x5000, "<Circle as Shape>::vtable")
+        .span("view", vec![
+            items.field_named("items"),
+            name.field_named("name"),
+            shape.field_named("shape"),
+            raw.field_named("raw"),
+        ])
+        .span("values", vec![values.field_named("values")])
+        .span("text", vec![text.field_named("text")]);
+
+    for (address, word) in [
+        (0x1000, 0x1040), (0x1008, 3),
+        (0x1010, 0x104c), (0x1018, 4),
+        (0x1020, 0x2000), (0x1028, 0x5000),
+        (0x1030, 0x104c), (0x1038, 2),
+    ] {
+        ribbon.write_at(address, PrimValue::Size(word));
+    }
+    for (index, value) in [1, 1, 2].into_iter().enumerate() {
+        ribbon.set(&format!("view.items[{index}]"), PrimValue::U32(value)).unwrap();
+    }
+    for (index, byte) in b"fib!".iter().enumerate() {
+        ribbon.set(&format!("view.name[{index}]"), PrimValue::U8(*byte)).unwrap();
+    }
+
+    ribbon.save_svg("fat_ptr.svg", spec, true, true);
+}
@@ -0,0 +1,54 @@
+use illustruct::{
+    kind::{
+        Kind,
+        primitive::{Primitive, PrimValue},
+        composite::Composite,
+        array::Array,
+    },
+    block_draw::BlockDrawSpec,
+    mem_ribbon::MemRibbon,
+};
+
+fn main() {
+    use Primitive::*;
+
+    let uint8_t = Kind::from(U8);
+    let uint16_t = Kind::from(U16);
+
+    // struct msg { uint16_t len; uint16_t data[]; };
+    let data = Kind::from(Array::flexible(&uint16_t));
+    let msg = Kind::from(Composite::product(
+        "msg",
+        vec![
+            uint16_t.field_named("len"),
+            data.field_named("data"),
+        ],
+    ));
+
+    // A packet whose payload length is read from its own header.
+    let payload = Kind::from(Array::counted(&uint8_t, "size"));
+
+    let spec = &BlockDrawSpec::default();
+
+    let mut ribbon = MemRibbon::new(0)
+        .span("m", vec![msg.field_named("msg")])
+        .flex(3)
+        .span("packet", vec![
+            uint8_t.field_named("kind"),
+            uint8_t.field_named("size"),
+            payload.field_named("payload"),
+        ]);
+
+    ribbon.set("m.msg.len", PrimValue::U16(3)).unwrap();
+    for (index, value) in [10, 20, 30].into_iter().enumerate() {
+        ribbon.set(&format!("m.msg.data[{index}]"), PrimValue::U16(value)).unwrap();
+    }
+
+    ribbon.set("packet.kind", PrimValue::U8(7)).unwrap();
+    ribbon.set("packet.size", PrimValue::U8(4)).unwrap();
+    for index in 0..4 {
+        ribbon.set(&format!("packet.payload[{index}]"), PrimValue::U8(index as u8 + 1)).unwrap();
+    }
+
+    ribbon.save_svg("flex.svg", spec, true, true);
+}
@@ -0,0 +1,46 @@
+use illustruct::{
+    kind::primitive::{Primitive, PrimValue},
+    block_draw::BlockDrawSpec,
+    context::Context,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+fn main() {
+    let context = Context::new();
+    let half = context.add(Primitive::F16);
+    let brain = context.add(Primitive::BF16);
+    let float = context.add(Primitive::F32);
+    let double = context.add(Primitive::F64);
+    let long_double = context.add(Primitive::F80);
+    let quad = context.add(Primitive::F128);
+
+    let spec = &BlockDrawSpec::default();
+
+    let mut ribbon = MemRibbon::new(0x1000)
+        .target(Target::lp64())
+        .break_down_floats()
+        .span("floats", vec![
+            half.field_named("h"),
+            brain.field_named("b"),
+            float.field_named("tiny"),
+            double.field_named("d"),
+            long_double.field_named("ld"),
+            quad.field_named("q"),
+        ]);
+
+    let values = [
+        ("floats.h", Primitive::F16, 65504.0),
+        ("floats.b", Primitive::BF16, -0.0),
+        ("floats.tiny", Primitive::F32, 1.0e-40),
+        ("floats.d", Primitive::F64, f64::NAN),
+        ("floats.ld", Primitive::F80, -6.0),
+        ("floats.q", Primitive::F128, f64::INFINITY),
+    ];
+
+    for (access, prim, value) in values {
+        ribbon.set(access, PrimValue::float(prim, value).unwrap()).unwrap();
+    }
+
+    ribbon.save_svg("floats.svg", spec, true, true);
+}
@@ -0,0 +1,48 @@
+use illustruct::{
+    kind::{
+        Kind,
+        primitive::{Primitive, PrimValue},
+        composite::Composite,
+        reference::{Reference, Mode},
+        function::Function,
+    },
+    block_draw::BlockDrawSpec,
+    mem_ribbon::MemRibbon,
+};
+
+fn main() {
+    use Primitive::*;
+
+    let int = Kind::from(I32);
+    let size_t = Kind::from(Size);
+    let char_ = Kind::from(Char);
+    let buf = Kind::from(Reference::new(Mode::Ptr, &char_));
+    let device = Kind::from(Composite::product("device", vec![]));
+    let dev_ptr = Kind::from(Reference::new(Mode::Ptr, &device));
+
+    let open = Kind::from(Function::new(vec![&dev_ptr], Some(&int)));
+    let read = Kind::from(Function::new(vec![&dev_ptr, &buf, &size_t], Some(&int)));
+    let release = Kind::from(Function::new(vec![&dev_ptr], None));
+
+    let ops = Kind::from(Composite::product(
+        "dev_ops",
+        vec![
+            open.field_named("open"),
+            read.field_named("read"),
+            release.field_named("release"),
+        ],
+    ));
+
+    let spec = &BlockDrawSpec::default();
+
+    let mut ribbon = MemRibbon::new(0x1000)
+        .symbol(0x4010, "uart_open")
+        .symbol(0x4080, "uart_read")
+        .span("uart", vec![ops.field_named("ops")]);
+
+    ribbon.set("uart.ops.open", PrimValue::Size(0x4010)).unwrap();
+    ribbon.set("uart.ops.read", PrimValue::Size(0x4080)).unwrap();
+    ribbon.set("uart.ops.release", PrimValue::Size(0x40f4)).unwrap();
+
+    ribbon.save_svg("fnptr.svg", spec, true, true);
+}
@@ -0,0 +1,44 @@
+use illustruct::{
+    kind::{
+        primitive::Primitive,
+        composite,
+        reference::{self, Reference},
+    },
+    block_draw::BlockDrawSpec,
+    context::Context,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+fn main() {
+    let context = Context::new();
+    let uint8_t = context.define("uint8_t", Primitive::U8).unwrap();
+    let double = context.define("double", Primitive::F64).unwrap();
+
+    context.generic("Pair", composite::Mode::Product, &["A", "B"], |_, _, args| vec![
+        args[0].field_named("first"),
+        args[1].field_named("second"),
+    ]).unwrap();
+    context.generic("Node", composite::Mode::Product, &["T"], |context, this, args| vec![
+        args[0].field_named("value"),
+        context.add(Reference::new(reference::Mode::Ptr, this)).field_named("next"),
+    ]).unwrap();
+
+    let wide = context.instantiate("Pair", &[uint8_t, double]).unwrap();
+    let narrow = context.instantiate("Pair", &[uint8_t, uint8_t]).unwrap();
+    let node = context.instantiate("Node", &[double]).unwrap();
+
+    let spec = &BlockDrawSpec::default();
+
+    let ribbon = MemRibbon::new(0x1000)
+        .target(Target::lp64())
+        .span("pairs", vec![
+            wide.field_named("wide"),
+            narrow.field_named("narrow"),
+        ])
+        .span("list", vec![
+            node.field_named("head"),
+        ]);
+
+    ribbon.save_svg("generics.svg", spec, true, true);
+}
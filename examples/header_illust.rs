@@ -0,0 +1,40 @@
+use illustruct::{
+    kind::primitive::PrimValue,
+    block_draw::BlockDrawSpec,
+    context::Context,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+const HEADER: &str = r"
+    #include <stdint.h>
+
+    typedef struct packet {
+        uint16_t length;
+        unsigned urgent : 1, kind : 3;
+        union { uint32_t word; uint8_t bytes[4]; } payload;
+        struct packet *next;
+    } packet_t;
+
+    packet_t first;
+    int16_t *(*rows)[2];
+";
+
+fn main() {
+    let _target = Target::ilp32().enter();
+    let context = Context::new();
+    let objects = context.parse_c(HEADER).unwrap();
+
+    let spec = &BlockDrawSpec::default();
+
+    let mut ribbon = MemRibbon::new(0x2000).span("globals", objects);
+
+    ribbon.set("globals.first.length", PrimValue::U16(64)).unwrap();
+    ribbon.set("globals.first.urgent", PrimValue::U32(1)).unwrap();
+    ribbon.set("globals.first.kind", PrimValue::U32(5)).unwrap();
+    ribbon.set("globals.first.payload.word", PrimValue::U32(0xC0A8_0001)).unwrap();
+    ribbon.write_at(0x200C, PrimValue::Size(0));
+    ribbon.write_at(0x2010, PrimValue::Size(0x2000));
+
+    ribbon.save_svg("header.svg", spec, true, true);
+}
@@ -0,0 +1,34 @@
+use illustruct::{
+    kind::{
+        primitive::{Primitive, PrimValue},
+        array::Array,
+    },
+    block_draw::BlockDrawSpec,
+    context::Context,
+    mem_ribbon::MemRibbon,
+};
+
+fn main() {
+    let context = Context::new();
+    let int = context.define("int", Primitive::I32).unwrap();
+    let scores = context.add(Array::new(int, 4));
+
+    let spec = &BlockDrawSpec::default();
+
+    let mut ribbon = MemRibbon::new(0x1000)
+        .allow_out_of_bounds()
+        .span("player", vec![
+            scores.field_named("scores"),
+            int.field_named("lives"),
+        ]);
+
+    ribbon.set("player.lives", PrimValue::I32(3)).unwrap();
+    for i in 0..4 {
+        ribbon.set(&format!("player.scores[{i}]"), PrimValue::I32(100 * i)).unwrap();
+    }
+
+    // An off-by-one loop writes its last score over `lives`.
+    ribbon.set("player.scores[4]", PrimValue::I32(400)).unwrap();
+
+    ribbon.save_svg("overrun.svg", spec, true, true);
+}
@@ -0,0 +1,41 @@
+use illustruct::{
+    kind::{
+        Kind,
+        primitive::Primitive,
+        composite::Composite,
+        reference::{self, Reference},
+    },
+    block_draw::BlockDrawSpec,
+    analysis::CACHELINE,
+    target::Target,
+};
+
+fn main() {
+    use Primitive::*;
+
+    let _target = Target::lp64().enter();
+
+    let bool_ = Kind::from(Bool);
+    let uint16_t = Kind::from(U16);
+    let int64_t = Kind::from(I64);
+    let char_ = Kind::from(Char);
+    let name = Kind::from(Reference::new(reference::Mode::Ptr, &char_));
+
+    let task = Composite::product(
+        "task",
+        vec![
+            bool_.field_named("ready"),
+            int64_t.field_named("deadline"),
+            uint16_t.field_named("priority"),
+            name.field_named("name"),
+            bool_.field_named("pinned"),
+        ],
+    );
+
+    let spec = &BlockDrawSpec::default();
+
+    println!("{}", task.analyze(CACHELINE));
+    println!("{}", task.reordered().analyze(CACHELINE));
+
+    task.save_reordering_svg("pahole.svg", spec);
+}
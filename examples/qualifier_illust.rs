@@ -0,0 +1,55 @@
+use illustruct::{
+    kind::{
+        Kind,
+        primitive::{Primitive, PrimValue},
+        composite::Composite,
+        reference::{self, Reference},
+        qualified::Qualified,
+    },
+    block_draw::BlockDrawSpec,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+fn main() {
+    use Primitive::*;
+
+    let uint8_t = Kind::from(U8);
+    let uint32_t = Kind::from(U32);
+    let int64_t = Kind::from(I64);
+
+    let const_u8 = Kind::from(Qualified::constant(&uint8_t));
+    let to_const = Kind::from(Reference::new(reference::Mode::Ptr, &const_u8));
+    let u8_ptr = Kind::from(Reference::new(reference::Mode::Ptr, &uint8_t));
+    let const_ptr = Kind::from(Qualified::constant(&u8_ptr));
+    let status = Kind::from(Qualified::volatile(&uint32_t));
+    let counter = Kind::from(Qualified::atomic(&int64_t));
+
+    let device = Kind::from(Composite::product(
+        "device",
+        vec![
+            to_const.field_named("name"),
+            const_ptr.field_named("buffer"),
+            status.field_named("status"),
+            counter.field_named("count"),
+        ],
+    ));
+
+    let spec = &BlockDrawSpec::default();
+
+    // On i386, `_Atomic` raises the alignment of the 8-byte counter from 4 to 8.
+    let mut ribbon = MemRibbon::new(0x100)
+        .target(Target::i386())
+        .span("dev", vec![device.field_named("d")]);
+
+    let buffer = ribbon.at("dev.d.buffer");
+    ribbon.write(&buffer, PrimValue::Size(0x200));
+    ribbon.set("dev.d.status", PrimValue::U32(1)).unwrap();
+    ribbon.set("dev.d.count", PrimValue::I64(42)).unwrap();
+
+    if let Err(err) = ribbon.set("dev.d.buffer", PrimValue::Size(0x300)) {
+        println!("{err}");
+    }
+
+    ribbon.save_svg("qualifier.svg", spec, true, true);
+}
@@ -0,0 +1,45 @@
+use illustruct::{
+    kind::{
+        Kind,
+        primitive::Primitive,
+        composite::{Composite, Order, Repr},
+    },
+    block_draw::BlockDrawSpec,
+    mem_ribbon::MemRibbon,
+};
+
+fn main() {
+    use Primitive::*;
+
+    let char_   = Kind::from(Char);
+    let int32_t = Kind::from(I32);
+    let int16_t = Kind::from(I16);
+
+    let spec = &BlockDrawSpec::default();
+
+    let reprs = [
+        ("c", Repr::default()),
+        ("rust", Repr { order: Order::Rust, ..Repr::default() }),
+        ("packed", Repr { packed: Some(1), ..Repr::default() }),
+        ("packed2", Repr { packed: Some(2), ..Repr::default() }),
+        ("align16", Repr { align: Some(16), ..Repr::default() }),
+    ];
+
+    let headers: Vec<(&str, Kind)> = reprs.into_iter()
+        .map(|(name, repr)| (name, Kind::from(Composite::product(
+            format!("Header_{name}"),
+            vec![
+                char_.field_named("kind"),
+                int32_t.field_named("length"),
+                char_.field_named("flags"),
+                int16_t.field_named("port"),
+            ],
+        ).repr(repr))))
+        .collect();
+
+    for (name, header) in &headers {
+        MemRibbon::new(0)
+            .span("pair", vec![header.field_named("first"), char_.field_named("tail")])
+            .save_svg(format!("repr_{name}.svg"), spec, false, true);
+    }
+}
@@ -0,0 +1,41 @@
+use illustruct::{
+    kind::primitive::PrimValue,
+    block_draw::BlockDrawSpec,
+    context::Context,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+const ITEMS: &str = r"
+    #[repr(u8)]
+    enum Kind { Ping = 1, Pong, Close = 8 }
+
+    #[repr(C)]
+    struct Frame<'a> {
+        kind: Kind,
+        id: u16,
+        body: &'a [u8],
+    }
+
+    #[repr(C, packed)]
+    struct Wire(Kind, u16, [u8; 3]);
+";
+
+fn main() {
+    let _target = Target::lp64().enter();
+    let context = Context::new();
+    let items = context.parse_rust(ITEMS).unwrap();
+
+    let spec = &BlockDrawSpec::default();
+
+    let mut ribbon = MemRibbon::new(0x1000)
+        .span("frame", vec![items[1].field_named("f")])
+        .span("wire", vec![items[2].field_named("w")]);
+
+    ribbon.set("frame.f.kind", PrimValue::U8(2)).unwrap();
+    ribbon.set("frame.f.id", PrimValue::U16(7)).unwrap();
+    ribbon.set("wire.w.0", PrimValue::U8(8)).unwrap();
+    ribbon.set("wire.w.1", PrimValue::U16(7)).unwrap();
+
+    ribbon.save_svg("rust.svg", spec, true, true);
+}
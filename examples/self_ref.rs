@@ -1,20 +1,21 @@
 use illustruct::{
     kind::{
-        Kind,
         primitive::{Primitive, PrimValue},
-        composite::{Field, Composite},
+        composite::{self, Field, Composite},
         reference::{self, Reference},
         Alias,
     },
     block_draw::{BlockDrawSpec, util::Vec2},
     mem_ribbon::MemRibbon,
+    context::Context,
 };
 
 #[allow(unused_variables)]
 fn main() {
-    let uint8_t  = Kind::from(Primitive::U8);
-    let uint16_t = Kind::from(Primitive::U16);
-    let float    = Kind::from(Primitive::F32);
+    let context = Context::new();
+    let uint8_t  = context.define("uint8_t", Primitive::U8).unwrap();
+    let uint16_t = context.define("uint16_t", Primitive::U16).unwrap();
+    let float    = context.define("float", Primitive::F32).unwrap();
 
     let spec = BlockDrawSpec {
         char_dims:  Vec2::new(5.0, 8.0),
@@ -27,14 +28,16 @@ fn main() {
         chamfer_size: 6.0
     };
 
-    let data = Kind::from(Alias::new("data", &float));
-    let link = Kind::from(Composite::product("link", Vec::new()));
-    let link_ptr = Kind::from(Reference::new(reference::Mode::Ptr, &link));
-    link.add_field("data", &data);
-    link.add_field("next", &link_ptr);
+    let data = context.define("data", Alias::new("data", float)).unwrap();
+    let link = context.declare("link", composite::Mode::Product);
+    let link_ptr = context.add(Reference::new(reference::Mode::Ptr, link));
+    context.define("link", Composite::product("link", vec![
+        data.field_named("data"),
+        link_ptr.field_named("next"),
+    ])).unwrap();
 
     let mut ribbon = MemRibbon::new(0x0)
-        .span("span", std::iter::repeat(&link)
+        .span("span", std::iter::repeat(link)
             .map(Field::anon)
             .take(4)
             .collect(),
@@ -0,0 +1,42 @@
+use illustruct::{
+    kind::{
+        primitive::{Primitive, PrimValue},
+        array::Array,
+        reference::{Reference, Mode},
+        qualified::Qualified,
+    },
+    block_draw::BlockDrawSpec,
+    context::Context,
+    mem_ribbon::MemRibbon,
+};
+
+fn main() {
+    let context = Context::new();
+    let char_ = context.add(Primitive::Char);
+    let uint8_t = context.add(Primitive::U8);
+    let const_char = context.add(Qualified::constant(char_));
+
+    let name = context.add(Array::new(char_, 12));
+    let tag = context.add(Array::new(uint8_t, 4));
+    let text_ptr = context.add(Reference::new(Mode::Ptr, const_char));
+
+    let spec = &BlockDrawSpec::default();
+
+    let mut ribbon = MemRibbon::new(0x1000)
+        .render_strings()
+        .span("user", vec![
+            name.field_named("name"),
+            tag.field_named("tag"),
+        ])
+        .span("refs", vec![
+            text_ptr.field_named("first"),
+            text_ptr.field_named("tagp"),
+        ]);
+
+    ribbon.set_bytes("user.name", "Zoë → ok\0".as_bytes()).unwrap();
+    ribbon.set_bytes("user.tag", b"ABCD").unwrap();
+    ribbon.write_at(0x1010, PrimValue::Size(0x1000));
+    ribbon.write_at(0x1014, PrimValue::Size(0x100C));
+
+    ribbon.save_svg("strings.svg", spec, true, true);
+}
@@ -0,0 +1,56 @@
+use illustruct::{
+    kind::{
+        Kind,
+        primitive::{Primitive, PrimValue},
+        composite::Field,
+        reference::{self, Reference},
+        tagged::{Tagged, Variant},
+    },
+    block_draw::BlockDrawSpec,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+fn main() {
+    use Primitive::*;
+
+    let bool_    = Kind::from(Bool);
+    let uint8_t  = Kind::from(U8);
+    let uint32_t = Kind::from(U32);
+    let ref_u32  = Kind::from(Reference::new(reference::Mode::Ref, &uint32_t));
+
+    let spec = &BlockDrawSpec::default();
+
+    let option_bool = Kind::from(Tagged::option(&bool_));
+    let option_ref  = Kind::from(Tagged::option(&ref_u32));
+    let option_u32  = Kind::from(Tagged::option(&uint32_t));
+
+    let message = Kind::from(Tagged::new("Message", vec![
+        Variant::unit("Quit"),
+        Variant::new("Move", vec![
+            Field::new("x", &uint8_t),
+            Field::new("y", &uint32_t),
+        ]),
+        Variant::new("Write", vec![Field::new(0, &ref_u32)]),
+    ]));
+
+    let mut ribbon = MemRibbon::new(0x1000)
+        .target(Target::lp64())
+        .span("options", vec![
+            option_bool.field_named("flag"),
+            option_ref.field_named("next"),
+            option_u32.field_named("count"),
+        ])
+        .span("events", vec![message.field_named("msg")]);
+
+    ribbon.set("options.flag.Some.0", PrimValue::Bool(1)).unwrap();
+    ribbon.write_at(0x1008, PrimValue::Size(0x1000));
+    ribbon.set_variant("options.count", "None").unwrap();
+    ribbon.set_variant("events.msg", "Move").unwrap();
+    ribbon.set("events.msg.Move.x", PrimValue::U8(3)).unwrap();
+    ribbon.set("events.msg.Move.y", PrimValue::U32(4)).unwrap();
+
+    println!("events.msg is {}", ribbon.variant("events.msg").unwrap());
+
+    ribbon.save_svg("tagged.svg", spec, true, true);
+}
@@ -0,0 +1,65 @@
+use illustruct::{
+    kind::{
+        Kind,
+        primitive::{Primitive, PrimValue},
+        composite::Composite,
+        reference::{self, Reference},
+    },
+    block_draw::BlockDrawSpec,
+    mem_ribbon::MemRibbon,
+    target::{Target, Endian},
+};
+
+#[allow(unused_variables)]
+fn main() {
+    use Primitive::*;
+
+    let char_    = Kind::from(Char);
+    let uint32_t = Kind::from(U32);
+    let long     = Kind::from(Long);
+    let double   = Kind::from(F64);
+    let int64_t  = Kind::from(I64);
+    let name     = Kind::from(Reference::new(reference::Mode::Ptr, &char_));
+
+    let spec = &BlockDrawSpec::default();
+
+    let record = Kind::from(Composite::product(
+        "Record",
+        vec![
+            char_.field_named("tag"),
+            double.field_named("weight"),
+            name.field_named("name"),
+            long.field_named("count"),
+            int64_t.field_named("id"),
+        ],
+    ));
+
+    let targets = [
+        Target::ilp32(),
+        Target::i386(),
+        Target::lp64(),
+        Target::llp64(),
+        Target::avr(),
+    ];
+
+    for target in targets {
+        let file_name = format!("record_{}.svg", target.name.to_lowercase());
+
+        MemRibbon::new(0)
+            .target(target)
+            .span("record", vec![record.field_named("rec")])
+            .save_svg(file_name, spec, false, true);
+    }
+
+    for endian in [Endian::Little, Endian::Big] {
+        let file_name = format!("word_{endian}.svg");
+
+        let mut ribbon = MemRibbon::new(0)
+            .endian(endian)
+            .mark_byte_order()
+            .span("word", vec![uint32_t.field_named("magic")]);
+
+        ribbon.write_at(0, PrimValue::U32(0xCAFEF00D));
+        ribbon.save_svg(file_name, spec, true, true);
+    }
+}
@@ -0,0 +1,41 @@
+use illustruct::{
+    kind::{primitive::{Primitive, PrimValue}, vector::Vector},
+    block_draw::BlockDrawSpec,
+    context::Context,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+fn main() {
+    let context = Context::new();
+    let float = context.add(Primitive::F32);
+    let int = context.add(Primitive::I32);
+    let uint8_t = context.add(Primitive::U8);
+
+    let m128 = context.add(Vector::new("__m128", float, 4));
+    let neon = context.add(Vector::new("int32x4_t", int, 4));
+    let bytes = context.add(Vector::new("u8x8", uint8_t, 8));
+
+    let spec = &BlockDrawSpec::default();
+
+    let mut ribbon = MemRibbon::new(0x1000)
+        .target(Target::lp64())
+        .span("simd", vec![
+            uint8_t.field_named("tag"),
+            m128.field_named("xyzw"),
+            neon.field_named("counts"),
+            bytes.field_named("mask"),
+        ]);
+
+    for (lane, value) in [1.0, -0.5, 2.25, 0.0].into_iter().enumerate() {
+        ribbon.set(&format!("simd.xyzw[{lane}]"), PrimValue::F32(value)).unwrap();
+    }
+    for lane in 0..4i32 {
+        ribbon.set(&format!("simd.counts[{lane}]"), PrimValue::I32(lane * 10)).unwrap();
+    }
+    for lane in 0..8 {
+        ribbon.set(&format!("simd.mask[{lane}]"), PrimValue::U8(1 << lane)).unwrap();
+    }
+
+    ribbon.save_svg("vectors.svg", spec, true, true);
+}
@@ -0,0 +1,44 @@
+use illustruct::{
+    kind::primitive::{Primitive, PrimValue},
+    block_draw::BlockDrawSpec,
+    context::Context,
+    mem_ribbon::MemRibbon,
+    target::Target,
+};
+
+fn main() {
+    let context = Context::new();
+    let wide = context.add(Primitive::WChar);
+    let utf16 = context.add(Primitive::Char16);
+    let utf32 = context.add(Primitive::Char32);
+    let rust_char = context.add(Primitive::RustChar);
+    let diff = context.add(Primitive::PtrDiff);
+    let uintptr = context.add(Primitive::UIntPtr);
+    let huge = context.add(Primitive::I128);
+
+    let spec = &BlockDrawSpec::default();
+
+    let mut ribbon = MemRibbon::new(0x1000)
+        .target(Target::lp64())
+        .span("wide", vec![
+            wide.field_named("w"),
+            utf16.field_named("u16"),
+            utf32.field_named("u32"),
+            rust_char.field_named("ok"),
+            rust_char.field_named("bad"),
+            diff.field_named("diff"),
+            uintptr.field_named("addr"),
+            huge.field_named("big"),
+        ]);
+
+    ribbon.set("wide.w", PrimValue::WChar('Ω' as u32)).unwrap();
+    ribbon.set("wide.u16", PrimValue::Char16(0xD83D)).unwrap();
+    ribbon.set("wide.u32", PrimValue::Char32('🦀' as u32)).unwrap();
+    ribbon.set("wide.ok", PrimValue::RustChar('é' as u32)).unwrap();
+    ribbon.set("wide.bad", PrimValue::RustChar(0x11_0000)).unwrap();
+    ribbon.set("wide.diff", PrimValue::PtrDiff(-16)).unwrap();
+    ribbon.set("wide.addr", PrimValue::UIntPtr(0x1000)).unwrap();
+    ribbon.set("wide.big", PrimValue::I128(-(1 << 100))).unwrap();
+
+    ribbon.save_svg("wide.svg", spec, true, true);
+}
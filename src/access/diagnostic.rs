@@ -0,0 +1,233 @@
+use std::{fmt, ops::Range};
+
+use unicode_width::UnicodeWidthStr;
+
+/// An error in an access string or declaration, pointing at the characters
+/// of the source it concerns.
+///
+/// It is displayed as the message, the offending line with carets under
+/// `range`, and any suggestions:
+///
+/// ```text
+/// Attempted to access non-existant field "nxt" in composite type nodes[2]
+///  1 | span.nodes[2]->nxt
+///    |               ^^^^^
+///    = help: did you mean `next`?
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub source: String,
+    /// Characters of `source` the diagnostic points at, which may end one
+    /// past the last character when input ended too early.
+    pub range: Range<usize>,
+    pub message: String,
+    /// Close alternatives to the text at `range`, such as field names.
+    pub suggestions: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(source: &str, range: Range<usize>, message: impl ToString) -> Self {
+        Self {
+            source: source.to_string(),
+            range,
+            message: message.to_string(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    /// A diagnostic for a parse error, saying what the parser expected and what it found instead.
+    pub fn syntax(source: &str, err: &pom::Error) -> Self {
+        let (position, message) = match err {
+            pom::Error::Expect { message, position, .. } => {
+                let expected = message.strip_prefix("Expect ").unwrap_or(message);
+                let found = match source.chars().nth(*position) {
+                    Some(ch) => format!("`{}`", ch.escape_debug()),
+                    None => "end of input".to_string(),
+                };
+                (*position, format!("expected {expected}, found {found}"))
+            }
+            pom::Error::Mismatch { message, position }
+            | pom::Error::Conversion { message, position }
+            | pom::Error::Custom { message, position, .. } => (*position, message.clone()),
+            pom::Error::Incomplete => (source.chars().count(), "unexpected end of input".to_string()),
+        };
+
+        Self::new(source, position..position + 1, message)
+    }
+
+    /// Suggests the `candidates` closest to `name`, as for a misspelt field
+    /// name, keeping the order of equally close ones.
+    pub fn suggest<'a>(mut self, name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Self {
+        let limit = (name.chars().count() / 3).max(1);
+        let mut close: Vec<_> = candidates.into_iter()
+            .map(|candidate| (distance(name, candidate), candidate))
+            .filter(|(distance, _)| (1..=limit).contains(distance))
+            .collect();
+
+        close.sort_by_key(|(distance, _)| *distance);
+        self.suggestions = close.into_iter().take(3).map(|(_, candidate)| candidate.to_string()).collect();
+        self
+    }
+}
+
+/// The edit distance between `a` and `b` in characters, where swapping two
+/// neighbouring characters counts as a single edit.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in table[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (table[i - 1][j] + 1)
+                .min(table[i][j - 1] + 1)
+                .min(table[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(table[i - 2][j - 2] + 1);
+            }
+            table[i][j] = best;
+        }
+    }
+
+    table[a.len()][b.len()]
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { source, range, message, suggestions } = self;
+
+        // Only the line holding the start of the range is shown, which matters for multi-line declarations.
+        let mut line_start = 0;
+        let mut line_no = 1;
+        for (index, ch) in source.chars().enumerate().take(range.start) {
+            if ch == '\n' {
+                line_start = index + 1;
+                line_no += 1;
+            }
+        }
+        let line: String = source.chars().skip(line_start).take_while(|ch| *ch != '\n').collect();
+        let column = range.start - line_start;
+        let before: String = line.chars().take(column).collect();
+        let marked: String = line.chars().skip(column).take(range.len()).collect();
+
+        let gutter = line_no.to_string().len();
+        writeln!(f, "{message}")?;
+        writeln!(f, " {line_no} | {line}")?;
+        write!(f, " {:gutter$} | {}{}", "", " ".repeat(before.width()), "^".repeat(marked.width().max(1)))?;
+
+        if let Some((last, rest)) = suggestions.split_last() {
+            let names: Vec<_> = rest.iter().map(|name| format!("`{name}`")).collect();
+            match names.is_empty() {
+                true => write!(f, "\n {:gutter$} = help: did you mean `{last}`?", "")?,
+                false => write!(f, "\n {:gutter$} = help: did you mean {} or `{last}`?", "", names.join(", "))?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        access,
+        context::{self, Context},
+        kind::primitive::PrimValue,
+        mem_ribbon::MemRibbon,
+        target::Target,
+    };
+
+    const HEADER: &str = "
+        struct node { int value; struct node *next; };
+        struct node head;
+        struct node *nodes[3];
+    ";
+
+    #[test]
+    fn syntax() {
+        let diagnostic = "span.nodes[2->nxt".parse::<access::Path>().err().unwrap();
+        assert_eq!(diagnostic.range, 12..13);
+        assert_eq!(diagnostic.to_string(), [
+            "expected `]`, found `-`",
+            " 1 | span.nodes[2->nxt",
+            "   |             ^",
+        ].join("\n"));
+
+        let diagnostic = "span.".parse::<access::Path>().err().unwrap();
+        assert_eq!((diagnostic.range, diagnostic.message.as_str()), (5..6, "expected field name, found end of input"));
+        assert!("span.nodes[2]->next!".parse::<access::Path>().is_err());
+
+        let context = Context::new();
+        let Err(context::Error::Syntax(diagnostic)) = context.parse_c("struct s {\n  int x\n};") else {
+            panic!("expected a syntax error");
+        };
+        assert_eq!(diagnostic.range, 19..20);
+        assert!(diagnostic.to_string().ends_with(" 3 | };\n   | ^"), "{diagnostic}");
+
+        let Err(context::Error::Syntax(diagnostic)) = context.parse_c("struct;") else {
+            panic!("expected a syntax error");
+        };
+        assert_eq!(diagnostic.message, "expected tag or `{`, found `;`");
+    }
+
+    #[test]
+    fn resolution() {
+        let _target = Target::lp64().enter();
+        let context = Context::new();
+        let mut ribbon = MemRibbon::new(0x100).target(Target::lp64())
+            .span("span", context.parse_c(HEADER).unwrap());
+        ribbon.write_at(0x120, PrimValue::Size(0x100));
+
+        assert_eq!(ribbon.resolve("span.nodes[2]->value").unwrap().address, 0x100);
+
+        let diagnostic = ribbon.resolve("span.nodes[2]->nxt").err().unwrap();
+        assert_eq!((diagnostic.range.clone(), diagnostic.suggestions.clone()), (13..18, vec!["next".to_string()]));
+        assert!(diagnostic.to_string().ends_with(&[
+            " 1 | span.nodes[2]->nxt",
+            "   |              ^^^^^",
+            "   = help: did you mean `next`?",
+        ].join("\n")));
+
+        let diagnostic = ribbon.resolve("spam.head").err().unwrap();
+        assert_eq!((diagnostic.range, diagnostic.suggestions), (0..4, vec!["span".to_string()]));
+
+        let diagnostic = ribbon.resolve("span.haed.value").err().unwrap();
+        assert_eq!((diagnostic.range, diagnostic.suggestions), (4..9, vec!["head".to_string()]));
+
+        let diagnostic = ribbon.resolve("span.nodes[7]").err().unwrap();
+        assert_eq!((diagnostic.range, diagnostic.suggestions), (10..13, Vec::<String>::new()));
+        assert!(diagnostic.message.starts_with("Index 7 out of bounds"));
+
+        let diagnostic = ribbon.set("span.haed.value", PrimValue::I32(1)).err().unwrap();
+        assert_eq!(diagnostic.suggestions, ["head"]);
+        assert_eq!(ribbon.set_bytes("span.head.value[", b"x").err().unwrap().range, 16..17);
+    }
+
+    #[test]
+    fn suggestions() {
+        assert_eq!(distance("nxt", "next"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("haed", "head"), 1);
+
+        let diagnostic = Diagnostic::new("s.lenght", 1..8, "unknown").suggest("lenght", ["height", "length", "left", "len"]);
+        assert_eq!(diagnostic.suggestions, ["length", "height"]);
+        assert!(Diagnostic::new("s.len", 1..5, "unknown").suggest("len", ["len"]).suggestions.is_empty());
+        assert!(diagnostic.to_string().ends_with("= help: did you mean `length` or `height`?"));
+    }
+}
@@ -0,0 +1,478 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    access::{self, Indirection, Path, PlaceValue, Trace, diagnostic::Diagnostic, parse::{self, TypeName}},
+    context::{self, Context},
+    kind::{Kind, CType, primitive::{Primitive, PrimValue}, reference::{Meta, Mode, Reference}},
+    mem_ribbon::MemRibbon,
+    target::Endian,
+};
+
+/// An access expression in C syntax, such as `(*s.p).x`, `&s.a[2]`,
+/// `((struct hdr *)s.buf)->len` or `offsetof(struct hdr, len)`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    /// A span name, which names a place once one of its fields is selected.
+    Name(String),
+    Integer(usize),
+    /// A `.field`, `->field` or `[index]` applied to an expression.
+    Member(Box<Expr>, Indirection),
+    Deref(Box<Expr>),
+    AddressOf(Box<Expr>),
+    /// A cast to a pointer or integer type.
+    Cast(TypeName, Box<Expr>),
+    SizeOf(Box<Expr>),
+    SizeOfType(TypeName),
+    AlignOf(TypeName),
+    /// The offset of the member reached by fields and indices from the start of a type.
+    OffsetOf(TypeName, Vec<Indirection>),
+}
+
+/// What an [`Expr`] evaluates to.
+#[derive(Clone, Copy)]
+pub enum Value<'kind> {
+    /// An object in memory, which can be read, written and have its address taken.
+    Place(PlaceValue<'kind>),
+    /// A pointer that is not stored in memory, such as `&s.x` or the result
+    /// of a cast, holding the address of the place it points to.
+    Address(PlaceValue<'kind>),
+    /// A size, alignment or offset.
+    Integer(usize),
+}
+
+impl<'kind> Value<'kind> {
+    pub fn place(&self) -> Option<&PlaceValue<'kind>> {
+        match self {
+            Value::Place(place) => Some(place),
+            _ => None,
+        }
+    }
+
+    /// The value as an integer, which is the address held by a pointer.
+    pub fn integer(&self) -> Option<usize> {
+        match self {
+            Value::Place(_) => None,
+            Value::Address(place) => Some(place.address),
+            Value::Integer(value) => Some(*value),
+        }
+    }
+
+    /// The integer stored in a place or held by a value, with arrays decaying to their address.
+    pub(crate) fn read(&self, ribbon: &MemRibbon<'kind>) -> Option<usize> {
+        let Value::Place(place) = self else {
+            return self.integer();
+        };
+
+        match place.kind.unqualified() {
+            Kind::Array(_) => Some(place.address),
+            Kind::Reference(Reference { meta: Meta::Thin, .. }) =>
+                Primitive::Size.parse_at(ribbon, place.address)?.as_int().map(|value| value as usize),
+            _ => ribbon.read(place)?.as_int().map(|value| value as usize),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error<'kind> {
+    Syntax(Diagnostic),
+    /// A type named in a cast, `sizeof`, `_Alignof` or `offsetof` could not be resolved.
+    Type(context::Error),
+    Access(access::Error<'kind>),
+    /// An operator applied to an operand it does not take, such as `&` on an integer.
+    Operand {
+        op: &'static str,
+        operand: String,
+    },
+}
+
+impl<'kind> From<access::Error<'kind>> for Error<'kind> {
+    fn from(err: access::Error<'kind>) -> Self {
+        Error::Access(err)
+    }
+}
+
+impl From<context::Error> for Error<'_> {
+    fn from(err: context::Error) -> Self {
+        Error::Type(err)
+    }
+}
+
+impl fmt::Display for Error<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax(err) => write!(f, "Invalid access expression: {err}"),
+            Error::Type(err) => err.fmt(f),
+            Error::Access(err) => err.fmt(f),
+            Error::Operand { op, operand } => write!(f, "Operation {op} undefined for {operand}"),
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = Diagnostic;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars = s.chars().collect::<Vec<_>>();
+        let parser = parse::access_expression();
+        parser.parse(&chars).map_err(|err| Diagnostic::syntax(s, &err))
+    }
+}
+
+impl Expr {
+    /// Evaluates the expression against `ribbon`, looking up the types it names in `context`.
+    pub fn eval<'kind>(
+        &self,
+        ribbon: &MemRibbon<'kind>,
+        context: &'kind Context<'kind>,
+    ) -> Result<Value<'kind>, Error<'kind>> {
+        let _target = ribbon.target.enter();
+        self.value(ribbon, context)
+    }
+
+    fn value<'kind>(
+        &self,
+        ribbon: &MemRibbon<'kind>,
+        context: &'kind Context<'kind>,
+    ) -> Result<Value<'kind>, Error<'kind>> {
+        let operand = |op, operand: &dyn fmt::Display| Error::Operand { op, operand: operand.to_string() };
+
+        Ok(match self {
+            Expr::Name(name) => return Err(access::Error::at(name, access::ErrorKind::DirectAccess).into()),
+            Expr::Integer(value) => Value::Integer(*value),
+            Expr::Member(base, Indirection::Field(field)) if matches!(**base, Expr::Name(_)) => {
+                let Expr::Name(span) = &**base else { unreachable!() };
+                Value::Place(ribbon.lookup(Path::new(span).field(field), false)?)
+            }
+            Expr::Member(base, indirection) => match (base.value(ribbon, context)?, indirection) {
+                (Value::Place(place), _) =>
+                    Value::Place(step(ribbon, &place, indirection.clone(), base)?),
+                (Value::Address(place), Indirection::Field(_)) =>
+                    return Err(operand(indirection.operator(), &format_args!("pointer to {}", place.kind))),
+                (Value::Address(place), _) =>
+                    Value::Place(follow(ribbon, context, &place, indirection.clone(), base)?),
+                (Value::Integer(_), _) => return Err(operand(indirection.operator(), &"an integer")),
+            },
+            Expr::Deref(inner) => match inner.value(ribbon, context)? {
+                Value::Place(place) => Value::Place(step(ribbon, &place, Indirection::Deref, inner)?),
+                Value::Address(place) => Value::Place(follow(ribbon, context, &place, Indirection::Deref, inner)?),
+                Value::Integer(_) => return Err(operand("*", &"an integer")),
+            },
+            Expr::AddressOf(inner) => match inner.value(ribbon, context)? {
+                Value::Place(place) if place.bits.is_some() => return Err(operand("&", &"a bitfield")),
+                Value::Place(place) => Value::Address(place),
+                _ => return Err(operand("&", &"a value that is not in memory")),
+            },
+            Expr::Cast(type_name, inner) => {
+                let kind = resolve(context, type_name)?;
+                let value = inner.value(ribbon, context)?;
+                let address = value.read(ribbon)
+                    .ok_or_else(|| operand("cast", &format_args!("{inner} to {kind}")))?;
+
+                match kind.unqualified() {
+                    Kind::Reference(Reference { meta: Meta::Thin, kind: pointee, .. }) => Value::Address(PlaceValue {
+                        kind: pointee,
+                        address,
+                        bits: None,
+                        qualifiers: pointee.qualifiers(),
+                    }),
+                    kind if kind.primitive().is_some() => {
+                        // Truncated to the target type, and sign-extended again when it is signed.
+                        let prim = kind.primitive().unwrap();
+                        let bytes = (address as u64).to_le_bytes();
+                        let size = (prim.size_of() as usize).min(bytes.len());
+                        let value = PrimValue::from_bytes(prim, &bytes[..size], Endian::Little).as_int()
+                            .ok_or_else(|| operand("cast", &format_args!("{inner} to {kind}")))?;
+                        Value::Integer(value as usize)
+                    }
+                    kind => return Err(operand("cast", &format_args!("{inner} to {kind}"))),
+                }
+            }
+            Expr::SizeOf(inner) => Value::Integer(inner.kind_of(ribbon, context)?.size_of() as usize),
+            Expr::SizeOfType(type_name) => Value::Integer(resolve(context, type_name)?.size_of() as usize),
+            Expr::AlignOf(type_name) => Value::Integer(resolve(context, type_name)?.align_of() as usize),
+            Expr::OffsetOf(type_name, designator) => {
+                let kind = resolve(context, type_name)?;
+                let start = PlaceValue { kind, address: 0, bits: None, qualifiers: Default::default() };
+                let member = designator.iter()
+                    .try_fold(start, |place, indirection| step(ribbon, &place, indirection.clone(), self))?;
+
+                if member.bits.is_some() {
+                    return Err(operand("offsetof", &"a bitfield"));
+                }
+                Value::Integer(member.address)
+            }
+        })
+    }
+
+    /// The span that the pointer this expression evaluates to is derived from:
+    /// the span of the object whose address is taken, or the one recorded for
+    /// the pointer it is read or cast from. Integers cast to pointers have none.
+    pub(crate) fn provenance<'kind>(&self, ribbon: &MemRibbon<'kind>, context: &'kind Context<'kind>) -> Option<String> {
+        match self {
+            Expr::AddressOf(inner) => inner.object_span(ribbon, context),
+            Expr::Cast(_, inner) => inner.provenance(ribbon, context),
+            _ => {
+                let place = *self.value(ribbon, context).ok()?.place()?;
+                match place.kind.unqualified() {
+                    Kind::Array(_) => self.object_span(ribbon, context),
+                    _ => ribbon.provenance.get(&place.address).cloned(),
+                }
+            }
+        }
+    }
+
+    /// The span of the object this expression designates, which is that of
+    /// the pointer or array it is reached through.
+    fn object_span<'kind>(&self, ribbon: &MemRibbon<'kind>, context: &'kind Context<'kind>) -> Option<String> {
+        match self {
+            Expr::Name(name) => Some(name.clone()),
+            Expr::Member(base, Indirection::Field(_)) => base.object_span(ribbon, context),
+            Expr::Member(base, _) | Expr::Deref(base) => base.provenance(ribbon, context),
+            _ => None,
+        }
+    }
+
+    /// The kind of the expression, found without reading memory since the
+    /// operand of `sizeof` is never evaluated.
+    fn kind_of<'kind>(
+        &self,
+        ribbon: &MemRibbon<'kind>,
+        context: &'kind Context<'kind>,
+    ) -> Result<&'kind Kind<'kind>, Error<'kind>> {
+        let field = |kind: &'kind Kind<'kind>, name: &str, op| match kind.underlying() {
+            Kind::Composite(comp) => comp.type_of(name).ok_or_else(|| access::Error::at(
+                kind,
+                access::ErrorKind::SubField { name: name.into() },
+            ).into()),
+            _ => Err(Error::Operand { op, operand: kind.to_string() }),
+        };
+
+        match self {
+            Expr::Name(name) => Err(access::Error::at(name, access::ErrorKind::DirectAccess).into()),
+            Expr::Integer(_) => Ok(context.add(Primitive::I32)),
+            Expr::Member(base, Indirection::Field(name)) if matches!(**base, Expr::Name(_)) => {
+                let Expr::Name(span) = &**base else { unreachable!() };
+                Ok(ribbon.lookup(Path::new(span).field(name), false)?.kind)
+            }
+            Expr::Member(base, Indirection::Field(name)) => field(base.kind_of(ribbon, context)?, name, "."),
+            Expr::Member(base, Indirection::Arrow(name)) => field(pointee(base.kind_of(ribbon, context)?, "->")?, name, "->"),
+            Expr::Member(base, indirection) => pointee(base.kind_of(ribbon, context)?, indirection.operator()),
+            Expr::Deref(inner) => pointee(inner.kind_of(ribbon, context)?, "*"),
+            Expr::AddressOf(inner) => Ok(context.add(Reference::new(Mode::Ptr, inner.kind_of(ribbon, context)?))),
+            Expr::Cast(type_name, _) => resolve(context, type_name),
+            Expr::SizeOf(_) | Expr::SizeOfType(_) | Expr::AlignOf(_) | Expr::OffsetOf(..) =>
+                Ok(context.add(Primitive::Size)),
+        }
+    }
+}
+
+/// The element of an array or the target of a pointer, as reached by `op`.
+fn pointee<'kind>(kind: &'kind Kind<'kind>, op: &'static str) -> Result<&'kind Kind<'kind>, Error<'kind>> {
+    match kind.underlying() {
+        Kind::Array(array) => Ok(array.kind),
+        Kind::Reference(Reference { meta: Meta::Thin, kind, .. }) => Ok(kind),
+        _ => Err(Error::Operand { op, operand: kind.to_string() }),
+    }
+}
+
+/// The kind named by `type_name`, which may not be `void`.
+fn resolve<'kind>(context: &'kind Context<'kind>, type_name: &TypeName) -> Result<&'kind Kind<'kind>, Error<'kind>> {
+    context.type_name(type_name)?
+        .ok_or_else(|| context::Error::Incomplete("void".to_string()).into())
+}
+
+/// Applies one indirection to a place reached by `base`, leaving a pointer at the end undereferenced.
+fn step<'kind>(
+    ribbon: &MemRibbon<'kind>,
+    place: &PlaceValue<'kind>,
+    indirection: Indirection,
+    base: &Expr,
+) -> access::Result<'kind> {
+    if place.bits.is_some() {
+        return Err(access::Error::at(base, access::ErrorKind::operation(&indirection, place.kind.clone())));
+    }
+
+    place.kind.access(Trace {
+        ribbon,
+        path: Path::from(vec![indirection]),
+        address: place.address,
+        field_name: base.to_string(),
+        qualifiers: place.qualifiers,
+        follow_pointer: false,
+    })
+}
+
+/// Follows the pointer `base` evaluates to, which points at `place`, with
+/// the bounds and provenance checks of a pointer read from memory.
+fn follow<'kind>(
+    ribbon: &MemRibbon<'kind>,
+    context: &'kind Context<'kind>,
+    place: &PlaceValue<'kind>,
+    indirection: Indirection,
+    base: &Expr,
+) -> access::Result<'kind> {
+    let provenance = base.provenance(ribbon, context);
+    let mut trace = Trace {
+        ribbon,
+        path: Path::from(vec![indirection.clone()]),
+        address: place.address,
+        field_name: base.to_string(),
+        qualifiers: place.qualifiers,
+        follow_pointer: false,
+    };
+
+    match indirection {
+        Indirection::Index(index) => {
+            let stride = place.kind.size_of() as usize;
+            // An address outside every span is left for the provenance check to report.
+            let len = ribbon.span_end(place.address)
+                .map_or(usize::MAX, |end| (end - place.address).checked_div(stride).unwrap_or_default());
+            trace.address = trace.check_index(index, len, stride)?;
+            trace.check_provenance(provenance.as_deref(), stride)?;
+            Ok(PlaceValue { address: trace.address, ..*place })
+        }
+        Indirection::Arrow(field) => {
+            trace.check_provenance(provenance.as_deref(), place.kind.size_of() as usize)?;
+            step(ribbon, place, Indirection::Field(field), base)
+        }
+        _ => {
+            trace.check_provenance(provenance.as_deref(), place.kind.size_of() as usize)?;
+            Ok(*place)
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Name(name) => f.write_str(name),
+            Expr::Integer(value) => write!(f, "{value}"),
+            Expr::Member(base, indirection) => {
+                match **base {
+                    Expr::Deref(_) | Expr::AddressOf(_) | Expr::Cast(..) | Expr::SizeOf(_) => write!(f, "({base})")?,
+                    _ => write!(f, "{base}")?,
+                }
+                match indirection {
+                    Indirection::Field(field) => write!(f, ".{field}"),
+                    Indirection::Arrow(field) => write!(f, "->{field}"),
+                    Indirection::Index(index) => write!(f, "[{index}]"),
+                    Indirection::Deref => f.write_str("[0]"),
+                }
+            }
+            Expr::Deref(inner) => write!(f, "*{inner}"),
+            Expr::AddressOf(inner) => write!(f, "&{inner}"),
+            Expr::Cast(type_name, inner) => write!(f, "({type_name}){inner}"),
+            Expr::SizeOf(inner) => write!(f, "sizeof {inner}"),
+            Expr::SizeOfType(type_name) => write!(f, "sizeof({type_name})"),
+            Expr::AlignOf(type_name) => write!(f, "_Alignof({type_name})"),
+            Expr::OffsetOf(type_name, designator) => {
+                write!(f, "offsetof({type_name}, ")?;
+                for (index, indirection) in designator.iter().enumerate() {
+                    match indirection {
+                        Indirection::Field(field) if index == 0 => f.write_str(field)?,
+                        Indirection::Field(field) => write!(f, ".{field}")?,
+                        Indirection::Index(index) => write!(f, "[{index}]")?,
+                        _ => (),
+                    }
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{kind::primitive::PrimValue, target::Target};
+
+    const HEADER: &str = "
+        struct hdr { unsigned short len; unsigned char kind; unsigned flag : 1; };
+        struct s { struct hdr h; char buf[8]; struct hdr *p; int a[3]; } s;
+    ";
+
+    fn address(value: Value) -> usize {
+        match value {
+            Value::Place(place) | Value::Address(place) => place.address,
+            Value::Integer(value) => value,
+        }
+    }
+
+    #[test]
+    fn places() {
+        let _target = Target::lp64().enter();
+        let context = Context::new();
+        let mut ribbon = MemRibbon::new(0x100).target(Target::lp64())
+            .span("g", context.parse_c(HEADER).unwrap());
+        ribbon.write_at(0x110, PrimValue::Size(0x100));
+
+        let eval = |source: &str| ribbon.eval(&context, source).map(address).unwrap();
+        assert_eq!(eval("g.s.p"), 0x110);
+        assert_eq!(eval("(*g.s.p).kind"), 0x102);
+        assert_eq!(eval("g.s.p->kind"), 0x102);
+        assert_eq!(eval("( g.s ).a[2]"), 0x120);
+        assert_eq!(eval("*g.s.a"), 0x118);
+        assert_eq!(eval("((struct hdr *)g.s.buf)->kind"), 0x106);
+        assert_eq!(eval("(unsigned long)g.s.p"), 0x100);
+
+        let value = ribbon.eval(&context, "&g.s.a[1]").unwrap();
+        assert_eq!(ribbon.eval(&context, "(&g.s.a[1])[1]").map(address).ok(), Some(0x120));
+        assert!(value.place().is_none());
+        assert_eq!(value.integer(), Some(0x11C));
+        assert_eq!(ribbon.eval(&context, "*&g.s.a[1]").unwrap().place().unwrap().address, 0x11C);
+    }
+
+    #[test]
+    fn integers() {
+        let _target = Target::lp64().enter();
+        let context = Context::new();
+        let mut ribbon = MemRibbon::new(0).target(Target::lp64())
+            .span("g", context.parse_c(HEADER).unwrap());
+        ribbon.write_at(16, PrimValue::Size(0x12f4));
+
+        let eval = |source: &str| ribbon.eval(&context, source).unwrap().integer();
+        assert_eq!(eval("(unsigned char)g.s.p"), Some(0xf4));
+        assert_eq!(eval("(signed char)g.s.p"), Some(-12_isize as usize));
+        assert_eq!(eval("(unsigned short)g.s.p"), Some(0x12f4));
+        assert_eq!(eval("sizeof(struct hdr)"), Some(4));
+        assert_eq!(eval("_Alignof(struct hdr)"), Some(4));
+        assert_eq!(eval("sizeof g.s.a"), Some(12));
+        assert_eq!(eval("sizeof *g.s.p"), Some(4));
+        assert_eq!(eval("sizeof(int *[2])"), Some(16));
+        assert_eq!(eval("offsetof(struct s, a[1])"), Some(28));
+        assert_eq!(eval("offsetof(struct s, h.kind)"), Some(2));
+        assert_eq!(ribbon.size_of("g.s.p"), Some(8));
+        assert_eq!(ribbon.address_of("g.s.buf"), Some(4));
+    }
+
+    #[test]
+    fn errors() {
+        let _target = Target::lp64().enter();
+        let context = Context::new();
+        let ribbon = MemRibbon::new(0).target(Target::lp64())
+            .span("g", context.parse_c(HEADER).unwrap());
+
+        let eval = |source: &str| ribbon.eval(&context, source).err().unwrap();
+        assert!(matches!(eval("&g.s.h.flag"), Error::Operand { op: "&", .. }));
+        assert!(matches!(eval("*3"), Error::Operand { op: "*", .. }));
+        assert!(matches!(eval("(nope *)g.s.p"), Error::Type(context::Error::Unknown(_))));
+        assert!(matches!(eval("g.s.h->len"), Error::Access(_)));
+        assert!(matches!(eval("g.s["), Error::Syntax(_)));
+        assert!(matches!(eval("g"), Error::Access(_)));
+
+        let access = |source: &str| match eval(source) {
+            Error::Access(err) => err.kind,
+            err => panic!("{err} is not an access error"),
+        };
+        assert!(matches!(access("((struct hdr *)0x200)[1].len"), access::ErrorKind::Dangling { address: 0x204 }));
+        assert!(matches!(access("((struct hdr *)0x9999)->len"), access::ErrorKind::Dangling { address: 0x9999 }));
+        assert!(matches!(access("(&g.s.a[1])[18446744073709551615]"), access::ErrorKind::OutOfBounds { .. }));
+
+        let shown = |source: &str| source.parse::<Expr>().unwrap().to_string();
+        assert_eq!(shown("((struct hdr *)g.s.buf)->len"), "((struct hdr *)g.s.buf)->len");
+        assert_eq!(shown("(int (* const)[3])&g.s.a"), "(int (* const)[3])&g.s.a");
+        assert_eq!(shown("sizeof(unsigned long *[2])"), "sizeof(unsigned long *[2])");
+        assert_eq!(shown("offsetof(struct s, h.len)"), "offsetof(struct s, h.len)");
+        assert_eq!(shown("_Alignof(const char)"), "_Alignof(const char)");
+    }
+}
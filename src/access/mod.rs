@@ -5,17 +5,23 @@ use std::{
 };
 
 use crate::{
-    kind::Kind,
+    kind::{Kind, composite::Bits, qualified::Qualifiers},
     mem_ribbon::MemRibbon,
 };
 
-mod parse;
+pub mod diagnostic;
+pub mod expr;
+pub(crate) mod parse;
 
 pub type Result<'kind> = std::result::Result<PlaceValue<'kind>, Error<'kind>>;
 
+#[derive(Clone, Copy)]
 pub struct PlaceValue<'kind> {
     pub kind: &'kind Kind <'kind>,
     pub address: usize,
+    pub bits: Option<Bits>,
+    /// Qualifiers of the place, including those of the objects it is a member of.
+    pub qualifiers: Qualifiers,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -48,7 +54,7 @@ impl Indirection {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Path(pub VecDeque<Indirection>);
 
 impl Path {
@@ -96,21 +102,117 @@ where VecDeque<Indirection>: PartialEq<T> {
 }
 
 impl FromStr for Path {
-    type Err = pom::Error;
+    type Err = diagnostic::Diagnostic;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         // Bindings required for borrow checker
         let chars = s.chars().collect::<Vec<_>>();
         let parser = parse::access_expr();
-        parser.parse(&chars)
+        parser.parse(&chars).map_err(|err| diagnostic::Diagnostic::syntax(s, &err))
     }
 }
 
-pub struct Trace<'kind> {
-    pub ribbon: &'kind MemRibbon<'kind>,
+pub struct Trace<'ribbon, 'kind> {
+    pub ribbon: &'ribbon MemRibbon<'kind>,
     pub path: Path,
     pub address: usize,
     pub field_name: String,
+    /// Qualifiers gathered from the objects traversed since the last dereference.
+    pub qualifiers: Qualifiers,
+    /// Whether a thin pointer at the end of the path stands for the object
+    /// it points to, as it does in the access strings of [`MemRibbon::at`].
+    pub follow_pointer: bool,
+}
+
+impl Trace<'_, '_> {
+    /// Checks `index` against the `len` elements of `stride` bytes starting at
+    /// the traced address, returning the address of the element.
+    ///
+    /// When the ribbon allows out-of-bounds access the index is let through,
+    /// and the bytes it reaches are recorded as an [`Overrun`] instead, unless
+    /// its address cannot be represented.
+    pub fn check_index<'kind>(
+        &self,
+        index: usize,
+        len: usize,
+        stride: usize,
+    ) -> std::result::Result<usize, Error<'kind>> {
+        let out_of_bounds = || Error::at(&self.field_name, ErrorKind::OutOfBounds { index, len });
+        let address = stride.checked_mul(index)
+            .and_then(|offset| self.address.checked_add(offset))
+            .ok_or_else(out_of_bounds)?;
+
+        if index < len {
+            return Ok(address);
+        }
+
+        if !self.ribbon.allow_overrun {
+            return Err(out_of_bounds());
+        }
+
+        let mut overruns = self.ribbon.overruns.borrow_mut();
+        if !overruns.iter().any(|overrun| overrun.address == address) {
+            overruns.push(Overrun {
+                access: format!("{}[{index}]", self.field_name),
+                address,
+                size: stride,
+            });
+        }
+        Ok(address)
+    }
+
+    /// Checks that `size` bytes at the traced address, reached through a
+    /// pointer derived from the span named `provenance`, lie within that span.
+    ///
+    /// Leaving that span is recorded as an [`Overrun`] when the ribbon allows
+    /// out-of-bounds access, while memory outside every span is always an error.
+    pub fn check_provenance<'kind>(
+        &self,
+        provenance: Option<&str>,
+        size: usize,
+    ) -> std::result::Result<(), Error<'kind>> {
+        let address = self.address;
+        let allocations = self.ribbon.allocations();
+        if !allocations.iter().any(|allocation| allocation.holds(address, size)) {
+            return Err(Error::at(&self.field_name, ErrorKind::Dangling { address }));
+        }
+
+        let Some(span) = provenance else {
+            return Ok(());
+        };
+        let inside = allocations.iter()
+            .any(|allocation| allocation.name == span && allocation.holds(address, size));
+
+        if inside {
+            return Ok(());
+        }
+
+        if !self.ribbon.allow_overrun {
+            return Err(Error::at(&self.field_name, ErrorKind::Foreign { span: span.to_string(), address }));
+        }
+
+        let mut overruns = self.ribbon.overruns.borrow_mut();
+        if !overruns.iter().any(|overrun| overrun.address == address) {
+            overruns.push(Overrun { access: self.field_name.clone(), address, size });
+        }
+        Ok(())
+    }
+}
+
+/// An access past the end of an array or span, let through by [`MemRibbon::allow_out_of_bounds`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Overrun {
+    /// The indexed field and index, such as `arr[10]`, or the pointer that left its span.
+    pub access: String,
+    /// Start of the element that was reached, which belongs to whatever lies there.
+    pub address: usize,
+    pub size: usize,
+}
+
+impl Overrun {
+    pub fn contains(&self, address: usize) -> bool {
+        (self.address..self.address + self.size).contains(&address)
+    }
 }
 
 pub struct Error<'kind> {
@@ -153,6 +255,16 @@ impl fmt::Display for Error<'_> {
                 write!(f, "{field_name} cannot be accessed as a field")?,
             ErrorKind::RibbonOp { op } =>
                 write!(f, "Operation {op} undefined for MemoryRibbon")?,
+            ErrorKind::ConstWrite =>
+                write!(f, "Cannot write to {field_name}, which is const")?,
+            ErrorKind::Mismatch { expected, found } =>
+                write!(f, "Expected {expected} at {field_name}, found {found}")?,
+            ErrorKind::OutOfBounds { index, len } =>
+                write!(f, "Index {index} out of bounds for {field_name} of length {len}")?,
+            ErrorKind::Dangling { address } =>
+                write!(f, "Pointer {field_name} is dangling: address {address:#x} is not inside any span")?,
+            ErrorKind::Foreign { span, address } =>
+                write!(f, "Pointer {field_name} into span {span} reaches address {address:#x}, outside of {span}")?,
         }
 
         if let Some(s) = context {
@@ -169,17 +281,18 @@ impl fmt::Debug for Error<'_> {
     }
 }
 
+/// The kinds held by value are boxed to keep results carrying an [`Error`] small.
 pub enum ErrorKind<'kind> {
     Operation {
         op: &'static str,
-        kind: Kind<'kind>,
+        kind: Box<Kind<'kind>>,
     },
     Arrow {
-        kind: Kind<'kind>
+        kind: Box<Kind<'kind>>,
     },
     Unwind {
         original: String,
-        kind: Kind<'kind>,
+        kind: Box<Kind<'kind>>,
     },
     Deref {
         old_addr: usize,
@@ -191,11 +304,30 @@ pub enum ErrorKind<'kind> {
     RibbonOp {
         op: &'static str,
     },
+    OutOfBounds {
+        index: usize,
+        len: usize,
+    },
+    /// A pointer reaches memory that belongs to no span.
+    Dangling {
+        address: usize,
+    },
+    /// A pointer reaches memory outside of the span it was derived from.
+    Foreign {
+        span: String,
+        address: usize,
+    },
+    ConstWrite,
+    /// The place holds a different kind than the operation needs.
+    Mismatch {
+        expected: Box<Kind<'kind>>,
+        found: Box<Kind<'kind>>,
+    },
 }
 
 impl<'kind> ErrorKind<'kind> {
     pub fn operation(indirection: &Indirection, kind: Kind<'kind>) -> Self {
-        Self::Operation { op: indirection.operator(), kind }
+        Self::Operation { op: indirection.operator(), kind: Box::new(kind) }
     }
 
     pub fn ribbon_op(indirection: &Indirection) -> Self {
@@ -1,12 +1,12 @@
 #![allow(dead_code)]
 
-use std::iter;
+use std::{fmt, iter, ops::Range};
 use access::Indirection;
-use pom::parser::{sym, is_a};
+use pom::parser::{sym, is_a, none_of, seq, any, call, list, end, empty};
 
 use crate::{
-    access,
-    kind::reference,
+    access::{self, expr::Expr},
+    kind::{composite, qualified::Qualifiers},
 };
 
 type Parser<'a, O> = pom::parser::Parser<'a, char, O>;
@@ -38,50 +38,566 @@ fn integer<'a>() -> Parser<'a, usize> {
         .convert(|s| s.parse())
 }
 
+fn tuple_label<'a>() -> Parser<'a, String> {
+    is_a(char::is_numeric).repeat(1..)
+        .map(|seq| seq.into_iter().collect())
+}
+
+/// Like `repeat(0..)`, but fails with the error of an item that got past
+/// one of its `expect`s, rather than stopping quietly before it.
+fn many<'a, O: 'a>(parser: Parser<'a, O>) -> Parser<'a, Vec<O>> {
+    Parser::new(move |input, mut position| {
+        let mut items = Vec::new();
+        loop {
+            match parser.parse_at(input, position) {
+                Ok((item, next)) => {
+                    items.push(item);
+                    position = next;
+                }
+                Err(err @ pom::Error::Expect { .. }) => return Err(err),
+                Err(_) => return Ok((items, position)),
+            }
+        }
+    })
+}
+
+/// Pairs what `parser` parses with the characters it was parsed from.
+fn located<'a, O: 'a>(parser: Parser<'a, O>) -> Parser<'a, (O, Range<usize>)> {
+    (empty().pos() + parser + empty().pos())
+        .map(|((start, out), end)| (out, start..end))
+}
+
 fn field_expr<'a>() -> Parser<'a, Indirection> {
-    (sym('.') * label())
+    (sym('.') * (label() | tuple_label()).expect("field name"))
         .map(Indirection::Field)
 }
 
 fn index_expr<'a>() -> Parser<'a, Indirection> {
-    (sym('[') * integer() - sym(']'))
+    (sym('[') * integer().expect("index") - sym(']').expect("`]`"))
         .map(Indirection::Index)
 }
 
 fn arrow_expr<'a>() -> Parser<'a, Indirection> {
-    ((sym('-') + sym('>')) * label())
+    ((sym('-') + sym('>')) * label().expect("field name"))
         .map(Indirection::Arrow)
 }
 
-pub fn access_expr<'a>() -> Parser<'a, access::Path> {
-    let parser = sym('*').opt()
-        + label()
-        + (field_expr() | index_expr() | arrow_expr()).repeat(0..);
+/// An access string, with the characters each of its indirections was parsed from.
+pub fn located_access_expr<'a>() -> Parser<'a, (access::Path, Vec<Range<usize>>)> {
+    let parser = located(sym('*')).opt()
+        + located(label().expect("span name"))
+        + many(located(field_expr() | index_expr() | arrow_expr()))
+        - end().expect("`.`, `[`, `->` or end of access");
 
-    parser.map(|((deref, head), tail)|
-        iter::once(Indirection::Field(head))
+    parser.map(|((deref, (head, head_range)), tail)| {
+        let (path, ranges): (Vec<_>, Vec<_>) = iter::once((Indirection::Field(head), head_range))
             .chain(tail)
-            .chain(deref.map(|_| Indirection::Deref))
-            .collect::<Vec<_>>()
-            .into()
-    )
+            .chain(deref.map(|(_, range)| (Indirection::Deref, range)))
+            .unzip();
+
+        (path.into(), ranges)
+    })
+}
+
+pub fn access_expr<'a>() -> Parser<'a, access::Path> {
+    located_access_expr().map(|(path, _)| path)
+}
+
+fn comment<'a>() -> Parser<'a, ()> {
+    let line_comment = seq(&['/', '/']) * none_of("\n").repeat(0..);
+    let block_comment = seq(&['/', '*']) * (!seq(&['*', '/']) * any()).repeat(0..) * seq(&['*', '/']);
+
+    line_comment.discard() | block_comment.discard()
+}
+
+/// Whitespace, comments and preprocessor lines between the tokens of a declaration.
+fn c_space<'a>() -> Parser<'a, ()> {
+    let directive = sym('#') * none_of("\n").repeat(0..);
+
+    (is_a(char::is_whitespace).discard() | comment() | directive.discard())
+        .repeat(0..)
+        .discard()
+}
+
+fn punct<'a>(ch: char) -> Parser<'a, ()> {
+    sym(ch).discard() - c_space()
+}
+
+const BUILTINS: [&str; 11] = [
+    "void", "char", "short", "int", "long", "float", "double",
+    "signed", "unsigned", "_Bool", "__int128",
+];
+
+const KEYWORDS: [&str; 8] = [
+    "struct", "union", "enum", "typedef", "const", "volatile", "_Atomic", "restrict",
+];
+
+fn keyword<'a>(word: &'static str) -> Parser<'a, ()> {
+    label().convert(move |text| if text == word { Ok(()) } else { Err(text) }).discard() - c_space()
+}
+
+fn identifier<'a>() -> Parser<'a, String> {
+    label().convert(|text| match BUILTINS.contains(&text.as_str()) || KEYWORDS.contains(&text.as_str()) {
+        true => Err(text),
+        false => Ok(text),
+    }) - c_space()
+}
+
+fn builtin<'a>() -> Parser<'a, String> {
+    label().convert(|text| match BUILTINS.contains(&text.as_str()) {
+        true => Ok(text),
+        false => Err(text),
+    }) - c_space()
+}
+
+fn qualifiers<'a>() -> Parser<'a, Qualifiers> {
+    let qualifier = keyword("const").map(|_| Qualifiers::CONST)
+        | keyword("volatile").map(|_| Qualifiers::VOLATILE)
+        | keyword("_Atomic").map(|_| Qualifiers::ATOMIC)
+        | keyword("restrict").map(|_| Qualifiers::default());
+
+    qualifier.repeat(0..).map(|all| all.into_iter().fold(Qualifiers::default(), |a, b| a | b))
+}
+
+/// The type a C declaration starts with, before its declarators.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Specifier {
+    /// Arithmetic keywords such as `unsigned long long`, in the order written.
+    Builtin(Vec<String>),
+    /// A typedef or any other type registered by name.
+    Named(String),
+    /// A struct or union, with its members when they are defined here.
+    Composite {
+        mode: composite::Mode,
+        tag: Option<String>,
+        members: Option<Vec<Declaration>>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeSpec {
+    pub specifier: Specifier,
+    pub qualifiers: Qualifiers,
+}
+
+/// One step from the type a declaration starts with towards the type of the declared name.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Derivation {
+    Pointer(Qualifiers),
+    /// An array, whose length is left out for a flexible array member.
+    Array(Option<usize>),
+    /// A function taking the given parameters, which is only reached through a pointer.
+    Function(Vec<TypeName>),
+}
+
+/// A declarator such as `*(*x)[4]`, with the inside-out C grammar flattened
+/// into the derivations to apply to the base type, in order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Declarator {
+    pub name: Option<String>,
+    pub derivations: Vec<Derivation>,
+    /// Width of a bitfield member.
+    pub bits: Option<u16>,
+}
+
+/// A type written without a name, such as the parameter type `char *`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeName {
+    pub spec: TypeSpec,
+    pub declarator: Declarator,
+}
+
+impl fmt::Display for TypeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let TypeSpec { specifier, qualifiers } = &self.spec;
+        if !qualifiers.is_empty() {
+            write!(f, "{qualifiers} ")?;
+        }
+        match specifier {
+            Specifier::Builtin(words) => f.write_str(&words.join(" "))?,
+            Specifier::Named(name) => f.write_str(name)?,
+            Specifier::Composite { mode, tag: Some(tag), .. } => write!(f, "{mode} {tag}")?,
+            Specifier::Composite { mode, tag: None, .. } => write!(f, "{mode} {{...}}")?,
+        }
+
+        // The last derivation binds closest to the (absent) name, so the declarator is built outwards from it.
+        let mut declarator = String::new();
+        for derivation in self.declarator.derivations.iter().rev() {
+            let wrapped = match declarator.starts_with('*') {
+                true => format!("({declarator})"),
+                false => declarator.clone(),
+            };
+            declarator = match derivation {
+                Derivation::Pointer(qualifiers) if qualifiers.is_empty() => format!("*{declarator}"),
+                Derivation::Pointer(qualifiers) => format!("* {qualifiers} {declarator}").trim_end().to_string(),
+                Derivation::Array(Some(len)) => format!("{wrapped}[{len}]"),
+                Derivation::Array(None) => format!("{wrapped}[]"),
+                Derivation::Function(params) => {
+                    let params: Vec<_> = params.iter().map(TypeName::to_string).collect();
+                    format!("{wrapped}({})", params.join(", "))
+                }
+            };
+        }
+
+        match declarator.is_empty() {
+            true => Ok(()),
+            false => write!(f, " {declarator}"),
+        }
+    }
+}
+
+/// A declaration ending in `;`, which declares no names when it only defines a struct or union.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Declaration {
+    pub typedef: bool,
+    pub spec: TypeSpec,
+    pub declarators: Vec<Declarator>,
+}
+
+fn composite_spec<'a>() -> Parser<'a, Specifier> {
+    let mode = keyword("struct").map(|_| composite::Mode::Product)
+        | keyword("union").map(|_| composite::Mode::Sum);
+    let members = || punct('{') * many(call(field_decl)) - punct('}').expect("member declaration or `}`");
+    let tagged = identifier().map(Some) + (members().map(Some) | empty().map(|_| None));
+    let anonymous = members().map(|members| (None, Some(members))).expect("tag or `{`");
+
+    (mode + (tagged | anonymous))
+        .map(|(mode, (tag, members))| Specifier::Composite { mode, tag, members })
+}
+
+fn type_spec<'a>() -> Parser<'a, TypeSpec> {
+    let specifier = composite_spec()
+        | builtin().repeat(1..).map(Specifier::Builtin)
+        | identifier().map(Specifier::Named);
+
+    (qualifiers() + specifier + qualifiers())
+        .map(|((before, specifier), after)| TypeSpec { specifier, qualifiers: before | after })
+}
+
+fn pointer<'a>() -> Parser<'a, Derivation> {
+    (punct('*') * qualifiers()).map(Derivation::Pointer)
+}
+
+fn suffix<'a>() -> Parser<'a, Derivation> {
+    let length = integer() - c_space();
+    let array = (punct('[') * length.opt() - punct(']')).map(Derivation::Array);
+
+    let void = keyword("void").map(|_| Vec::new()) - !(punct('*') | punct('('));
+    let params = void | list(call(type_name), punct(','));
+    let function = (punct('(') * params - punct(')')).map(Derivation::Function);
+
+    array | function
+}
+
+/// A declarator, whose name is optional when `abstract_` is set.
+fn declarator_of<'a>(abstract_: bool) -> Parser<'a, Declarator> {
+    let inner = punct('(') * call(move || declarator_of(abstract_)) - punct(')');
+    let name = identifier().map(|name| Declarator { name: Some(name), ..Default::default() });
+    let direct = match abstract_ {
+        true => inner | name | empty().map(|_| Declarator::default()),
+        false => inner | name,
+    };
+
+    (pointer().repeat(0..) + direct + suffix().repeat(0..))
+        .map(|((pointers, inner), suffixes)| {
+            let derivations = pointers.into_iter()
+                .chain(suffixes.into_iter().rev())
+                .chain(inner.derivations)
+                .collect();
+            Declarator { name: inner.name, derivations, bits: None }
+        })
+}
+
+pub fn declarator<'a>() -> Parser<'a, Declarator> {
+    declarator_of(false)
+}
+
+/// A type without a name, such as `int *[4]`.
+pub fn type_name<'a>() -> Parser<'a, TypeName> {
+    (type_spec() + declarator_of(true))
+        .map(|(spec, declarator)| TypeName { spec, declarator })
+}
+
+/// A declaration ending in `;`, whose declarators may have bitfield widths.
+pub fn field_decl<'a>() -> Parser<'a, Declaration> {
+    let width = punct(':') * (integer() - c_space()).convert(u16::try_from);
+    let member = (declarator().opt() + width.opt()).convert(|(declarator, bits)| match (declarator, bits) {
+        (None, None) => Err("empty declarator"),
+        (declarator, bits) => Ok(Declarator { bits, ..declarator.unwrap_or_default() }),
+    });
+
+    (keyword("typedef").opt() + type_spec() + list(member, punct(',')) - punct(';').expect("`;`"))
+        .map(|((typedef, spec), declarators)| Declaration { typedef: typedef.is_some(), spec, declarators })
+}
+
+/// The declarations of a header snippet.
+pub fn declarations<'a>() -> Parser<'a, Vec<Declaration>> {
+    c_space() * many(field_decl()) - end().expect("declaration")
 }
 
-pub struct RefrDecl {
-    label: String,
-    mode: reference::Mode,
+/// A type name such as `const char *[4]`, with nothing after it.
+pub fn kind_expr<'a>() -> Parser<'a, TypeName> {
+    c_space() * type_name() - end().expect("end of type name")
+}
+
+/// A decimal or `0x` hexadecimal integer literal.
+fn c_integer<'a>() -> Parser<'a, usize> {
+    let hex = seq(&['0', 'x']) * is_a(|ch: char| ch.is_ascii_hexdigit()).repeat(1..)
+        .convert(|digits| usize::from_str_radix(&digits.into_iter().collect::<String>(), 16));
+
+    (hex | integer()) - c_space()
+}
+
+/// A `.field`, `->field` or `[index]` after an expression.
+fn postfix_op<'a>() -> Parser<'a, Indirection> {
+    let field = punct('.') * (label() | tuple_label()).expect("field name") - c_space();
+    let arrow = seq(&['-', '>']) * c_space() * label().expect("field name") - c_space();
+    let index = punct('[') * c_integer().expect("index") - punct(']').expect("`]`");
+
+    field.map(Indirection::Field) | arrow.map(Indirection::Arrow) | index.map(Indirection::Index)
+}
+
+fn primary<'a>() -> Parser<'a, Expr> {
+    let paren = punct('(') * call(unary) - punct(')');
+    let member = (label() - c_space()).map(Indirection::Field);
+    let designator = (member + (!punct('-') * postfix_op()).repeat(0..))
+        .map(|(head, tail)| iter::once(head).chain(tail).collect());
+    let offsetof = keyword("offsetof") * punct('(') * type_name() - punct(',') + designator - punct(')');
+
+    c_integer().map(Expr::Integer)
+        | paren
+        | offsetof.map(|(type_name, designator)| Expr::OffsetOf(type_name, designator))
+        | (label() - c_space()).map(Expr::Name)
+}
+
+fn postfix<'a>() -> Parser<'a, Expr> {
+    (primary() + many(postfix_op()))
+        .map(|(base, ops)| ops.into_iter().fold(base, |base, op| Expr::Member(Box::new(base), op)))
+}
+
+fn unary<'a>() -> Parser<'a, Expr> {
+    let deref = punct('*') * call(unary);
+    let address = punct('&') * call(unary);
+    let cast = punct('(') * type_name() - punct(')') + call(unary);
+    let size_of_type = keyword("sizeof") * punct('(') * type_name() - punct(')');
+    let size_of = keyword("sizeof") * call(unary);
+    let align_of = (keyword("_Alignof") | keyword("alignof")) * punct('(') * type_name() - punct(')');
+
+    deref.map(|expr| Expr::Deref(Box::new(expr)))
+        | address.map(|expr| Expr::AddressOf(Box::new(expr)))
+        | cast.map(|(type_name, expr)| Expr::Cast(type_name, Box::new(expr)))
+        | size_of_type.map(Expr::SizeOfType)
+        | size_of.map(|expr| Expr::SizeOf(Box::new(expr)))
+        | align_of.map(Expr::AlignOf)
+        | postfix()
+}
+
+/// An access expression such as `((struct hdr *)buf.data)->len` or
+/// `sizeof s.items[0]`, with nothing after it.
+pub fn access_expression<'a>() -> Parser<'a, Expr> {
+    c_space() * unary() - end().expect("end of expression")
+}
+
+/// Whitespace and comments between the tokens of a Rust item.
+fn rust_space<'a>() -> Parser<'a, ()> {
+    (is_a(char::is_whitespace).discard() | comment())
+        .repeat(0..)
+        .discard()
+}
+
+fn rust_punct<'a>(ch: char) -> Parser<'a, ()> {
+    sym(ch).discard() - rust_space()
+}
+
+const RUST_KEYWORDS: [&str; 12] = [
+    "struct", "union", "enum", "pub", "mut", "const", "fn", "dyn",
+    "crate", "super", "in", "where",
+];
+
+fn rust_keyword<'a>(word: &'static str) -> Parser<'a, ()> {
+    label().convert(move |text| if text == word { Ok(()) } else { Err(text) }).discard() - rust_space()
+}
+
+fn rust_identifier<'a>() -> Parser<'a, String> {
+    label().convert(|text| match RUST_KEYWORDS.contains(&text.as_str()) {
+        true => Err(text),
+        false => Ok(text),
+    }) - rust_space()
+}
+
+/// A decimal or hexadecimal integer literal, which may be negative and contain underscores.
+fn rust_integer<'a>() -> Parser<'a, i64> {
+    let digits = |radix| is_a(move |ch: char| ch.is_digit(radix) || ch == '_').repeat(1..)
+        .map(|digits| digits.into_iter().filter(|ch| *ch != '_').collect::<String>());
+    let hex = seq(&['0', 'x']) * digits(16).convert(|text| i64::from_str_radix(&text, 16));
+    let decimal = digits(10).convert(|text| text.parse::<i64>());
+
+    (sym('-').opt() + (hex | decimal))
+        .map(|(minus, value)| if minus.is_some() { -value } else { value })
+        - rust_space()
+}
+
+fn lifetime<'a>() -> Parser<'a, ()> {
+    (sym('\'') * label()).discard() - rust_space()
+}
+
+/// `pub`, `pub(crate)` and the like, which do not change the layout.
+fn visibility<'a>() -> Parser<'a, ()> {
+    let scope = rust_keyword("crate") | rust_keyword("super") | rust_keyword("self")
+        | (rust_keyword("in") * rust_path()).discard();
+    (rust_keyword("pub") * (rust_punct('(') * scope - rust_punct(')')).opt()).discard()
+}
+
+fn rust_path<'a>() -> Parser<'a, String> {
+    list(rust_identifier(), seq(&[':', ':']) - rust_space())
+        .convert(|segments| match segments.last() {
+            Some(last) => Ok(last.clone()),
+            None => Err("empty path"),
+        })
+}
+
+/// A Rust type as written, such as `&'a mut [u8; 4]` or `Option<Box<Node>>`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RustType {
+    /// A named type, with the last segment of its path and its type arguments.
+    Path(String, Vec<RustType>),
+    Ref { mutable: bool, ty: Box<RustType> },
+    Ptr { mutable: bool, ty: Box<RustType> },
+    Array(Box<RustType>, usize),
+    Slice(Box<RustType>),
+    Tuple(Vec<RustType>),
+    Fn(Vec<RustType>, Option<Box<RustType>>),
+}
+
+/// One hint of a `#[repr(...)]` attribute.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReprHint {
+    C,
+    Transparent,
+    Packed(Option<u64>),
+    Align(u64),
+    /// A primitive integer such as `u8`, giving the type of an enum's tag.
+    Int(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RustFields {
+    Named(Vec<(String, RustType)>),
+    Tuple(Vec<RustType>),
+    Unit,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RustVariant {
+    pub name: String,
+    pub fields: RustFields,
+    pub discriminant: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RustBody {
+    Struct(RustFields),
+    Union(Vec<(String, RustType)>),
+    Enum(Vec<RustVariant>),
+}
+
+/// A `struct`, `union` or `enum` item with the hints of its `#[repr]` attributes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RustItem {
+    pub name: String,
+    pub repr: Vec<ReprHint>,
+    pub body: RustBody,
+}
+
+pub fn rust_type<'a>() -> Parser<'a, RustType> {
+    // Lifetime arguments do not change the layout, so only the types are kept.
+    let arg = lifetime().map(|_| None) | call(rust_type).map(Some);
+    let args = rust_punct('<') * list(arg, rust_punct(',')) - rust_punct(',').opt() - rust_punct('>');
+    let path = (rust_path() + args.opt())
+        .map(|(name, args)| RustType::Path(name, args.unwrap_or_default().into_iter().flatten().collect()));
+
+    let reference = (rust_punct('&') * lifetime().opt() * rust_keyword("mut").opt() + call(rust_type))
+        .map(|(mutable, ty)| RustType::Ref { mutable: mutable.is_some(), ty: Box::new(ty) });
+    let mutability = rust_keyword("mut").map(|_| true) | rust_keyword("const").map(|_| false);
+    let pointer = (rust_punct('*') * mutability + call(rust_type))
+        .map(|(mutable, ty)| RustType::Ptr { mutable, ty: Box::new(ty) });
+
+    let array = (rust_punct('[') * call(rust_type) - rust_punct(';') + rust_integer().convert(usize::try_from) - rust_punct(']'))
+        .map(|(ty, len)| RustType::Array(Box::new(ty), len));
+    let slice = (rust_punct('[') * call(rust_type) - rust_punct(']'))
+        .map(|ty| RustType::Slice(Box::new(ty)));
+
+    let tuple = (rust_punct('(') * list(call(rust_type), rust_punct(',')) - rust_punct(',').opt() - rust_punct(')'))
+        .map(RustType::Tuple);
+
+    let ret = seq(&['-', '>']) * rust_space() * call(rust_type);
+    let function = (rust_keyword("fn") * rust_punct('(') * list(call(rust_type), rust_punct(',')) - rust_punct(')') + ret.opt())
+        .map(|(params, ret)| RustType::Fn(params, ret.map(Box::new)));
+
+    reference | pointer | array | slice | tuple | function | path
+}
+
+fn attribute<'a>() -> Parser<'a, Vec<ReprHint>> {
+    let number = || rust_punct('(') * rust_integer().convert(u64::try_from) - rust_punct(')');
+    let hint = rust_keyword("C").map(|_| ReprHint::C)
+        | rust_keyword("transparent").map(|_| ReprHint::Transparent)
+        | (rust_keyword("packed") * number().opt()).map(ReprHint::Packed)
+        | (rust_keyword("align") * number()).map(ReprHint::Align)
+        | rust_identifier().map(ReprHint::Int);
+    let repr = rust_keyword("repr") * rust_punct('(') * list(hint, rust_punct(',')) - rust_punct(')');
+    let other = none_of("]").repeat(0..).map(|_| Vec::new());
+
+    rust_punct('#') * rust_punct('[') * (repr | other) - rust_punct(']')
+}
+
+fn named_fields<'a>() -> Parser<'a, Vec<(String, RustType)>> {
+    let field = visibility().opt() * rust_identifier() - rust_punct(':') + rust_type();
+    rust_punct('{') * list(attribute().repeat(0..) * field, rust_punct(',')) - rust_punct(',').opt()
+        - rust_punct('}').expect("field or `}`")
+}
+
+fn tuple_fields<'a>() -> Parser<'a, Vec<RustType>> {
+    let field = visibility().opt() * rust_type();
+    rust_punct('(') * list(attribute().repeat(0..) * field, rust_punct(',')) - rust_punct(',').opt() - rust_punct(')')
+}
+
+fn rust_fields<'a>() -> Parser<'a, RustFields> {
+    named_fields().map(RustFields::Named)
+        | tuple_fields().map(RustFields::Tuple)
+        | empty().map(|_| RustFields::Unit)
+}
+
+fn rust_variant<'a>() -> Parser<'a, RustVariant> {
+    (attribute().repeat(0..) * rust_identifier() + rust_fields() + (rust_punct('=') * rust_integer()).opt())
+        .map(|((name, fields), discriminant)| RustVariant { name, fields, discriminant })
+}
+
+/// Lifetime parameters of an item, which do not change its layout.
+fn lifetimes<'a>() -> Parser<'a, ()> {
+    (rust_punct('<') * list(lifetime(), rust_punct(',')) - rust_punct(',').opt() - rust_punct('>')).discard()
+}
+
+fn rust_item<'a>() -> Parser<'a, RustItem> {
+    let structure = rust_keyword("struct") * rust_identifier() - lifetimes().opt()
+        + (named_fields().map(RustFields::Named)
+            | (tuple_fields().map(RustFields::Tuple) - rust_punct(';'))
+            | rust_punct(';').map(|_| RustFields::Unit))
+            .map(RustBody::Struct);
+    let union = rust_keyword("union") * rust_identifier() - lifetimes().opt() + named_fields().map(RustBody::Union);
+    let enumeration = rust_keyword("enum") * rust_identifier() - lifetimes().opt()
+        + (rust_punct('{') * list(rust_variant(), rust_punct(',')) - rust_punct(',').opt() - rust_punct('}'))
+            .map(RustBody::Enum);
+
+    (attribute().repeat(0..) - visibility().opt() + (structure | union | enumeration))
+        .map(|(attributes, (name, body))| RustItem { name, repr: attributes.concat(), body })
 }
 
-pub fn field_decl<'a>() -> Parser<'a, ()> {
-    (
-        (label() - some_space())
-        + ((sym('*') | sym('&')).opt() - some_space())
-        + (label() - some_space())
-        + ((sym('[') - some_space()) + integer() - (some_space() - sym(']')))
-        - sym(';')
-    ).map(drop)
+/// The `struct`, `union` and `enum` items of a Rust snippet.
+pub fn rust_items<'a>() -> Parser<'a, Vec<RustItem>> {
+    rust_space() * many(rust_item()) - end().expect("`struct`, `union` or `enum` item")
 }
 
-pub fn kind_expr<'a>() -> Parser<'a, access::Path> {
-    todo!()
+/// A Rust type such as `Option<&[u8; 4]>`, with nothing after it.
+pub fn rust_type_expr<'a>() -> Parser<'a, RustType> {
+    rust_space() * rust_type() - end().expect("end of type")
 }
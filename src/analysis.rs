@@ -0,0 +1,281 @@
+use std::{cmp::Reverse, fmt};
+
+use svg::{Document, node::element::Group};
+
+use crate::{
+    kind::{
+        Kind, CType,
+        composite::{Composite, Bits, Mode, Order},
+    },
+    block_draw::{BlockDrawSpec, util::{Vec2, Bounds, Translate}},
+    mem_ribbon::MemRibbon,
+};
+
+/// Bytes in a cacheline on most desktop and server CPUs.
+pub const CACHELINE: u64 = 64;
+
+/// A member of an analysed composite.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Member {
+    pub name: Option<String>,
+    pub kind: String,
+    pub offset: u64,
+    pub size: u64,
+    pub bits: Option<Bits>,
+}
+
+/// Unused bytes between two members.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hole {
+    pub offset: u64,
+    pub size: u64,
+    /// Index of the member the hole follows.
+    pub after: usize,
+}
+
+/// Where the bytes of a composite go, as reported by `pahole`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Analysis {
+    pub name: String,
+    pub mode: Mode,
+    /// Members in the order they are placed.
+    pub members: Vec<Member>,
+    pub holes: Vec<Hole>,
+    /// Padding after the last member, up to the size of the composite.
+    pub trailing: u64,
+    pub size: u64,
+    pub cacheline: u64,
+}
+
+impl Analysis {
+    pub fn cachelines(&self) -> u64 {
+        self.size.div_ceil(self.cacheline.max(1))
+    }
+
+    pub fn hole_bytes(&self) -> u64 {
+        self.holes.iter().map(|hole| hole.size).sum()
+    }
+
+    /// Bytes held by at least one member, counting the bytes that bitfields
+    /// share, and those of union members, once.
+    pub fn member_bytes(&self) -> u64 {
+        let mut bytes = 0;
+        let mut end = 0;
+
+        for member in &self.members {
+            let start = member.offset.max(end);
+            end = end.max(member.offset + member.size);
+            bytes += end.saturating_sub(start);
+        }
+
+        bytes
+    }
+
+    /// Bytes that hold no member, between members or at the end.
+    pub fn padding(&self) -> u64 {
+        self.hole_bytes() + self.trailing
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {} {{", self.mode, self.name)?;
+
+        for (index, member) in self.members.iter().enumerate() {
+            let name = member.name.as_deref().unwrap_or_default();
+            let decl = match member.bits {
+                Some(bits) => format!("{name}:{}", bits.width),
+                None => name.to_string(),
+            };
+            let offset = match member.bits {
+                Some(bits) => format!("{}:{:2}", member.offset, bits.offset),
+                None => member.offset.to_string(),
+            };
+            writeln!(f, "\t{:<24} {:<20} /* {offset:>8} {:5} */", member.kind, format!("{decl};"), member.size)?;
+
+            if let Some(hole) = self.holes.iter().find(|hole| hole.after == index) {
+                writeln!(f, "\n\t/* XXX {} bytes hole, try to pack */\n", hole.size)?;
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(f, "\t/* size: {}, cachelines: {}, members: {} */", self.size, self.cachelines(), self.members.len())?;
+        if !self.holes.is_empty() {
+            writeln!(f, "\t/* sum members: {}, holes: {}, sum holes: {} */", self.member_bytes(), self.holes.len(), self.hole_bytes())?;
+        }
+        if self.trailing > 0 {
+            writeln!(f, "\t/* padding: {} */", self.trailing)?;
+        }
+        write!(f, "}};")
+    }
+}
+
+impl<'kind> Composite<'kind> {
+    /// Lists the members, holes and trailing padding of this composite on the active target.
+    pub fn analyze(&self, cacheline: u64) -> Analysis {
+        let layout = self.layout();
+        let fields = self.fields.borrow();
+
+        let mut placed: Vec<_> = fields.iter()
+            .zip(&layout.fields)
+            .filter(|(_, slot)| slot.bits.is_none_or(|bits| bits.width > 0))
+            .collect();
+        placed.sort_by_key(|(_, slot)| slot.offset);
+
+        let mut members = Vec::with_capacity(placed.len());
+        let mut holes = Vec::new();
+        let mut end = 0;
+
+        for (field, slot) in placed {
+            if slot.offset > end && !members.is_empty() {
+                holes.push(Hole { offset: end, size: slot.offset - end, after: members.len() - 1 });
+            }
+            end = end.max(slot.end());
+
+            members.push(Member {
+                name: field.name.clone(),
+                kind: field.kind.to_string(),
+                offset: slot.offset,
+                size: slot.size,
+                bits: slot.bits,
+            });
+        }
+
+        let size = self.size_of();
+
+        Analysis {
+            name: self.name.clone(),
+            mode: self.mode,
+            members,
+            holes,
+            trailing: size.saturating_sub(end),
+            size,
+            cacheline,
+        }
+    }
+
+    /// The same struct with its fields in decreasing order of alignment, which
+    /// leaves the least padding.
+    ///
+    /// Bitfields sharing a storage unit move together, and an array sized at
+    /// runtime stays last. A union comes back as the same union.
+    pub fn reordered(&self) -> Composite<'kind> {
+        let fields = self.fields.borrow();
+        let mut runs = self.layout().runs();
+
+        if self.mode == Mode::Product {
+            runs.sort_by_key(|run| {
+                let field = &fields[run.start];
+                let runtime = matches!(field.kind, Kind::Array(array) if !array.is_fixed());
+                (runtime, Reverse(self.field_align(field)))
+            });
+        }
+
+        let reordered = runs.into_iter()
+            .flat_map(|run| fields[run].to_vec())
+            .collect();
+
+        let mut repr = self.repr.get();
+        repr.order = Order::C;
+        Composite { mode: self.mode, ..Composite::product(&self.name, reordered) }.repr(repr)
+    }
+
+    /// Draws the ribbon of this composite beside that of its `reordered` version.
+    pub fn draw_reordering(&self, spec: &BlockDrawSpec) -> (Group, Bounds) {
+        let before = MemRibbon::new(0)
+            .span_with(&self.name, self.fields.borrow().clone(), self.repr.get());
+        let after = self.reordered();
+        let after = MemRibbon::new(0)
+            .span_with(&after.name, after.fields.into_inner(), after.repr.get());
+
+        let (before_group, mut bounds) = before.draw(Vec2::ZERO, spec, true, true);
+        let (after_group, after_bounds) = after.draw(Vec2::ZERO, spec, true, true);
+
+        let shift = Vec2::x(bounds.max.x - after_bounds.min.x + spec.line_height());
+        bounds.expand(Bounds {
+            min: after_bounds.min + shift,
+            max: after_bounds.max + shift,
+        });
+
+        let group = Group::new()
+            .add(before_group)
+            .add(after_group.set("transform", Translate::from(shift)));
+
+        (group, bounds)
+    }
+
+    pub fn save_reordering_svg(&self, file_name: impl AsRef<std::path::Path>, spec: &BlockDrawSpec) {
+        let (group, bounds) = self.draw_reordering(spec);
+
+        let document = Document::new()
+            .set("viewBox", bounds.svg_viewbox())
+            .add(group);
+
+        svg::save(file_name, &document).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kind::{leak, primitive::Primitive, composite::Field};
+
+    fn char_int_char() -> Composite<'static> {
+        let char_ = leak(Primitive::Char);
+        let int32_t = leak(Primitive::I32);
+
+        Composite::product("s", vec![
+            char_.field_named("a"),
+            int32_t.field_named("b"),
+            char_.field_named("c"),
+        ])
+    }
+
+    #[test]
+    fn holes() {
+        let analysis = char_int_char().analyze(CACHELINE);
+
+        assert_eq!(analysis.size, 12);
+        assert_eq!(analysis.holes, vec![Hole { offset: 1, size: 3, after: 0 }]);
+        assert_eq!(analysis.trailing, 3);
+        assert_eq!(analysis.padding(), 6);
+        assert_eq!(analysis.cachelines(), 1);
+        assert_eq!(char_int_char().analyze(8).cachelines(), 2);
+    }
+
+    #[test]
+    fn reordering() {
+        let reordered = char_int_char().reordered();
+        let names: Vec<_> = reordered.fields.borrow().iter()
+            .map(|field| field.name.clone().unwrap())
+            .collect();
+
+        assert_eq!(names, vec!["b", "a", "c"]);
+        assert_eq!(reordered.size_of(), 8);
+        assert_eq!(reordered.analyze(CACHELINE).padding(), 2);
+    }
+
+    #[test]
+    fn union_reordering() {
+        let fields = char_int_char().fields.into_inner();
+        let reordered = Composite::sum("u", fields).reordered();
+
+        assert_eq!(reordered.mode, Mode::Sum);
+        assert_eq!(reordered.size_of(), 4);
+        assert_eq!(reordered.analyze(CACHELINE).member_bytes(), 4);
+    }
+
+    #[test]
+    fn shared_bitfield_bytes() {
+        let uint8_t = leak(Primitive::U8);
+        let flags = Composite::product("flags", vec![
+            Field::bitfield("a", uint8_t, 3),
+            Field::bitfield("b", uint8_t, 3),
+            leak(Primitive::U16).field_named("c"),
+        ]);
+        let analysis = flags.analyze(CACHELINE);
+
+        assert_eq!(analysis.member_bytes(), 3);
+        assert!(analysis.to_string().contains("sum members: 3, holes: 1, sum holes: 1"));
+    }
+}
@@ -14,6 +14,8 @@ use svg::node::element::Group;
 pub struct BlockBodyPlan {
     pub block_width: f32,
     pub notch: bool,
+    /// Height of the block in bytes.
+    pub size: u64,
 }
 
 pub struct BlockDiagPlan<'kind> {
@@ -45,6 +47,10 @@ impl<'kind> BlockDiagPlan<'kind> {
             for block in self.sub_blocks.iter_mut() {
                 block.setup_nodes(graph, Some(index))
             }
+        } else {
+            for block in self.sub_blocks.iter_mut() {
+                block.setup_nodes(graph, parent)
+            }
         }
     }
 
@@ -75,6 +81,16 @@ impl<'kind> BlockDiagPlan<'kind> {
         graph: &mut StableGraph<(), (), Undirected>,
         left_side: Option<NodeIndex>,
     ) -> (Vec<BlockAdjSpan>, Vec<BlockAdjSpan>)
+    {
+        self.cap_span(self.block_stack(graph, left_side))
+    }
+
+    /// Connects the sub-blocks top to bottom, without a block of its own around them.
+    pub fn block_stack(
+        &self,
+        graph: &mut StableGraph<(), (), Undirected>,
+        left_side: Option<NodeIndex>,
+    ) -> (Vec<BlockAdjSpan>, Vec<BlockAdjSpan>)
     {
         let mut interface: Option<(Vec<BlockAdjSpan>,Vec<BlockAdjSpan>)> = None;
         for block in &self.sub_blocks {
@@ -94,7 +110,7 @@ impl<'kind> BlockDiagPlan<'kind> {
             }
         };
 
-        self.cap_span(interface.unwrap_or_default())
+        interface.unwrap_or_default()
     }
 
     pub fn block_sum_row(
@@ -150,14 +166,21 @@ impl<'kind> BlockDiagPlan<'kind> {
             return Default::default();
         }
 
-        match self.kind {
+        if self.body_plan.is_none() {
+            return self.block_stack(graph, left_side);
+        }
+
+        match self.kind.unqualified() {
             Kind::Composite(comp) => match comp.mode {
                 composite::Mode::Product => self.block_product_stack(graph, left_side),
                 composite::Mode::Sum => self.block_sum_row(graph,left_side),
             }
             Kind::Array(_) => self.block_product_stack(graph, left_side),
+            Kind::Tagged(_) => self.block_sum_row(graph, left_side),
             _ => {
-                let index = self.graph_index.unwrap();
+                let Some(index) = self.graph_index else {
+                    return Default::default();
+                };
                 let spans = vec![BlockAdjSpan {
                     min: self.mins.x,
                     max: self.maxs.x,
@@ -178,10 +201,10 @@ impl<'kind> BlockDiagPlan<'kind> {
         let base_pos = self.relative_pos.unwrap_or_default();
 
         let base_group = self.body_plan.as_ref()
-            .map(|&BlockBodyPlan { block_width, notch }| {
+            .map(|&BlockBodyPlan { block_width, notch, size }| {
                 let tone = colors[color_map[&self.graph_index.unwrap()]];
 
-                self.spec.draw_block(self.kind, block_width, notch)
+                self.spec.draw_block(size, block_width, notch)
                     .map(|group| group.set("fill", tone))
                     .unwrap_or_default()
             })
@@ -5,7 +5,15 @@ use svg::node::element::{
     path::Data
 };
 
-use crate::kind::{Kind, composite::{self, Field, Composite}, array::Array, CType};
+use crate::{
+    kind::{
+        Kind, CType,
+        composite::{self, Field, Composite},
+        array::{Array, Length},
+        tagged::{Tagged, Tag},
+    },
+    mem_ribbon::MemRibbon,
+};
 
 pub mod block_plan;
 pub mod util;
@@ -14,6 +22,23 @@ use util::Vec2;
 
 use self::block_plan::BlockDiagPlan;
 
+/// One column of a tagged enum diagram: an optional header at a given
+/// offset, and fields at their offsets.
+struct TaggedColumn<'kind> {
+    header: Option<(String, u64)>,
+    fields: Vec<(Field<'kind>, u64)>,
+}
+
+/// Where a kind being planned is stored, so that arrays whose length is
+/// only known at runtime are drawn with the length found in memory.
+#[derive(Clone, Copy)]
+pub struct RibbonPlace<'ribbon, 'kind> {
+    pub ribbon: &'ribbon MemRibbon<'kind>,
+    pub address: usize,
+    /// Runtime length, when the kind is an array sized by its composite.
+    pub len: Option<usize>,
+}
+
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct BlockDrawSpec {
     pub char_dims: Vec2,
@@ -26,6 +51,22 @@ pub struct BlockDrawSpec {
     pub chamfer_size: f32,
 }
 
+/// Spacing for 10 by 16 pixel characters, as used by the examples.
+impl Default for BlockDrawSpec {
+    fn default() -> Self {
+        BlockDrawSpec {
+            char_dims:  Vec2::new(10.0, 16.0),
+            text_pads:  Vec2::new( 4.0,  4.0),
+            label_pads: Vec2::new(10.0,  4.0),
+            union_xpad: 6.0,
+            fill_inset: 3.0,
+            prong_width: 12.0,
+            prong_xpad: 6.0,
+            chamfer_size: 12.0,
+        }
+    }
+}
+
 impl BlockDrawSpec {
     fn label_height(&self) -> f32 {
         self.char_dims.y + self.text_pads.y * 2.0
@@ -95,8 +136,7 @@ impl BlockDrawSpec {
         Group::new().add(path).add(text)
     }
 
-    pub fn draw_block(&self, kind: &Kind, width: f32, notch: bool) -> Option<Group> {
-        let size = kind.size_of();
+    pub fn draw_block(&self, size: u64, width: f32, notch: bool) -> Option<Group> {
         if size <= 1 { return None; }
 
         let head_height = self.line_height();
@@ -200,14 +240,19 @@ impl BlockDrawSpec {
     }
 
     pub fn composite_member_width<'kind>(&self,comp: &Composite<'kind>) -> f32 {
+        let fields = comp.fields.borrow();
+
         match comp.mode {
-            composite::Mode::Product => comp.fields.borrow().iter()
-                .enumerate()
-                .map(|(i, f)| self.field_width(f, i==0))
+            composite::Mode::Product => comp.layout().runs().into_iter()
+                .map(|run| fields[run].iter()
+                    .enumerate()
+                    .map(|(i, f)| self.field_width(f, i==0))
+                    .sum()
+                )
                 .max_by(f32::total_cmp)
                 .unwrap_or_default()
                     + self.prong_xpad,
-            composite::Mode::Sum => comp.fields.borrow().iter()
+            composite::Mode::Sum => fields.iter()
                 .map(|x| self.field_width(x, false)
                     + self.union_xpad
                     + self.prong_width
@@ -217,7 +262,73 @@ impl BlockDrawSpec {
         }
     }
 
-    pub fn array_member_width(&self, Array { kind, size }: Array) -> f32 {
+    /// Splits a tagged enum into the tag and its variants, overlaid side by side.
+    ///
+    /// When the tag lives in a niche, the dataful variant has no header of its
+    /// own, so its fields are labeled with the variant name instead.
+    fn tagged_columns<'kind>(&self, tagged: &Tagged<'kind>) -> Vec<TaggedColumn<'kind>> {
+        let layout = tagged.layout();
+
+        let mut columns = match layout.tag {
+            Tag::Direct(prim) => vec![TaggedColumn {
+                header: Some((format!("tag: {prim}"), 0)),
+                fields: Vec::new(),
+            }],
+            _ => Vec::new(),
+        };
+
+        for (index, variant) in tagged.variants.iter().enumerate() {
+            let header = match layout.tag {
+                Tag::Direct(_) => Some((variant.name.clone(), 0)),
+                Tag::Niche { dataful, niche, .. } if index != dataful =>
+                    Some((variant.name.clone(), niche.offset)),
+                _ => None,
+            };
+
+            let fields = variant.fields.iter()
+                .zip(&layout.offsets[index])
+                .map(|(field, offset)| {
+                    let mut field = field.clone();
+                    if header.is_none() && tagged.variants.len() > 1 {
+                        let name = field.name.unwrap_or_default();
+                        field.name = Some(format!("{}.{name}", variant.name));
+                    }
+                    (field, *offset)
+                })
+                .collect();
+
+            columns.push(TaggedColumn { header, fields });
+        }
+
+        columns
+    }
+
+    fn tagged_column_width(&self, column: &TaggedColumn) -> f32 {
+        let header_width = column.header.as_ref()
+            .map(|(title, _)| self.padded_width(title))
+            .unwrap_or_default();
+
+        column.fields.iter()
+            .map(|(field, _)| self.field_width(field, false))
+            .fold(header_width, f32::max)
+    }
+
+    pub fn tagged_member_width<'kind>(&self, tagged: &Tagged<'kind>) -> f32 {
+        self.tagged_columns(tagged).iter()
+            .map(|column| self.tagged_column_width(column)
+                + self.union_xpad
+                + self.prong_width
+                + self.prong_xpad
+            )
+            .sum()
+    }
+
+    pub fn array_member_width(&self, Array { kind, size, length, .. }: Array) -> f32 {
+        // The length of a runtime array is not known here, so leave room for two-digit indices.
+        let size = match length {
+            Length::Fixed => size,
+            _ => size.max(100),
+        };
         if size == 0 { return 0.0; }
 
         let first_field = Field::new(0, kind);
@@ -230,16 +341,17 @@ impl BlockDrawSpec {
     }
 
     pub fn member_width<'kind>(&self, kind: &Kind<'kind>) -> f32 {
-        match kind {
+        match kind.unqualified() {
             Kind::Composite(comp) => self.composite_member_width(comp),
             Kind::Array(array) => self.array_member_width(*array),
+            Kind::Tagged(tagged) => self.tagged_member_width(tagged),
             _ => 0.0,
         }
     }
 
     pub fn unlabeled_width(&self, kind: &Kind<'_>, _notch: bool) -> f32 {
-        let prong_width = match kind {
-            Kind::Composite(_) | Kind::Array(_) => self.prong_width,
+        let prong_width = match kind.unqualified() {
+            Kind::Composite(_) | Kind::Array(_) | Kind::Tagged(_) => self.prong_width,
             _ => 0.0,
         };
 
@@ -253,7 +365,12 @@ impl BlockDrawSpec {
     }
 
     pub fn field_width(&self, field: &Field, notch: bool) -> f32 {
-        self.unlabeled_width(field.kind, notch)
+        let unlabeled_width = match field.bits {
+            Some(_) => self.padded_width(&field.kind_name()),
+            None => self.unlabeled_width(field.kind, notch),
+        };
+
+        unlabeled_width
             + self.label_width(field.name.as_deref().unwrap_or_default())
             + self.label_pads.x * 2.0
     }
@@ -288,9 +405,31 @@ impl BlockDrawSpec {
         }
     }
 
+    /// Plans a bitfield as a bare header, since it may share its bytes with its neighbours.
+    pub fn plan_bitfield<'kind>(
+        &self,
+        field: &Field<'kind>,
+        mins: Vec2,
+        width: f32,
+        with_notch: bool,
+    ) -> BlockDiagPlan<'kind> {
+        BlockDiagPlan {
+            spec: *self,
+            head: self.draw_header(field.kind_name(), width, with_notch),
+            head_offset: 0.0,
+            body_plan: None,
+            relative_pos: None,
+            mins,
+            maxs: mins + Vec2::new(width, self.line_height()),
+            kind: field.kind,
+            sub_blocks: Vec::new(),
+            graph_index: None,
+        }
+    }
+
     pub fn plan_array_fields<'kind>(
         &self,
-        Array { size, kind }: Array<'kind>,
+        Array { size, kind, .. }: Array<'kind>,
         mins: Vec2,
         width: f32,
     ) -> Vec<BlockDiagPlan<'kind>> {
@@ -313,7 +452,8 @@ impl BlockDrawSpec {
                 self,
                 mins + deltas,
                 Some(width),
-                notch
+                notch,
+                None,
             );
 
             f_plan.relative_pos  = Some(deltas);
@@ -328,38 +468,50 @@ impl BlockDrawSpec {
 
     pub fn plan_product_fields<'kind>(
         &self,
-        fields: &Vec<Field<'kind>>,
+        comp: &Composite<'kind>,
         mins: Vec2,
         width: f32,
+        place: Option<RibbonPlace>,
     ) -> Vec<BlockDiagPlan<'kind>> {
+        let fields = comp.fields.borrow();
+        let layout = comp.layout();
         let mut field_plans = Vec::with_capacity(fields.len());
-        let mut offset = 0;
-        let mut deltas = Vec2::ZERO;
-
-        for field in fields {
-            let pad = field.kind.align_pad(offset);
-            let pad_height = pad as f32 * self.line_height();
-            let size = field.kind.size_of();
-            let size_height = size as f32 * self.line_height();
 
-            deltas.y += pad_height;
-
-            println!("{:?}", deltas + mins);
-
-            let has_notch = offset == 0;
-
-            let mut f_plan = field.make_plan(
-                self,
-                mins+deltas,
-                Some(width),
-                has_notch
-            );
-
-            f_plan.relative_pos = Some(deltas);
-
-            field_plans.push(f_plan);
-            deltas.y += size_height;
-            offset += size + pad;
+        for run in layout.runs() {
+            let mut deltas = Vec2::y(layout.fields[run.start].offset as f32 * self.line_height());
+
+            for index in run.clone() {
+                let (field, slot) = (&fields[index], layout.fields[index]);
+                if slot.bits.is_some_and(|bits| bits.width == 0) {
+                    continue;
+                }
+
+                let has_notch = slot.offset == 0 && deltas.x == 0.0;
+                let field_width = if run.len() > 1 {
+                    self.field_width(field, has_notch)
+                } else {
+                    width
+                };
+
+                let field_place = place.map(|place| RibbonPlace {
+                    address: place.address + slot.offset as usize,
+                    len: comp.array_len(index, place.ribbon, place.address),
+                    ..place
+                });
+
+                let mut f_plan = field.make_plan(
+                    self,
+                    mins+deltas,
+                    Some(field_width),
+                    has_notch,
+                    field_place,
+                );
+
+                f_plan.relative_pos = Some(deltas);
+
+                field_plans.push(f_plan);
+                deltas.x += field_width;
+            }
         }
 
         field_plans
@@ -384,7 +536,8 @@ impl BlockDrawSpec {
                 self,
                 mins + deltas,
                 None,
-                NOTCH
+                NOTCH,
+                None,
             );
 
             f_plan.relative_pos = Some(deltas);
@@ -396,19 +549,86 @@ impl BlockDrawSpec {
         field_plans
     }
 
+    /// Plans each column of a tagged enum as a bodiless block holding its
+    /// header and fields, laid out side by side like the fields of a union.
+    pub fn plan_tagged_fields<'kind>(
+        &self,
+        kind: &'kind Kind<'kind>,
+        tagged: &Tagged<'kind>,
+        mins: Vec2,
+    ) -> Vec<BlockDiagPlan<'kind>> {
+        const NOTCH: bool = false;
+        let mut column_plans = Vec::new();
+        let mut deltas = Vec2::ZERO;
+
+        for column in self.tagged_columns(tagged) {
+            let width = self.tagged_column_width(&column);
+            let column_mins = mins + deltas;
+
+            let head = column.header
+                .map(|(title, offset)| Group::new().add(self.draw_header(title, width, NOTCH)
+                    .set("transform", util::Translate(0.0, offset as f32 * self.line_height()))
+                ))
+                .unwrap_or_default();
+
+            let sub_blocks = column.fields.iter()
+                .map(|(field, offset)| {
+                    let field_deltas = Vec2::y(*offset as f32 * self.line_height());
+                    let mut f_plan = field.make_plan(
+                        self,
+                        column_mins + field_deltas,
+                        Some(width),
+                        NOTCH,
+                        None,
+                    );
+                    f_plan.relative_pos = Some(field_deltas);
+                    f_plan
+                })
+                .collect();
+
+            column_plans.push(BlockDiagPlan {
+                spec: *self,
+                head,
+                head_offset: 0.0,
+                body_plan: None,
+                relative_pos: Some(deltas),
+                mins: column_mins,
+                maxs: column_mins + Vec2::new(width, self.height(kind)),
+                kind,
+                sub_blocks,
+                graph_index: None,
+            });
+
+            deltas.x += width
+                + self.union_xpad
+                + self.prong_width
+                + self.prong_xpad;
+        }
+
+        column_plans
+    }
+
     pub fn make_plan<'kind>(
         &self,
         kind: &'kind Kind<'kind>,
         mins: Vec2,
         width: Option<f32>,
         with_notch: bool,
+        place: Option<RibbonPlace>,
     ) -> BlockDiagPlan<'kind> {
+        let size = match (kind, place) {
+            (Kind::Array(array), Some(RibbonPlace { len: Some(len), .. })) =>
+                array.with_len(len).byte_len(),
+            (_, Some(place)) => kind.extent(place.ribbon, place.address),
+            (_, None) => kind.size_of() as usize,
+        } as u64;
+
         let block_width = width.unwrap_or_else(|| self.unlabeled_width(kind, with_notch));
 
         let (head, head_offset) = {
             let member_width = self.member_width(kind);
-            let prong_padding = match kind {
-                Kind::Composite(_) | Kind::Array(_) => self.prong_xpad,
+            let prong_padding = match kind.unqualified() {
+                Kind::Composite(_) | Kind::Array(_) | Kind::Tagged(_) => self.prong_xpad,
                 _ => 0.0,
             };
             let head = self.draw_header(
@@ -420,12 +640,13 @@ impl BlockDrawSpec {
             (head, member_width + prong_padding)
         };
 
-        let fields = match kind {
+        let fields = match kind.unqualified() {
             Kind::Composite(comp) => match comp.mode {
                 composite::Mode::Product => self.plan_product_fields(
-                    &comp.fields.borrow(),
+                    comp,
                     mins,
-                    self.member_width(kind)
+                    self.member_width(kind),
+                    place,
                 ),
                 composite::Mode::Sum => self.plan_sum_fields(
                     &comp.fields.borrow(),
@@ -433,10 +654,11 @@ impl BlockDrawSpec {
                 ),
             },
             Kind::Array(array) => self.plan_array_fields(
-                    *array,
+                    array.with_len(place.and_then(|place| place.len).unwrap_or(array.size)),
                     mins,
                     self.member_width(kind)
                 ),
+            Kind::Tagged(tagged) => self.plan_tagged_fields(kind, tagged, mins),
             _ => Default::default(),
         };
 
@@ -448,9 +670,10 @@ impl BlockDrawSpec {
             body_plan: Some(block_plan::BlockBodyPlan {
                 block_width,
                 notch: with_notch,
+                size,
             }),
             mins,
-            maxs: mins + Vec2::new(block_width, self.height(kind)),
+            maxs: mins + Vec2::new(block_width, self.line_height() * size as f32),
             kind,
             sub_blocks: fields,
             graph_index: None,
@@ -466,9 +689,10 @@ impl BlockDrawSpec {
         let fields = match kind {
             Kind::Composite(comp) => match comp.mode {
                 composite::Mode::Product => self.plan_product_fields(
-                    &comp.fields.borrow(),
+                    comp,
                     mins,
                     width,
+                    None,
                 ),
                 _ => unreachable!(),
             },
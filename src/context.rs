@@ -1,17 +1,843 @@
-#![allow(dead_code)]
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
 
 use typed_arena::Arena;
 
-use crate::kind::Kind;
+use crate::{
+    access::{
+        diagnostic::Diagnostic,
+        parse::{
+            self, Declaration, Declarator, Derivation, Specifier, TypeName, TypeSpec,
+            ReprHint, RustBody, RustFields, RustItem, RustType,
+        },
+    },
+    kind::{
+        Kind, CType, Alias,
+        array::Array,
+        composite::{self, Composite, Field, Order, Repr},
+        enumeration::{self, Enum},
+        function::Function,
+        tagged::{self, Tagged},
+        primitive::Primitive,
+        qualified::Qualified,
+        reference::{self, Reference},
+    },
+    target::Target,
+};
 
-struct Context <'context> {
-    kinds: Arena<Kind<'context>>,
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// No type has been declared under this name.
+    Unknown(String),
+    /// The name is already taken by a type that is not a matching declaration.
+    Redefined(String),
+    /// A type is held by value while it is only declared.
+    Incomplete(String),
+    /// The size of a type does not fit in the target's `size_t`.
+    Overflow(String),
+    /// A generic was given the wrong number of type arguments; it takes the count given.
+    Arity(String, usize),
+    /// C or Rust source that could not be parsed, marked where it went wrong.
+    Syntax(Diagnostic),
 }
 
-impl <'context> Context <'context> {
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Unknown(name) => write!(f, "Unknown type {name}"),
+            Error::Redefined(name) => write!(f, "Type {name} is already defined"),
+            Error::Incomplete(name) => write!(f, "Type {name} is declared but never defined"),
+            Error::Overflow(name) => write!(f, "Type {name} is too large for size_t"),
+            Error::Arity(name, count) => write!(f, "Type {name} takes {count} type arguments"),
+            Error::Syntax(message) => write!(f, "Invalid declaration: {message}"),
+        }
+    }
+}
+
+/// Builds the fields of one instantiation of a generic from the context, the
+/// instantiation itself, so members can point back at it, and the type arguments.
+pub type Body<'kind> = fn(&'kind Context<'kind>, &'kind Kind<'kind>, &[&'kind Kind<'kind>]) -> Vec<Field<'kind>>;
+
+/// A struct or union with type parameters, such as `Pair<A, B>`.
+pub struct Generic<'kind> {
+    pub name: String,
+    pub mode: composite::Mode,
+    pub params: Vec<String>,
+    pub body: Body<'kind>,
+}
+
+/// Owns every kind of a program and registers them by name.
+///
+/// Kinds are allocated for as long as the context lives, so they can refer to
+/// each other freely. Structs and unions may be declared before they are
+/// defined, which ties recursive types together.
+///
+/// A context checks sizes against its own target, taken from the active
+/// target when it is made, so [`Self::resolve`] gives the same answer on
+/// every thread whatever target is active when it is called.
+pub struct Context<'kind> {
+    pub target: Target,
+    kinds: Arena<Kind<'kind>>,
+    names: RefCell<HashMap<String, &'kind Kind<'kind>>>,
+    generics: RefCell<HashMap<String, Rc<Generic<'kind>>>>,
+}
+
+impl<'kind> Context<'kind> {
     pub fn new() -> Self {
         Context {
-            kinds:  Arena::new(),
+            target: Target::active(),
+            kinds: Arena::new(),
+            names: RefCell::default(),
+            generics: RefCell::default(),
+        }
+    }
+
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Allocates an unnamed kind, such as a pointer or array type.
+    pub fn add(&'kind self, kind: impl Into<Kind<'kind>>) -> &'kind Kind<'kind> {
+        self.kinds.alloc(kind.into())
+    }
+
+    /// Registers `kind` under `name`.
+    ///
+    /// A composite completes an earlier declaration of the same name in place,
+    /// so the kinds already pointing at it see its fields.
+    pub fn define(&'kind self, name: impl ToString, kind: impl Into<Kind<'kind>>) -> Result<&'kind Kind<'kind>, Error> {
+        let name = name.to_string();
+        let kind = kind.into();
+
+        let Some(existing) = self.get(&name) else {
+            let kind = self.add(kind);
+            self.names.borrow_mut().insert(name, kind);
+            return Ok(kind);
+        };
+
+        match (existing, kind) {
+            (Kind::Composite(declared), Kind::Composite(comp))
+                if !declared.defined.get() && declared.mode == comp.mode =>
+            {
+                declared.define(comp.fields.into_inner(), comp.repr.get());
+                Ok(existing)
+            }
+            _ => Err(Error::Redefined(name)),
+        }
+    }
+
+    /// Declares a struct or union without fields, as with `struct node;`.
+    ///
+    /// Declaring a name again returns the kind it already refers to.
+    pub fn declare(&'kind self, name: impl ToString, mode: composite::Mode) -> &'kind Kind<'kind> {
+        let name = name.to_string();
+
+        if let Some(existing) = self.get(&name) {
+            return existing;
+        }
+
+        let kind = self.add(Composite::declare(&name, mode));
+        self.names.borrow_mut().insert(name, kind);
+        kind
+    }
+
+    pub fn get(&self, name: &str) -> Option<&'kind Kind<'kind>> {
+        self.names.borrow().get(name).copied()
+    }
+
+    /// Registers a generic struct or union taking the type parameters `params`.
+    pub fn generic(
+        &self,
+        name: impl ToString,
+        mode: composite::Mode,
+        params: &[&str],
+        body: Body<'kind>,
+    ) -> Result<(), Error> {
+        let name = name.to_string();
+        if self.get(&name).is_some() || self.generics.borrow().contains_key(&name) {
+            return Err(Error::Redefined(name));
+        }
+
+        let params = params.iter().map(ToString::to_string).collect();
+        let generic = Generic { name: name.clone(), mode, params, body };
+        self.generics.borrow_mut().insert(name, Rc::new(generic));
+        Ok(())
+    }
+
+    /// The generic `name` applied to `args`, named like `Pair<uint8_t, double>`.
+    ///
+    /// Each instantiation is laid out once and registered under its name, so
+    /// instantiating it again returns the same kind.
+    pub fn instantiate(&'kind self, name: &str, args: &[&'kind Kind<'kind>]) -> Result<&'kind Kind<'kind>, Error> {
+        let generic = self.generics.borrow().get(name).cloned()
+            .ok_or_else(|| Error::Unknown(name.to_string()))?;
+
+        if generic.params.len() != args.len() {
+            return Err(Error::Arity(name.to_string(), generic.params.len()));
+        }
+
+        let args_text = args.iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let full_name = format!("{name}<{args_text}>");
+
+        if let Some(existing) = self.get(&full_name) {
+            return Ok(existing);
+        }
+
+        let kind = self.declare(&full_name, generic.mode);
+        let fields = (generic.body)(self, kind, args);
+        let comp = match generic.mode {
+            composite::Mode::Product => Composite::product(&full_name, fields),
+            composite::Mode::Sum => Composite::sum(&full_name, fields),
+        };
+
+        self.define(full_name, comp)
+    }
+
+    /// The kind registered as `name`, once everything it holds by value is
+    /// defined and its size fits the context's target.
+    pub fn resolve(&self, name: &str) -> Result<&'kind Kind<'kind>, Error> {
+        let _target = self.target.enter();
+        let kind = self.get(name).ok_or_else(|| Error::Unknown(name.to_string()))?;
+
+        if let Some(incomplete) = kind.incomplete() {
+            return Err(Error::Incomplete(incomplete));
+        }
+
+        if kind.size_of() > self.target.size_max() {
+            return Err(Error::Overflow(name.to_string()));
+        }
+
+        Ok(kind)
+    }
+
+    /// Declares the structs, unions and typedefs of a C header snippet.
+    ///
+    /// Objects declared by the snippet, such as `int *(*x)[4];`, are returned
+    /// as fields, ready to lay out in a span. Function prototypes declare no
+    /// object and are left out.
+    pub fn parse_c(&'kind self, source: &str) -> Result<Vec<Field<'kind>>, Error> {
+        let chars = source.chars().collect::<Vec<_>>();
+        let declarations = parse::declarations().parse(&chars)
+            .map_err(|err| Error::Syntax(Diagnostic::syntax(source, &err)))?;
+
+        let mut objects = Vec::new();
+        for declaration in &declarations {
+            objects.extend(self.declaration(declaration)?);
         }
+        Ok(objects)
+    }
+
+    /// The kind of a C type name such as `const char *[4]`.
+    pub fn parse_kind(&'kind self, source: &str) -> Result<&'kind Kind<'kind>, Error> {
+        let chars = source.chars().collect::<Vec<_>>();
+        let type_name = parse::kind_expr().parse(&chars)
+            .map_err(|err| Error::Syntax(Diagnostic::syntax(source, &err)))?;
+
+        self.type_name(&type_name)?.ok_or_else(|| Error::Incomplete("void".to_string()))
+    }
+
+    /// Registers the types a declaration defines, returning the fields it declares.
+    fn declaration(&'kind self, declaration: &Declaration) -> Result<Vec<Field<'kind>>, Error> {
+        // `typedef struct { ... } name;` names the struct itself.
+        let hint = match &declaration.declarators[..] {
+            [Declarator { name: Some(name), derivations, .. }]
+                if declaration.typedef && derivations.is_empty() => Some(name.as_str()),
+            _ => None,
+        };
+        let base = self.type_spec(&declaration.spec, hint)?;
+
+        if declaration.declarators.is_empty() {
+            return Ok(match (&declaration.spec.specifier, base) {
+                (Specifier::Composite { tag: None, .. }, Some(kind)) => vec![Field::anon(kind)],
+                _ => Vec::new(),
+            });
+        }
+
+        let mut fields = Vec::new();
+        for declarator in &declaration.declarators {
+            let kind = self.derive(base, &declarator.derivations)?
+                .ok_or_else(|| Error::Incomplete("void".to_string()))?;
+
+            // A prototype such as `int f(int);` declares a function, not an object.
+            let prototype = matches!(declarator.derivations.last(), Some(Derivation::Function(_)));
+            if prototype && !declaration.typedef {
+                continue;
+            }
+
+            match (&declarator.name, declarator.bits) {
+                (Some(name), _) if declaration.typedef => {
+                    if !matches!(self.get(name), Some(existing) if std::ptr::eq(existing, kind)) {
+                        self.define(name, Alias::new(name, kind))?;
+                    }
+                }
+                (Some(name), None) => fields.push(Field::new(name, kind)),
+                (Some(name), Some(width)) => fields.push(Field::bitfield(name, kind, width)),
+                (None, Some(width)) => fields.push(Field::anon_bitfield(kind, width)),
+                (None, None) => fields.push(Field::anon(kind)),
+            }
+        }
+
+        Ok(fields)
+    }
+
+    /// The kind a declaration starts with, or `None` for `void`.
+    ///
+    /// An anonymous struct or union is named `hint` when one is given.
+    fn type_spec(&'kind self, spec: &TypeSpec, hint: Option<&str>) -> Result<Option<&'kind Kind<'kind>>, Error> {
+        let kind = match &spec.specifier {
+            Specifier::Builtin(words) => match builtin(words) {
+                Some(prim) => self.add(prim),
+                None => return Ok(None),
+            },
+            Specifier::Named(name) => match (self.get(name), Primitive::named(name)) {
+                (Some(kind), _) => kind,
+                (None, Some(prim)) => self.add(prim),
+                (None, None) => return Err(Error::Unknown(name.clone())),
+            },
+            Specifier::Composite { mode, tag: Some(tag), members: None } => self.declare(tag, *mode),
+            Specifier::Composite { mode, tag, members: Some(members) } => {
+                let name = match (tag, hint) {
+                    (Some(tag), _) => tag.clone(),
+                    (None, Some(hint)) => hint.to_string(),
+                    (None, None) => match mode {
+                        composite::Mode::Product => "(anonymous struct)".to_string(),
+                        composite::Mode::Sum => "(anonymous union)".to_string(),
+                    },
+                };
+
+                // Declared first, so members can point back at it.
+                if tag.is_some() || hint.is_some() {
+                    self.declare(&name, *mode);
+                }
+
+                let mut fields = Vec::new();
+                for member in members {
+                    fields.extend(self.declaration(member)?);
+                }
+
+                let comp = match mode {
+                    composite::Mode::Product => Composite::product(&name, fields),
+                    composite::Mode::Sum => Composite::sum(&name, fields),
+                };
+                match tag.is_some() || hint.is_some() {
+                    true => self.define(name, comp)?,
+                    false => self.add(comp),
+                }
+            }
+            Specifier::Composite { tag: None, members: None, .. } =>
+                unreachable!("the parser requires a tag or members"),
+        };
+
+        Ok(Some(match spec.qualifiers.is_empty() {
+            true => kind,
+            false => self.add(Qualified::new(kind, spec.qualifiers)),
+        }))
+    }
+
+    pub(crate) fn type_name(&'kind self, type_name: &TypeName) -> Result<Option<&'kind Kind<'kind>>, Error> {
+        let base = self.type_spec(&type_name.spec, None)?;
+        self.derive(base, &type_name.declarator.derivations)
+    }
+
+    /// Applies the derivations of a declarator to `base`, from the type it
+    /// starts with out to the type of the declared name.
+    ///
+    /// `void` is `None`, and only allowed as the return type of a function or
+    /// behind a pointer, where it stands for an opaque type.
+    fn derive(
+        &'kind self,
+        base: Option<&'kind Kind<'kind>>,
+        derivations: &[Derivation],
+    ) -> Result<Option<&'kind Kind<'kind>>, Error> {
+        let void = || Error::Incomplete("void".to_string());
+        let mut kind = base;
+        // Set right after a function type, which is only held through the pointer that follows.
+        let mut function = false;
+
+        for derivation in derivations {
+            kind = Some(match derivation {
+                Derivation::Pointer(qualifiers) => {
+                    let pointer = match (kind, function) {
+                        (Some(kind), true) => kind,
+                        (Some(kind), false) => self.add(Reference::new(reference::Mode::Ptr, kind)),
+                        (None, _) => self.add(Reference::new(
+                            reference::Mode::Ptr,
+                            self.add(Composite::declare("void", composite::Mode::Product)),
+                        )),
+                    };
+                    function = false;
+
+                    match qualifiers.is_empty() {
+                        true => pointer,
+                        false => self.add(Qualified::new(pointer, *qualifiers)),
+                    }
+                }
+                Derivation::Array(length) => {
+                    let element = kind.ok_or_else(void)?;
+                    match length {
+                        Some(length) => self.add(Array::new(element, *length)),
+                        None => self.add(Array::flexible(element)),
+                    }
+                }
+                Derivation::Function(params) => {
+                    let params = params.iter()
+                        .map(|param| self.type_name(param)?.ok_or_else(void))
+                        .collect::<Result<_, _>>()?;
+                    function = true;
+                    self.add(Function::new(params, kind))
+                }
+            });
+        }
+
+        Ok(kind)
+    }
+
+    /// Defines the `struct`, `union` and `enum` items of a Rust snippet,
+    /// returning them in the order they are written.
+    ///
+    /// Items may refer to each other in any order, as they can in Rust.
+    pub fn parse_rust(&'kind self, source: &str) -> Result<Vec<&'kind Kind<'kind>>, Error> {
+        let chars = source.chars().collect::<Vec<_>>();
+        let items = parse::rust_items().parse(&chars)
+            .map_err(|err| Error::Syntax(Diagnostic::syntax(source, &err)))?;
+
+        // Structs and unions are declared up front, so they can be pointed at before they are defined.
+        for item in &items {
+            match item.body {
+                RustBody::Struct(_) => { self.declare(&item.name, composite::Mode::Product); }
+                RustBody::Union(_) => { self.declare(&item.name, composite::Mode::Sum); }
+                RustBody::Enum(_) => (),
+            }
+        }
+
+        // Enums cannot be declared, so the items naming one wait until it is defined.
+        let mut pending: Vec<&RustItem> = items.iter().collect();
+        while !pending.is_empty() {
+            let mut waiting = Vec::new();
+            let mut last_error = None;
+
+            for item in pending.iter().copied() {
+                match self.rust_item(item) {
+                    Ok(()) => (),
+                    Err(Error::Unknown(name)) if items.iter().any(|other| other.name == name) => {
+                        waiting.push(item);
+                        last_error = Some(Error::Unknown(name));
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+
+            if waiting.len() == pending.len() {
+                return Err(last_error.unwrap_or_else(|| Error::Unknown(waiting[0].name.clone())));
+            }
+            pending = waiting;
+        }
+
+        items.iter()
+            .map(|item| self.get(&item.name).ok_or_else(|| Error::Unknown(item.name.clone())))
+            .collect()
+    }
+
+    /// The kind of a Rust type such as `Option<&[u8; 4]>`.
+    pub fn parse_rust_kind(&'kind self, source: &str) -> Result<&'kind Kind<'kind>, Error> {
+        let chars = source.chars().collect::<Vec<_>>();
+        let ty = parse::rust_type_expr().parse(&chars)
+            .map_err(|err| Error::Syntax(Diagnostic::syntax(source, &err)))?;
+
+        self.rust_type(&ty)
+    }
+
+    fn rust_item(&'kind self, item: &RustItem) -> Result<(), Error> {
+        let mut repr = Repr { order: Order::Rust, ..Repr::default() };
+        let mut tag = None;
+
+        for hint in &item.repr {
+            match hint {
+                ReprHint::C | ReprHint::Transparent => repr.order = Order::C,
+                ReprHint::Packed(packed) => repr.packed = Some(packed.unwrap_or(1)),
+                ReprHint::Align(align) => repr.align = Some(*align),
+                ReprHint::Int(name) => tag = Some(rust_primitive(name)
+                    .ok_or_else(|| Error::Unknown(name.clone()))?),
+            }
+        }
+
+        match &item.body {
+            RustBody::Struct(fields) => {
+                let comp = Composite::product(&item.name, self.rust_fields(fields)?).repr(repr);
+                self.define(&item.name, comp)?;
+            }
+            RustBody::Union(fields) => {
+                let fields = self.rust_fields(&RustFields::Named(fields.clone()))?;
+                self.define(&item.name, Composite::sum(&item.name, fields).repr(repr))?;
+            }
+            RustBody::Enum(variants) if variants.iter().all(|variant| variant.fields == RustFields::Unit) => {
+                let mut next = 0;
+                let variants: Vec<_> = variants.iter()
+                    .map(|variant| {
+                        let value = variant.discriminant.unwrap_or(next);
+                        next = value + 1;
+                        enumeration::Variant::new(&variant.name, value)
+                    })
+                    .collect();
+
+                let min = variants.iter().map(|variant| variant.value).min().unwrap_or_default();
+                let max = variants.iter().map(|variant| variant.value).max().unwrap_or_default();
+                let prim = match (tag, repr.order) {
+                    (Some(prim), _) => prim,
+                    (None, Order::C) => Primitive::I32,
+                    (None, Order::Rust) => fitting(min, max),
+                };
+
+                self.define(&item.name, Enum::named(&item.name, prim, variants))?;
+            }
+            // `#[repr(C)]` puts the variants in a union after the tag, while
+            // `#[repr(u8)]` and the like put the tag ahead of each variant.
+            RustBody::Enum(variants) => {
+                let variants = variants.iter()
+                    .map(|variant| Ok(tagged::Variant::new(&variant.name, self.rust_fields(&variant.fields)?)))
+                    .collect::<Result<_, Error>>()?;
+
+                let tagged = match (tag, repr.order) {
+                    (tag, Order::C) => Tagged::new(&item.name, variants).repr_c(tag.unwrap_or(Primitive::I32)),
+                    (Some(prim), Order::Rust) => Tagged::new(&item.name, variants).repr(prim),
+                    (None, Order::Rust) => Tagged::new(&item.name, variants),
+                };
+                self.define(&item.name, tagged)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn rust_fields(&'kind self, fields: &RustFields) -> Result<Vec<Field<'kind>>, Error> {
+        match fields {
+            RustFields::Named(fields) => fields.iter()
+                .map(|(name, ty)| Ok(Field::new(name, self.rust_type(ty)?)))
+                .collect(),
+            RustFields::Tuple(types) => types.iter()
+                .enumerate()
+                .map(|(index, ty)| Ok(Field::new(index, self.rust_type(ty)?)))
+                .collect(),
+            RustFields::Unit => Ok(Vec::new()),
+        }
+    }
+
+    /// The kind registered as `name`, or `kind` registered under that name
+    /// when there is none yet, so types spelled the same way are shared.
+    fn named(&'kind self, name: &str, kind: impl FnOnce() -> Kind<'kind>) -> Result<&'kind Kind<'kind>, Error> {
+        match self.get(name) {
+            Some(existing) => Ok(existing),
+            None => self.define(name, kind()),
+        }
+    }
+
+    fn rust_type(&'kind self, ty: &RustType) -> Result<&'kind Kind<'kind>, Error> {
+        Ok(match ty {
+            RustType::Path(name, args) if args.is_empty() => match rust_primitive(name) {
+                Some(prim) => self.named(name, || Alias::new(name, self.add(prim)).into())?,
+                None if name == "str" => return Err(Error::Incomplete("str".to_string())),
+                None => self.get(name).ok_or_else(|| Error::Unknown(name.clone()))?,
+            },
+            RustType::Path(name, args) => match (name.as_str(), &args[..]) {
+                ("Box", [pointee]) => self.rust_pointer(reference::Mode::Box, pointee)?,
+                ("Option", [some]) => {
+                    let some = self.rust_type(some)?;
+                    self.named(&format!("Option<{some}>"), || Tagged::option(some).into())?
+                }
+                _ => {
+                    let args = args.iter()
+                        .map(|arg| self.rust_type(arg))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    self.instantiate(name, &args)?
+                }
+            },
+            RustType::Ref { ty, .. } => self.rust_pointer(reference::Mode::Ref, ty)?,
+            RustType::Ptr { ty, .. } => self.rust_pointer(reference::Mode::Ptr, ty)?,
+            RustType::Array(element, len) => self.add(Array::new(self.rust_type(element)?, *len).rust()),
+            RustType::Slice(element) => return Err(Error::Incomplete(format!("[{}]", self.rust_type(element)?))),
+            RustType::Tuple(types) => {
+                let fields = self.rust_fields(&RustFields::Tuple(types.clone()))?;
+                let names = fields.iter().map(|field| field.kind.to_string()).collect::<Vec<_>>();
+                let name = match &names[..] {
+                    [single] => format!("({single},)"),
+                    _ => format!("({})", names.join(", ")),
+                };
+                self.named(&name, || Composite::product(&name, fields).order(Order::Rust).into())?
+            }
+            RustType::Fn(params, ret) => {
+                let params = params.iter()
+                    .map(|param| self.rust_type(param))
+                    .collect::<Result<_, _>>()?;
+                let ret = ret.as_ref().map(|ret| self.rust_type(ret)).transpose()?;
+                self.add(Function::rust(params, ret))
+            }
+        })
+    }
+
+    /// A reference, raw pointer or box to `pointee`, which is fat for slices and `str`.
+    fn rust_pointer(&'kind self, mode: reference::Mode, pointee: &RustType) -> Result<&'kind Kind<'kind>, Error> {
+        let reference = match pointee {
+            RustType::Path(name, args) if name == "str" && args.is_empty() =>
+                Reference::str(mode, self.rust_type(&RustType::Path("u8".to_string(), Vec::new()))?),
+            RustType::Slice(element) => Reference::slice(mode, self.rust_type(element)?),
+            pointee => Reference::new(mode, self.rust_type(pointee)?).rust(),
+        };
+        Ok(self.add(reference))
+    }
+}
+
+/// The Rust primitive type spelled `name`, such as `u8` or `usize`.
+fn rust_primitive(name: &str) -> Option<Primitive> {
+    Some(match name {
+        "bool" => Primitive::Bool,
+        "char" => Primitive::RustChar,
+        "u8" => Primitive::U8, "u16" => Primitive::U16, "u32" => Primitive::U32,
+        "u64" => Primitive::U64, "u128" => Primitive::U128,
+        "i8" => Primitive::I8, "i16" => Primitive::I16, "i32" => Primitive::I32,
+        "i64" => Primitive::I64, "i128" => Primitive::I128,
+        "f16" => Primitive::F16, "f32" => Primitive::F32,
+        "f64" => Primitive::F64, "f128" => Primitive::F128,
+        "usize" => Primitive::Size, "isize" => Primitive::SSize,
+        _ => return None,
+    })
+}
+
+/// The smallest integer holding every discriminant from `min` to `max`, as
+/// rustc picks for a fieldless enum without `#[repr]`.
+fn fitting(min: i64, max: i64) -> Primitive {
+    let fits = |lo: i64, hi: i64| min >= lo && max <= hi;
+
+    match min >= 0 {
+        true if fits(0, 0xFF) => Primitive::U8,
+        true if fits(0, 0xFFFF) => Primitive::U16,
+        true if fits(0, 0xFFFF_FFFF) => Primitive::U32,
+        true => Primitive::U64,
+        false if fits(i8::MIN.into(), i8::MAX.into()) => Primitive::I8,
+        false if fits(i16::MIN.into(), i16::MAX.into()) => Primitive::I16,
+        false if fits(i32::MIN.into(), i32::MAX.into()) => Primitive::I32,
+        false => Primitive::I64,
+    }
+}
+
+/// The primitive named by arithmetic keywords such as `unsigned long long`,
+/// or `None` for `void`.
+fn builtin(words: &[String]) -> Option<Primitive> {
+    let has = |word: &str| words.iter().any(|w| w == word);
+    let longs = words.iter().filter(|w| *w == "long").count();
+    let unsigned = has("unsigned");
+
+    Some(match () {
+        _ if has("void") => return None,
+        _ if has("_Bool") => Primitive::Bool,
+        _ if has("char") && unsigned => Primitive::U8,
+        _ if has("char") && has("signed") => Primitive::I8,
+        _ if has("char") => Primitive::Char,
+        _ if has("float") => Primitive::F32,
+        _ if has("double") && longs > 0 => Primitive::F80,
+        _ if has("double") => Primitive::F64,
+        _ if has("__int128") => if unsigned { Primitive::U128 } else { Primitive::I128 },
+        _ if has("short") => if unsigned { Primitive::U16 } else { Primitive::I16 },
+        _ if longs == 1 => if unsigned { Primitive::ULong } else { Primitive::Long },
+        _ if longs > 1 => if unsigned { Primitive::U64 } else { Primitive::I64 },
+        _ => if unsigned { Primitive::U32 } else { Primitive::I32 },
+    })
+}
+
+impl Default for Context<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        kind::{array::Array, primitive::Primitive, reference::{self, Reference}},
+        target::Target,
+    };
+
+    #[test]
+    fn forward_declaration() {
+        let context = Context::new();
+        let int = context.define("int", Primitive::I32).unwrap();
+        let node = context.declare("node", composite::Mode::Product);
+        let next = context.add(Reference::new(reference::Mode::Ptr, node));
+
+        assert_eq!(context.resolve("node").err(), Some(Error::Incomplete("node".to_string())));
+
+        context.define("node", Composite::product("node", vec![
+            int.field_named("value"),
+            next.field_named("next"),
+        ])).unwrap();
+
+        let node = context.resolve("node").unwrap();
+        assert_eq!(node.size_of(), 2 * next.size_of());
+        assert!(context.define("node", Composite::product("node", vec![])).is_err());
+    }
+
+    #[test]
+    fn incomplete_member() {
+        let context = Context::new();
+        let inner = context.declare("inner", composite::Mode::Product);
+        context.define("outer", Composite::product("outer", vec![inner.field_named("x")])).unwrap();
+
+        assert_eq!(context.resolve("outer").err(), Some(Error::Incomplete("inner".to_string())));
+        assert_eq!(context.resolve("missing").err(), Some(Error::Unknown("missing".to_string())));
+    }
+
+    #[test]
+    fn overflow() {
+        let context = Context::new();
+        let uint8_t = context.add(Primitive::U8);
+        context.define("page", Array::new(uint8_t, 70000)).unwrap();
+
+        assert_eq!(context.resolve("page").unwrap().size_of(), 70000);
+
+        let avr = Context::new().target(Target::avr());
+        let uint8_t = avr.add(Primitive::U8);
+        avr.define("page", Array::new(uint8_t, 70000)).unwrap();
+        assert_eq!(avr.resolve("page").err(), Some(Error::Overflow("page".to_string())));
+
+        // The ambient target does not change what either context resolves.
+        let _target = Target::avr().enter();
+        assert!(context.resolve("page").is_ok());
+        let _target = Target::lp64().enter();
+        assert!(avr.resolve("page").is_err());
+    }
+
+    #[test]
+    fn generics() {
+        let _target = Target::lp64().enter();
+        let context = Context::new();
+        let uint8_t = context.add(Primitive::U8);
+        let double = context.add(Primitive::F64);
+
+        context.generic("Pair", composite::Mode::Product, &["A", "B"], |_, _, args| vec![
+            args[0].field_named("first"),
+            args[1].field_named("second"),
+        ]).unwrap();
+        context.generic("Node", composite::Mode::Product, &["T"], |context, this, args| vec![
+            args[0].field_named("value"),
+            context.add(Reference::new(reference::Mode::Ptr, this)).field_named("next"),
+        ]).unwrap();
+
+        let pair = context.instantiate("Pair", &[uint8_t, double]).unwrap();
+        assert_eq!(pair.to_string(), "Pair<uint8_t, double>");
+        assert_eq!(pair.size_of(), 2 * double.align_of());
+        assert!(std::ptr::eq(pair, context.instantiate("Pair", &[uint8_t, double]).unwrap()));
+        assert_eq!(context.instantiate("Pair", &[uint8_t, uint8_t]).unwrap().size_of(), 2);
+
+        let node = context.instantiate("Node", &[uint8_t]).unwrap();
+        assert!(context.resolve("Node<uint8_t>").is_ok());
+        assert_eq!(node.size_of(), 2 * Primitive::Size.size_of());
+        assert_eq!(context.instantiate("Pair", &[uint8_t]).err(), Some(Error::Arity("Pair".to_string(), 2)));
+    }
+
+    #[test]
+    fn c_declarations() {
+        let _target = Target::lp64().enter();
+        let context = Context::new();
+        let objects = context.parse_c(r"
+            #include <stdint.h>
+            typedef struct node {
+                int value;
+                struct node *next; // points back at itself
+            } node_t;
+            union word { uint32_t u; float f; unsigned char bytes[4]; };
+            struct flags { unsigned ready : 1, error : 1; unsigned : 0; char name[]; };
+            typedef struct { long long x, y; } point;
+            /* objects */
+            int *(*x)[4];
+            node_t head;
+            int (*handler)(const char *, void *);
+            int parse(const char *text, node_t *out), *find(int);
+        ").unwrap();
+
+        let names: Vec<_> = objects.iter().map(|field| field.name.clone().unwrap()).collect();
+        assert_eq!(names, ["x", "head", "handler"]);
+
+        assert_eq!(context.resolve("node_t").unwrap().size_of(), 16);
+        assert_eq!(context.resolve("word").unwrap().size_of(), 4);
+        assert_eq!(context.resolve("flags").unwrap().size_of(), 4);
+        assert_eq!(context.resolve("point").unwrap().to_string(), "point");
+        assert_eq!(objects[1].kind.to_string(), "node_t");
+
+        let Kind::Reference(pointer) = objects[0].kind else { panic!("x is not a pointer") };
+        let Kind::Array(array) = pointer.kind else { panic!("x does not point at an array") };
+        assert_eq!(array.size, 4);
+        assert_eq!(array.kind.to_string(), "int32_t*");
+
+        let Kind::Function(handler) = objects[2].kind else { panic!("handler is not a function pointer") };
+        assert_eq!(handler.params.len(), 2);
+        assert_eq!(handler.ret.map(ToString::to_string).as_deref(), Some("int32_t"));
+    }
+
+    #[test]
+    fn c_type_names() {
+        let _target = Target::lp64().enter();
+        let context = Context::new();
+
+        assert_eq!(context.parse_kind("const char *[4]").unwrap().size_of(), 32);
+        assert_eq!(context.parse_kind("unsigned long long").unwrap().to_string(), "uint64_t");
+        assert_eq!(context.parse_kind("widget").err(), Some(Error::Unknown("widget".to_string())));
+        assert!(matches!(context.parse_c("struct s { int x }"), Err(Error::Syntax(_))));
+        assert!(context.parse_c("void v;").is_err());
+    }
+
+    #[test]
+    fn rust_items() {
+        let _target = Target::lp64().enter();
+        let context = Context::new();
+
+        let items = context.parse_rust(r"
+            /// Items may name those defined after them.
+            #[derive(Clone)]
+            pub struct Node<'a> { value: u8, next: Option<&'a Node<'a>>, state: State }
+            #[repr(u8)] enum State { Idle, Busy = 4, Done }
+            #[repr(C)] struct Header { tag: u8, len: u32 }
+            #[repr(C, packed)] struct Packed { tag: u8, len: u32 }
+            #[repr(align(16))] struct Aligned(u16, [i32; 2]);
+            union Bits { word: u32, bytes: [u8; 4] }
+            enum Shape { Dot, Circle(f32), Rect { w: f32, h: f32 } }
+        ").unwrap();
+
+        let sizes: Vec<_> = items.iter().map(|kind| (kind.size_of(), kind.align_of())).collect();
+        assert_eq!(sizes, [(16, 8), (1, 1), (8, 4), (5, 1), (16, 16), (4, 4), (12, 4)]);
+        assert_eq!(items[0].to_string(), "Node");
+        assert_eq!(context.get("Header").unwrap().size_of(), 8);
+
+        let Kind::Enum(state) = items[1] else { panic!("expected an enum") };
+        let values: Vec<_> = state.variants.iter().map(|variant| variant.value).collect();
+        assert_eq!(values, [0, 4, 5]);
+
+        let payloads = context.parse_rust(r"
+            #[repr(C)] enum Wide { A(u8), B(u64) }
+            #[repr(C, u8)] enum Narrow { A(u8), B(u16) }
+            #[repr(u8)] enum Inline { A(u8), B(u16) }
+        ").unwrap();
+        let layouts: Vec<_> = payloads.iter()
+            .map(|kind| match kind {
+                Kind::Tagged(tagged) => (tagged.layout().offsets, kind.size_of()),
+                _ => panic!("expected a tagged enum"),
+            })
+            .collect();
+        assert_eq!(layouts, [
+            (vec![vec![8], vec![8]], 16),
+            (vec![vec![2], vec![2]], 4),
+            (vec![vec![1], vec![2]], 4),
+        ]);
+
+        assert_eq!(context.parse_rust_kind("&[u16]").unwrap().size_of(), 16);
+        assert_eq!(context.parse_rust_kind("(u8, u32)").unwrap().size_of(), 8);
+        assert_eq!(context.parse_rust_kind("*const [char; 3]").unwrap().to_string(), "*const [char; 3]");
+        assert_eq!(context.parse_rust_kind("char").unwrap().to_string(), "char");
+        assert_eq!(context.parse_rust_kind("char").unwrap().underlying().to_string(), "Rust char");
+        assert_eq!(Primitive::named("char"), Some(Primitive::Char));
+        assert_eq!(context.parse_rust("struct S { x: Missing }").err(), Some(Error::Unknown("Missing".to_string())));
+        assert!(matches!(context.parse_rust("struct S { x: u8"), Err(Error::Syntax(_))));
     }
 }
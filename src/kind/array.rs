@@ -2,23 +2,86 @@ use core::fmt;
 
 use crate::{
     access::{self, Indirection, Trace},
-    kind::{Kind, primitive::Primitive},
+    kind::{Kind, BaseField, Decode, function::Syntax},
+    mem_ribbon::MemRibbon,
 };
 
 use super::CType;
 
+/// Where the number of elements of an array comes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Length<'kind> {
+    /// Declared with the type, as in `T a[N]`.
+    #[default]
+    Fixed,
+    /// A C99 flexible array member `T a[]`, holding as many elements as
+    /// the span it ends has room for.
+    Flexible,
+    /// Read at runtime from the named integer field of the same composite.
+    Field(&'kind str),
+}
+
+/// An array of `size` elements.
+///
+/// Arrays whose length is only known at runtime take no space in the layout
+/// and must end their composite, which reads their length from memory and
+/// fills in `size` before indexing or drawing them.
 #[derive(Clone, Copy)]
 pub struct Array <'kind> {
     pub kind: &'kind Kind<'kind>,
     pub size: usize,
+    pub length: Length<'kind>,
+    /// Whether the array is spelled `T[N]` or `[T; N]`.
+    pub syntax: Syntax,
 }
 
 impl <'kind> Array <'kind> {
-    pub fn base_fields(&self, address: &mut usize) -> Vec<(usize, Primitive)> {
-        *address += self.kind.align_pad(*address as u16) as usize;
+    pub fn new(kind: &'kind Kind<'kind>, size: usize) -> Self {
+        Self { kind, size, length: Length::Fixed, syntax: Syntax::C }
+    }
+
+    pub fn flexible(kind: &'kind Kind<'kind>) -> Self {
+        Self { kind, size: 0, length: Length::Flexible, syntax: Syntax::C }
+    }
+
+    /// An array whose length is stored in the sibling field `field_name`.
+    pub fn counted(kind: &'kind Kind<'kind>, field_name: &'kind str) -> Self {
+        Self { kind, size: 0, length: Length::Field(field_name), syntax: Syntax::C }
+    }
+
+    /// The same array spelled as a Rust array, `[T; N]`.
+    pub fn rust(self) -> Self {
+        Self { syntax: Syntax::Rust, ..self }
+    }
 
+    pub fn is_fixed(&self) -> bool {
+        self.length == Length::Fixed
+    }
+
+    /// The same array holding `size` elements, as found in memory.
+    pub fn with_len(self, size: usize) -> Self {
+        Self { size, ..self }
+    }
+
+    /// Bytes covered by the elements, including those of a runtime length.
+    pub fn byte_len(&self) -> usize {
+        self.kind.size_of() as usize * self.size
+    }
+
+    pub fn base_fields(&self, ribbon: Option<&MemRibbon>, address: &mut usize) -> Vec<BaseField<'kind>> {
+        let start = *address;
+        let stride = self.kind.size_of() as usize;
+        *address = start + stride * self.size;
+
+        let text = self.kind.is_text();
         (0..self.size)
-            .flat_map(|_| self.kind.base_fields(address))
+            .flat_map(|index| {
+                let mut fields = self.kind.base_fields(ribbon, &mut (start + stride * index));
+                if text {
+                    fields[0].decode = Decode::Text(index);
+                }
+                fields
+            })
             .collect()
     }
 }
@@ -28,19 +91,23 @@ impl<'kind> CType<'kind> for Array<'kind> {
         self
     }
 
-    fn size_of(&self) -> u16 {
-        self.kind.size_of() * (self.size as u16)
+    fn size_of(&self) -> u64 {
+        match self.length {
+            Length::Fixed => self.kind.size_of().saturating_mul(self.size as u64),
+            _ => 0,
+        }
     }
 
-    fn align_of(&self) -> u16 {
+    fn align_of(&self) -> u64 {
         self.kind.align_of()
     }
-    
-    fn access_with(&self, indirection: Indirection, mut trace: Trace<'kind>) -> access::Result<'kind> {
+
+    fn access_with(&self, indirection: Indirection, mut trace: Trace<'_, 'kind>) -> access::Result<'kind> {
         match indirection {
             Indirection::Deref => self.kind.access(trace),
             Indirection::Index(idx) => {
-                trace.address += self.kind.size_of() as usize * idx;
+                let stride = self.kind.size_of() as usize;
+                trace.address = trace.check_index(idx, self.size, stride)?;
                 self.kind.access(trace)
             },
             indirection => Err(access::Error::at(
@@ -53,6 +120,71 @@ impl<'kind> CType<'kind> for Array<'kind> {
 
 impl <'kind> fmt::Display for Array<'kind> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f,"{}[{}]",self.kind, self.size)
+        match (self.syntax, self.length) {
+            (Syntax::Rust, Length::Fixed) => write!(f, "[{}; {}]", self.kind, self.size),
+            (Syntax::Rust, _) => write!(f, "[{}]", self.kind),
+            (Syntax::C, Length::Fixed) => write!(f, "{}[{}]", self.kind, self.size),
+            (Syntax::C, Length::Flexible) => write!(f, "{}[]", self.kind),
+            (Syntax::C, Length::Field(field_name)) => write!(f, "{}[{field_name}]", self.kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kind::{
+        leak,
+        primitive::{Primitive, PrimValue},
+        reference::{self, Reference},
+    };
+
+    fn ribbon() -> MemRibbon<'static> {
+        let uint32_t = leak(Primitive::U32);
+        let quad = leak(Array::new(uint32_t, 4));
+        let ptr = leak(Reference::new(reference::Mode::Ptr, quad));
+
+        let mut ribbon = MemRibbon::new(0x100)
+            .span("s", vec![
+                quad.field_named("arr"),
+                uint32_t.field_named("secret"),
+                ptr.field_named("p"),
+            ]);
+        // `p` points at `arr`, leaving room for one element before the end of the span.
+        ribbon.write_at(0x114, PrimValue::Size(0x100));
+        ribbon
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let ribbon = ribbon();
+        assert_eq!(ribbon.at("s.arr[3]").address, 0x10c);
+
+        let err = ribbon.get("s.arr[4]".parse().unwrap()).err().unwrap();
+        assert!(matches!(err.kind, access::ErrorKind::OutOfBounds { index: 4, len: 4 }));
+
+        assert_eq!(ribbon.at("s.p[0][3]").address, 0x10c);
+        let err = ribbon.get("s.p[2]".parse().unwrap()).err().unwrap();
+        assert!(matches!(err.kind, access::ErrorKind::OutOfBounds { index: 2, len: 1 }));
+        assert!(ribbon.overruns.borrow().is_empty());
+    }
+
+    #[test]
+    fn overrun() {
+        let mut ribbon = ribbon().allow_out_of_bounds();
+        ribbon.set("s.secret", PrimValue::U32(42)).unwrap();
+
+        let place = ribbon.at("s.arr[4]");
+        assert_eq!(place.address, 0x110);
+        assert!(matches!(ribbon.read(&place), Some(PrimValue::U32(42))));
+        ribbon.at("s.arr[4]");
+
+        let err = ribbon.get(format!("s.arr[{}]", usize::MAX).parse().unwrap()).err().unwrap();
+        assert!(matches!(err.kind, access::ErrorKind::OutOfBounds { len: 4, .. }));
+
+        let overruns = ribbon.overruns.borrow();
+        assert_eq!(overruns.len(), 1);
+        assert_eq!(overruns[0].access, "arr[4]");
+        assert!(overruns[0].contains(0x113) && !overruns[0].contains(0x114));
     }
 }
@@ -1,31 +1,60 @@
 use crate::{
-    access::{self, Indirection, Trace},
-    kind::Kind,
-    block_draw::{block_plan::BlockDiagPlan, util::{Vec2, Translate}, BlockDrawSpec},
+    access::{self, Indirection, Trace, PlaceValue},
+    kind::{Kind, BaseField, tagged::Niche, array::{Array, Length}},
+    mem_ribbon::MemRibbon,
+    block_draw::{block_plan::BlockDiagPlan, util::{Vec2, Translate}, BlockDrawSpec, RibbonPlace},
+    target::{Target, BitfieldAbi, Endian},
 };
 
-use std::{fmt, cell::RefCell};
+use std::{fmt, cell::{Cell, RefCell}, ops::Range};
 
-use super::{Primitive, CType};
+use super::CType;
 
 #[derive(Clone)]
 pub struct Field<'kind> {
     pub name: Option<String>,
     pub kind: &'kind Kind<'kind>,
+    pub bits: Option<u16>,
 }
 
 impl<'kind> Field<'kind> {
     pub fn new(name: impl ToString, kind: &'kind Kind<'kind>) -> Self {
-        Self { name: Some(name.to_string()), kind }
+        Self { name: Some(name.to_string()), kind, bits: None }
     }
 
     pub fn anon(kind: &'kind Kind<'kind> ) -> Self {
-        Self { name: None, kind }
+        Self { name: None, kind, bits: None }
     }
 
-    pub fn make_plan(&self, spec: &BlockDrawSpec, mins: Vec2, width: Option<f32>, with_notch: bool) -> BlockDiagPlan<'kind> {
+    pub fn bitfield(name: impl ToString, kind: &'kind Kind<'kind>, width: u16) -> Self {
+        Self { name: Some(name.to_string()), kind, bits: Some(width) }
+    }
+
+    /// An unnamed bitfield, such as the `int : 0` used to force alignment.
+    pub fn anon_bitfield(kind: &'kind Kind<'kind>, width: u16) -> Self {
+        Self { name: None, kind, bits: Some(width) }
+    }
+
+    pub fn kind_name(&self) -> String {
+        match self.bits {
+            Some(width) => format!("{}:{}", self.kind, width),
+            None => self.kind.to_string(),
+        }
+    }
+
+    pub fn make_plan(
+        &self,
+        spec: &BlockDrawSpec,
+        mins: Vec2,
+        width: Option<f32>,
+        with_notch: bool,
+        place: Option<RibbonPlace>,
+    ) -> BlockDiagPlan<'kind> {
         let width = width.unwrap_or_else(|| spec.field_width(self, with_notch));
-        let mut plan = spec.make_plan(self.kind, mins, Some(width), with_notch);
+        let mut plan = match self.bits {
+            Some(_) => spec.plan_bitfield(self, mins, width, with_notch),
+            None => spec.make_plan(self.kind, mins, Some(width), with_notch, place),
+        };
 
         if let Some(label) = self.name.as_deref() {
             let mut left_width = spec.member_width(self.kind);
@@ -44,7 +73,7 @@ impl<'kind> Field<'kind> {
         plan
     }
 
-    pub fn size_of(&self) -> u16 {
+    pub fn size_of(&self) -> u64 {
         self.kind.size_of()
     }
 }
@@ -64,11 +93,144 @@ impl fmt::Display for Mode {
     }
 }
 
+/// Position of a bitfield, counted in bits from the start of the first byte it occupies.
+///
+/// Bits are numbered from the least significant bit of each byte on
+/// little-endian targets and from the most significant bit on big-endian ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bits {
+    pub offset: u16,
+    pub width: u16,
+}
+
+impl Bits {
+    pub fn byte_len(&self) -> usize {
+        (self.offset as usize + self.width as usize).div_ceil(8)
+    }
+
+    fn locate(index: usize, endian: Endian) -> (usize, u8) {
+        let shift = match endian {
+            Endian::Little => index % 8,
+            Endian::Big => 7 - index % 8,
+        };
+
+        (index / 8, 1 << shift)
+    }
+
+    pub fn extract(&self, bytes: &[u8], endian: Endian) -> u64 {
+        (0..self.width as usize).fold(0, |acc, i| {
+            let (byte, mask) = Self::locate(self.offset as usize + i, endian);
+            let bit = (bytes[byte] & mask != 0) as u64;
+
+            match endian {
+                Endian::Little => acc | bit << i,
+                Endian::Big => acc << 1 | bit,
+            }
+        })
+    }
+
+    pub fn insert(&self, bytes: &mut [u8], value: u64, endian: Endian) {
+        for i in 0..self.width as usize {
+            let (byte, mask) = Self::locate(self.offset as usize + i, endian);
+            let shift = match endian {
+                Endian::Little => i,
+                Endian::Big => self.width as usize - 1 - i,
+            };
+
+            if value >> shift & 1 != 0 {
+                bytes[byte] |= mask;
+            } else {
+                bytes[byte] &= !mask;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldLayout {
+    pub offset: u64,
+    pub size: u64,
+    pub bits: Option<Bits>,
+}
+
+impl FieldLayout {
+    pub fn end(&self) -> u64 {
+        self.offset + self.size
+    }
+}
+
+/// Placement of every field of a composite on the active target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub fields: Vec<FieldLayout>,
+    pub size: u64,
+    pub align: u64,
+}
+
+impl Layout {
+    /// Groups consecutive fields into runs, where the fields of a run are bitfields sharing bytes.
+    pub fn runs(&self) -> Vec<Range<usize>> {
+        let mut runs: Vec<Range<usize>> = Vec::new();
+        let mut run_end = 0;
+
+        for (index, slot) in self.fields.iter().enumerate() {
+            let shared = slot.bits.is_some()
+                && index > 0
+                && self.fields[index - 1].bits.is_some()
+                && slot.offset < run_end;
+
+            match runs.last_mut() {
+                Some(run) if shared => run.end = index + 1,
+                _ => runs.push(index..index + 1),
+            }
+
+            run_end = if shared { run_end.max(slot.end()) } else { slot.end() };
+        }
+
+        runs
+    }
+}
+
+/// Order in which the fields of a struct are placed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Order {
+    /// Declaration order, as C and `#[repr(C)]` require.
+    #[default]
+    C,
+    /// Decreasing alignment with zero-sized fields first, as rustc does for
+    /// the default `repr(Rust)`. Structs with bitfields keep declaration order.
+    Rust,
+}
+
+/// Layout policy of a composite, covering `#[repr(...)]`, `__attribute__((packed))`
+/// `#pragma pack(N)` and `alignas`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Repr {
+    pub order: Order,
+    /// Caps the alignment of every field at N.
+    pub packed: Option<u64>,
+    /// Raises the alignment of the whole composite to at least N.
+    pub align: Option<u64>,
+}
+
+// Layouts are computed in bits with room to spare, so a kind too large for
+// any target still gets a size, which is then clamped to `u64::MAX`.
+fn round_up(value: u128, align: u128) -> u128 {
+    value.next_multiple_of(align.max(1))
+}
+
+fn clamp(value: u128) -> u64 {
+    u64::try_from(value).unwrap_or(u64::MAX)
+}
+
 #[derive(Clone)]
 pub struct Composite<'kind> {
     pub name: String,
     pub mode: Mode,
+    pub repr: Cell<Repr>,
     pub fields: RefCell<Vec<Field<'kind>>>,
+    /// Cleared for a forward declaration such as `struct node;`, until its fields are given.
+    pub defined: Cell<bool>,
 }
 
 impl<'kind> Composite<'kind> {
@@ -76,7 +238,9 @@ impl<'kind> Composite<'kind> {
         Self {
             name: name.to_string(),
             mode: Mode::Product,
+            repr: Cell::default(),
             fields: RefCell::new(fields),
+            defined: Cell::new(true),
         }
     }
     
@@ -84,52 +248,314 @@ impl<'kind> Composite<'kind> {
         Self {
             name: name.to_string(),
             mode: Mode::Sum,
+            repr: Cell::default(),
             fields: RefCell::new(fields),
+            defined: Cell::new(true),
+        }
+    }
+
+    /// An incomplete struct or union, which can be pointed to before it is defined.
+    pub fn declare(name: impl ToString, mode: Mode) -> Self {
+        Self {
+            name: name.to_string(),
+            mode,
+            repr: Cell::default(),
+            fields: RefCell::new(Vec::new()),
+            defined: Cell::new(false),
+        }
+    }
+
+    /// Completes a declared composite in place, so kinds already referring to it see the fields.
+    pub fn define(&self, fields: Vec<Field<'kind>>, repr: Repr) {
+        *self.fields.borrow_mut() = fields;
+        self.repr.set(repr);
+        self.defined.set(true);
+    }
+
+    pub fn repr(self, repr: Repr) -> Self {
+        self.repr.set(repr);
+        self
+    }
+
+    pub fn order(mut self, order: Order) -> Self {
+        self.repr.get_mut().order = order;
+        self
+    }
+
+    pub fn packed(mut self, pack: u64) -> Self {
+        self.repr.get_mut().packed = Some(pack);
+        self
+    }
+
+    pub fn align(mut self, align: u64) -> Self {
+        self.repr.get_mut().align = Some(align);
+        self
+    }
+
+    /// Alignment of a field once `packed` has been applied.
+    pub(crate) fn field_align(&self, field: &Field) -> u64 {
+        let align = field.kind.align_of();
+        self.repr.get().packed.map_or(align, |pack| align.min(pack.max(1)))
+    }
+
+    /// Indices of the fields in the order they are placed.
+    fn placement_order(&self, fields: &[Field]) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..fields.len()).collect();
+
+        if self.repr.get().order == Order::Rust && fields.iter().all(|field| field.bits.is_none()) {
+            order.sort_by_key(|&index| {
+                let field = &fields[index];
+                let runtime = matches!(field.kind, Kind::Array(array) if !array.is_fixed());
+                (field.size_of() != 0 || runtime, runtime, std::cmp::Reverse(self.field_align(field)))
+            });
         }
+
+        order
     }
 
-    pub fn size_of_no_end_pad(&self) -> u16 {
-        match self.mode {
-            Mode::Product => self.fields.borrow().iter().fold(0, |acc,x|
-                acc + x.kind.size_of() + x.kind.align_pad(acc)
-            ),
-            Mode::Sum => self.fields.borrow().iter()
-                .map(Field::size_of)
-                .max()
-                .unwrap_or_default()
+    pub fn layout(&self) -> Layout {
+        let mut layout = self.layout_fields();
+        if let Some(min_align) = self.repr.get().align {
+            layout.align = layout.align.max(min_align);
         }
+        layout
     }
 
-    pub fn offset_of(&self, field_name: &str) -> Option<u16> {
+    fn layout_fields(&self) -> Layout {
+        let fields = self.fields.borrow();
+        let abi = Target::with_active(|target| target.bitfields);
+        let mut slots = Vec::with_capacity(fields.len());
+        let mut align = 1;
+
         if self.mode == Mode::Sum {
-            return Some(0);
+            let mut size = 0;
+
+            for field in fields.iter() {
+                let bits = field.bits.map(|width| Bits { offset: 0, width });
+                let slot_size = match bits {
+                    Some(bits) => bits.byte_len() as u64,
+                    None => field.kind.size_of(),
+                };
+
+                size = size.max(field.kind.size_of());
+                align = align.max(self.field_align(field));
+                slots.push(FieldLayout { offset: 0, size: slot_size, bits });
+            }
+
+            return Layout { fields: slots, size, align };
         }
 
-        let mut result = 0;
+        let order = self.placement_order(&fields);
+
+        // Running end of the layout in bits, and the end and width of the
+        // storage unit currently open for MSVC bitfields.
+        let mut bit: u128 = 0;
+        let mut unit: Option<(u128, u128)> = None;
 
-        for field in self.fields.borrow().iter() {
-            result += field.kind.align_pad(result);
+        for field in order.iter().map(|&index| &fields[index]) {
+            let size = field.kind.size_of() as u128;
+            let field_align = self.field_align(field) as u128;
 
-            if field.name.as_deref() == Some(field_name) {
-                return Some(result)
+            if let Some((unit_end, _)) = unit {
+                if field.bits.unwrap_or_default() == 0 {
+                    bit = unit_end;
+                    unit = None;
+                }
             }
-            
-            result += field.kind.size_of();
+
+            let width = match field.bits {
+                None => {
+                    let offset = round_up(bit.div_ceil(8), field_align);
+                    bit = (offset + size) * 8;
+                    align = align.max(field_align as u64);
+                    slots.push(FieldLayout { offset: clamp(offset), size: clamp(size), bits: None });
+                    continue;
+                }
+                Some(width) => width as u128,
+            };
+
+            let start = match abi {
+                _ if width == 0 => round_up(bit, field_align * 8),
+                BitfieldAbi::SysV => {
+                    if bit % (field_align * 8) + width > size * 8 {
+                        bit = round_up(bit, field_align * 8);
+                    }
+                    bit
+                }
+                BitfieldAbi::Msvc => match unit {
+                    Some((unit_end, unit_bits)) if unit_bits == size * 8 && bit + width <= unit_end => bit,
+                    _ => {
+                        let base = unit.map_or(bit, |(unit_end, _)| unit_end);
+                        let unit_start = round_up(base.div_ceil(8), field_align) * 8;
+                        unit = Some((unit_start + size * 8, size * 8));
+                        unit_start
+                    }
+                },
+            };
+
+            bit = start + width;
+
+            if field.name.is_some() || (abi == BitfieldAbi::Msvc && width > 0) {
+                align = align.max(field_align as u64);
+            }
+
+            let bits = Bits { offset: (start % 8) as u16, width: width as u16 };
+            slots.push(FieldLayout {
+                offset: clamp(start / 8),
+                size: bits.byte_len() as u64,
+                bits: Some(bits),
+            });
         }
 
-        None
+        if let Some((unit_end, _)) = unit {
+            bit = unit_end;
+        }
+
+        let mut placed: Vec<_> = order.into_iter().zip(slots).collect();
+        placed.sort_by_key(|(index, _)| *index);
+
+        Layout {
+            fields: placed.into_iter().map(|(_, slot)| slot).collect(),
+            size: clamp(bit.div_ceil(8)),
+            align,
+        }
+    }
+
+    pub fn size_of_no_end_pad(&self) -> u64 {
+        self.layout().size
     }
 
-    pub fn base_fields(&self, address: &mut usize) -> Vec<(usize,Primitive)> {
-        match self.mode {
-            Mode::Sum => self.fields.borrow().first()
-                .map(|field| field.kind.base_fields(address))
-                .unwrap_or_default(),
-            Mode::Product => self.fields.borrow().iter()
-                .flat_map(|field| field.kind.base_fields(address))
-                .collect(),
+    fn field_index(&self, field_name: &str) -> Option<usize> {
+        self.fields.borrow().iter()
+            .position(|field| field.name.as_deref() == Some(field_name))
+    }
+
+    pub fn field_layout(&self, field_name: &str) -> Option<(&'kind Kind<'kind>, FieldLayout)> {
+        let index = self.field_index(field_name)?;
+        Some((self.fields.borrow()[index].kind, self.layout().fields[index]))
+    }
+
+    /// Number of elements found in memory for the field at `index` of a
+    /// composite placed at `address`, when that field is an array whose
+    /// length is only known at runtime.
+    ///
+    /// A counted array takes the value of its length field, as far as the
+    /// ribbon lets a span grow, and a flexible array member fills the rest of
+    /// the span it is in.
+    pub fn array_len(&self, index: usize, ribbon: &MemRibbon, address: usize) -> Option<usize> {
+        let Kind::Array(array) = self.fields.borrow().get(index)?.kind else {
+            return None;
+        };
+
+        let len = match array.length {
+            Length::Fixed => return None,
+            Length::Flexible => {
+                let start = address + self.layout().fields[index].offset as usize;
+                let end = ribbon.span_end(start).unwrap_or(start);
+                (end - start).checked_div(array.kind.size_of() as usize).unwrap_or_default()
+            }
+            Length::Field(field_name) => {
+                let (kind, slot) = self.field_layout(field_name)?;
+                let place = PlaceValue {
+                    kind,
+                    address: address + slot.offset as usize,
+                    bits: slot.bits,
+                    qualifiers: Default::default(),
+                };
+                ribbon.read(&place)
+                    .and_then(|value| value.as_int())
+                    .and_then(|value| usize::try_from(value).ok())
+                    .unwrap_or_default()
+                    .min(ribbon.max_span / (array.kind.size_of() as usize).max(1))
+            }
+        };
+
+        Some(len)
+    }
+
+    /// Bytes taken by a composite placed at `address`, without end padding,
+    /// including the elements of arrays whose length is read from memory.
+    pub fn extent(&self, ribbon: &MemRibbon, address: usize) -> usize {
+        let layout = self.layout();
+        let fields = self.fields.borrow();
+
+        fields.iter()
+            .zip(&layout.fields)
+            .enumerate()
+            .filter(|(_, (_, slot))| slot.bits.is_none())
+            .map(|(index, (field, slot))| {
+                let start = address + slot.offset as usize;
+                let size = match (field.kind, self.array_len(index, ribbon, address)) {
+                    (Kind::Array(array), Some(len)) => array.with_len(len).byte_len(),
+                    _ => field.kind.extent(ribbon, start),
+                };
+                slot.offset as usize + size
+            })
+            .fold(layout.size as usize, usize::max)
+    }
+
+    /// The flexible array member ending this struct, or ending a struct that ends it.
+    pub fn flexible_member(&self) -> Option<Array<'kind>> {
+        match self.fields.borrow().last()?.kind {
+            Kind::Array(array) if array.length == Length::Flexible => Some(*array),
+            Kind::Composite(comp) => comp.flexible_member(),
+            _ => None,
+        }
+    }
+
+    pub fn offset_of(&self, field_name: &str) -> Option<u64> {
+        self.field_layout(field_name).map(|(_, slot)| slot.offset)
+    }
+
+    pub fn base_fields(&self, ribbon: Option<&MemRibbon>, address: &mut usize) -> Vec<BaseField<'kind>> {
+        let start = *address;
+        let layout = self.layout();
+        let fields = self.fields.borrow();
+        let count = match self.mode {
+            Mode::Sum => fields.len().min(1),
+            Mode::Product => fields.len(),
+        };
+
+        let mut result = Vec::new();
+        for (index, (field, slot)) in fields.iter().zip(&layout.fields).enumerate().take(count) {
+            let mut field_address = start + slot.offset as usize;
+
+            match slot.bits {
+                Some(bits) if bits.width == 0 => (),
+                Some(bits) => result.extend(field.kind.primitive().map(|prim| BaseField {
+                    address: field_address,
+                    prim,
+                    bits: Some(bits),
+                    decode: field.kind.decode(),
+                })),
+                None => {
+                    let runtime_len = ribbon.and_then(|ribbon| self.array_len(index, ribbon, start));
+                    match (field.kind, runtime_len) {
+                        (Kind::Array(array), Some(len)) =>
+                            result.extend(array.with_len(len).base_fields(ribbon, &mut field_address)),
+                        _ => result.extend(field.kind.base_fields(ribbon, &mut field_address)),
+                    }
+                }
+            }
+        }
+
+        result.sort_by_key(|field| field.address);
+        *address = start + clamp(round_up(layout.size as u128, layout.align as u128)) as usize;
+        result
+    }
+
+    /// The niche with the most invalid values among the fields of a struct.
+    pub fn niche(&self) -> Option<Niche> {
+        if self.mode == Mode::Sum {
+            return None;
         }
 
+        let layout = self.layout();
+        self.fields.borrow().iter()
+            .zip(&layout.fields)
+            .filter(|(_, slot)| slot.bits.is_none())
+            .filter_map(|(field, slot)| field.kind.niche().map(|niche| niche.at(slot.offset)))
+            .max_by_key(Niche::available)
     }
 
     pub fn type_of(&self, field_name: &str) -> Option<&'kind Kind<'kind>> {
@@ -144,35 +570,57 @@ impl<'kind> CType<'kind> for Composite<'kind> {
         &self.mode
     }
 
-    fn size_of(&self) -> u16 {
-        let base_size = self.size_of_no_end_pad();
-        let align = self.align_of();
-        let remainder = base_size % align;
-        let padding = if remainder > 0 { align - remainder } else { 0 };
-
-        base_size + padding
+    fn size_of(&self) -> u64 {
+        let Layout { size, align, .. } = self.layout();
+        clamp(round_up(size as u128, align as u128))
     }
 
-    fn align_of(&self) -> u16 {
-        self.fields.borrow().iter()
-            .map(Field::size_of)
-            .max()
-            .unwrap_or_default()
+    fn align_of(&self) -> u64 {
+        self.layout().align
     }
 
-    fn access_with(&self, indirection: Indirection, mut trace: Trace<'kind>) -> access::Result<'kind> {
+    fn access_with(&self, indirection: Indirection, mut trace: Trace<'_, 'kind>) -> access::Result<'kind> {
         let subfield = indirection.as_field().ok_or_else(|| access::Error::at(
             &trace.field_name,
             access::ErrorKind::operation(&indirection, Kind::from(self.clone())),
         ))?;
 
-        let offset = self.offset_of(subfield).ok_or_else(|| access::Error::at(
+        let index = self.field_index(subfield).ok_or_else(|| access::Error::at(
             &trace.field_name,
             access::ErrorKind::SubField { name: subfield.into() },
         ))?;
+        let kind = self.fields.borrow()[index].kind;
+        let slot = self.layout().fields[index];
+
+        let start = trace.address;
+        trace.address += slot.offset as usize;
 
-        trace.address += offset as usize;
-        self.type_of(subfield).unwrap().access(trace)
+        if let (Kind::Array(array), Some(len)) = (kind, self.array_len(index, trace.ribbon, start)) {
+            let array = array.with_len(len);
+            return match trace.path.pop_front() {
+                None => kind.get_place_value(trace),
+                Some(indirection) => array.access_with(indirection, trace).map_err(|err|
+                    err.with_context(&array, subfield)
+                ),
+            };
+        }
+
+        let Some(bits) = slot.bits else {
+            return kind.access(trace);
+        };
+
+        match trace.path.pop_front() {
+            None => Ok(PlaceValue {
+                kind,
+                address: trace.address,
+                bits: Some(bits),
+                qualifiers: trace.qualifiers | kind.qualifiers(),
+            }),
+            Some(indirection) => Err(access::Error::at(
+                subfield,
+                access::ErrorKind::operation(&indirection, kind.clone()),
+            )),
+        }
     }
 }
 
@@ -181,3 +629,255 @@ impl fmt::Display for Composite<'_> {
         f.write_str(&self.name)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kind::{leak, primitive::{Primitive, PrimValue}};
+
+    fn char_int_char() -> Composite<'static> {
+        let char_ = leak(Primitive::Char);
+        let int32_t = leak(Primitive::I32);
+
+        Composite::product("s", vec![
+            char_.field_named("a"),
+            int32_t.field_named("b"),
+            char_.field_named("c"),
+        ])
+    }
+
+    #[test]
+    fn c_order() {
+        let comp = char_int_char();
+        assert_eq!(comp.size_of(), 12);
+        assert_eq!(comp.offset_of("c"), Some(8));
+    }
+
+    #[test]
+    fn packed() {
+        let comp = char_int_char().packed(1);
+        assert_eq!(comp.size_of(), 6);
+        assert_eq!(comp.align_of(), 1);
+        assert_eq!(comp.offset_of("b"), Some(1));
+
+        let comp = char_int_char().packed(2);
+        assert_eq!(comp.size_of(), 8);
+        assert_eq!(comp.offset_of("b"), Some(2));
+    }
+
+    #[test]
+    fn min_align() {
+        let comp = char_int_char().align(64);
+        assert_eq!(comp.size_of(), 64);
+        assert_eq!(comp.align_of(), 64);
+        assert_eq!(comp.offset_of("c"), Some(8));
+    }
+
+    #[test]
+    fn rust_order() {
+        let comp = char_int_char().order(Order::Rust);
+        assert_eq!(comp.size_of(), 8);
+        assert_eq!(comp.offset_of("b"), Some(0));
+        assert_eq!(comp.offset_of("a"), Some(4));
+        assert_eq!(comp.offset_of("c"), Some(5));
+
+        let kind = leak(comp);
+        let addresses: Vec<usize> = kind.base_fields(None, &mut 0).iter()
+            .map(|field| field.address)
+            .collect();
+        assert_eq!(addresses, vec![0, 4, 5]);
+    }
+
+    #[test]
+    fn large_array() {
+        let page = char_int_char();
+        page.fields.borrow_mut().insert(1, leak(Array::new(leak(Primitive::U8), 70000)).field_named("data"));
+
+        assert_eq!(page.offset_of("b"), Some(70004));
+        assert_eq!(page.size_of(), 70012);
+        assert_eq!(leak(Array::new(leak(Primitive::U64), usize::MAX)).size_of(), u64::MAX);
+    }
+
+    #[test]
+    fn packed_array_stride() {
+        let array = leak(Array::new(leak(char_int_char().packed(1)), 2));
+        let mut end = 0;
+        let addresses: Vec<usize> = array.base_fields(None, &mut end).iter()
+            .map(|field| field.address)
+            .collect();
+
+        assert_eq!(addresses, vec![0, 1, 5, 6, 7, 11]);
+        assert_eq!(end, 12);
+    }
+
+    #[test]
+    fn counted_array() {
+        let u16_t = leak(Primitive::U16);
+        let data = leak(Array::counted(leak(Primitive::U8), "len"));
+        let mut ribbon = MemRibbon::new(0)
+            .span("msg", vec![u16_t.field_named("len"), data.field_named("data")])
+            .span("after", vec![u16_t.field_named("x")]);
+
+        assert_eq!(ribbon.data.len(), 4);
+        ribbon.set("msg.len", PrimValue::U16(3)).unwrap();
+        assert_eq!(ribbon.data.len(), 8);
+        assert_eq!(ribbon.at("after.x").address, 6);
+
+        assert_eq!(ribbon.at("msg.data[2]").address, 4);
+        let err = ribbon.get("msg.data[3]".parse().unwrap()).err().unwrap();
+        assert!(matches!(err.kind, access::ErrorKind::OutOfBounds { index: 3, len: 3 }));
+
+        ribbon.set("after.x", PrimValue::U16(7)).unwrap();
+        ribbon.set("msg.len", PrimValue::U16(0)).unwrap();
+        assert_eq!(ribbon.data.len(), 4);
+        assert_eq!(ribbon.read(&ribbon.at("after.x")).and_then(|value| value.as_int()), Some(7));
+
+        let mut ribbon = ribbon.max_span_size(16);
+        ribbon.set("msg.len", PrimValue::U16(u16::MAX)).unwrap();
+        assert_eq!(ribbon.data.len(), 18);
+        assert_eq!(ribbon.at("after.x").address, 16);
+    }
+
+    #[test]
+    fn moved_pointers() {
+        let u16_t = leak(Primitive::U16);
+        let ptr = leak(crate::kind::reference::Reference::new(crate::kind::reference::Mode::Ptr, u16_t));
+        let data = leak(Array::counted(leak(Primitive::U8), "len"));
+        let context = crate::context::Context::new();
+        let mut ribbon = MemRibbon::new(0)
+            .span("before", vec![ptr.field_named("p")])
+            .span("msg", vec![u16_t.field_named("len"), data.field_named("data")])
+            .span("after", vec![u16_t.field_named("x")])
+            .span("later", vec![ptr.field_named("q")]);
+
+        ribbon.point(&context, "before.p", "&after.x").unwrap();
+        ribbon.point(&context, "later.q", "&after.x").unwrap();
+        ribbon.set("msg.len", PrimValue::U16(3)).unwrap();
+
+        let x = ribbon.at("after.x").address;
+        assert_eq!(x, 10);
+        assert_eq!(ribbon.at("before.p[0]").address, x);
+        assert_eq!(ribbon.at("later.q[0]").address, x);
+
+        ribbon.set("msg.len", PrimValue::U16(0)).unwrap();
+        assert_eq!(ribbon.at("later.q[0]").address, 6);
+    }
+
+    #[test]
+    fn flexible_array() {
+        let u32_t = leak(Primitive::U32);
+        let msg = leak(Composite::product("msg", vec![
+            u32_t.field_named("len"),
+            leak(Array::flexible(u32_t)).field_named("data"),
+        ]));
+        assert_eq!(msg.size_of(), 4);
+
+        let ribbon = MemRibbon::new(0)
+            .span("buf", vec![msg.field_named("m")])
+            .flex(2);
+
+        assert_eq!(ribbon.data.len(), 12);
+        assert_eq!(ribbon.at("buf.m.data[1]").address, 8);
+        assert!(ribbon.get("buf.m.data[2]".parse().unwrap()).is_err());
+
+        let addresses: Vec<usize> = msg.base_fields(Some(&ribbon), &mut 0).iter()
+            .map(|field| field.address)
+            .collect();
+        assert_eq!(addresses, vec![0, 4, 8]);
+    }
+
+    fn flags() -> Kind<'static> {
+        let uint8_t = leak(Primitive::U8);
+        let uint32_t = leak(Primitive::U32);
+        let int32_t = leak(Primitive::I32);
+
+        Kind::from(Composite::product(
+            "flags",
+            vec![
+                Field::bitfield("a", uint32_t, 3),
+                Field::bitfield("b", int32_t, 5),
+                Field::bitfield("c", uint8_t, 4),
+            ],
+        ))
+    }
+
+    #[test]
+    fn sysv_bitfields() {
+        let kind = flags();
+        assert_eq!(kind.size_of(), 4);
+
+        let Kind::Composite(comp) = &kind else { unreachable!() };
+        let layout = comp.layout();
+        assert_eq!(layout.runs(), vec![0..2, 2..3]);
+        assert_eq!(layout.fields[2].offset, 1);
+    }
+
+    #[test]
+    fn msvc_bitfields() {
+        let _target = Target::llp64().enter();
+        let kind = flags();
+        assert_eq!(kind.size_of(), 8);
+
+        let Kind::Composite(comp) = &kind else { unreachable!() };
+        assert_eq!(comp.offset_of("c"), Some(4));
+    }
+
+    #[test]
+    fn zero_width_bitfield() {
+        let uint8_t = leak(Primitive::U8);
+        let uint32_t = leak(Primitive::U32);
+        let kind = Kind::from(Composite::product(
+            "split",
+            vec![
+                Field::bitfield("a", uint8_t, 1),
+                Field::anon_bitfield(uint32_t, 0),
+                Field::bitfield("b", uint8_t, 1),
+            ],
+        ));
+
+        let Kind::Composite(comp) = &kind else { unreachable!() };
+        assert_eq!(comp.offset_of("b"), Some(4));
+        assert_eq!(kind.size_of(), 5);
+    }
+
+    #[test]
+    fn bitfield_round_trip() {
+        let kind = leak(flags());
+
+        for target in [Target::ilp32(), Target::ppc32()] {
+            let mut ribbon = MemRibbon::new(0)
+                .target(target)
+                .span("span", vec![kind.field_named("f")]);
+
+            ribbon.set("span.f.a", PrimValue::U32(5)).unwrap();
+            ribbon.set("span.f.b", PrimValue::I32(-3)).unwrap();
+            ribbon.set("span.f.c", PrimValue::U8(9)).unwrap();
+
+            assert!(matches!(ribbon.read(&ribbon.at("span.f.a")), Some(PrimValue::U32(5))));
+            assert!(matches!(ribbon.read(&ribbon.at("span.f.b")), Some(PrimValue::I32(-3))));
+            assert!(matches!(ribbon.read(&ribbon.at("span.f.c")), Some(PrimValue::U8(9))));
+        }
+    }
+
+    #[test]
+    fn set_mismatch() {
+        let uint8_t = leak(Primitive::U8);
+        let kind = leak(Composite::product("s", vec![
+            uint8_t.field_named("a"),
+            uint8_t.field_named("b"),
+            leak(Primitive::U16).field_named("c"),
+        ]));
+        let mut ribbon = MemRibbon::new(0).span("g", vec![kind.field_named("s")]);
+
+        assert_eq!(
+            ribbon.set("g.s.a", PrimValue::U64(!0)).err().unwrap().message,
+            "Expected uint8_t at g.s.a, found uint64_t",
+        );
+        assert!(ribbon.set("g.s.a", PrimValue::F32(1.0)).is_err());
+        assert!(ribbon.data.iter().all(|byte| matches!(byte, crate::mem_ribbon::MemByte::Undefined)));
+
+        ribbon.set("g.s.a", PrimValue::Char(b'x')).unwrap();
+        ribbon.set("g.s.c", PrimValue::I16(-1)).unwrap();
+        assert!(matches!(ribbon.read(&ribbon.at("g.s.c")), Some(PrimValue::U16(u16::MAX))));
+    }
+}
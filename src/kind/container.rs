@@ -0,0 +1,276 @@
+use crate::{
+    access::{self, diagnostic::Diagnostic},
+    context::Context,
+    kind::{
+        Kind, CType,
+        primitive::{Primitive, PrimValue},
+        array::Array,
+        composite::Composite,
+        reference::{self, Reference},
+    },
+    mem_ribbon::{MemRibbon, Segment, whole},
+};
+
+/// Bytes of text `std::string` keeps inside itself before it allocates.
+pub const SSO_CAPACITY: usize = 15;
+
+/// The standard library type a container models.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flavor {
+    /// `Vec<T>`: pointer, capacity and length.
+    Vec,
+    /// `String`, laid out as a `Vec<u8>`.
+    String,
+    /// `Box<T>`: a single non-null pointer.
+    Box,
+    /// `Rc<T>`, pointing at the counts and the value.
+    Rc,
+    /// `Arc<T>`, pointing at the counts and the value.
+    Arc,
+    /// libstdc++ `std::vector<T>`: pointers to the start, the end and the end of storage.
+    Vector,
+    /// libstdc++ `std::string`, which stores short text in place of its capacity.
+    CxxString,
+}
+
+/// A standard library container: the kind drawn where it is stored, and the
+/// kind of each element of its heap allocation.
+#[derive(Clone, Copy)]
+pub struct Container<'kind> {
+    pub flavor: Flavor,
+    pub kind: &'kind Kind<'kind>,
+    /// An element of the allocation, or the control block of `Rc` and `Arc`.
+    pub elem: &'kind Kind<'kind>,
+    /// Owns the arrays laid out in heap spans.
+    context: &'kind Context<'kind>,
+}
+
+impl<'kind> Container<'kind> {
+    fn buffer(context: &'kind Context<'kind>, flavor: Flavor, name: String, elem: &'kind Kind<'kind>) -> Self {
+        let size_t = context.add(Primitive::Size);
+        let ptr = context.add(Reference::new(reference::Mode::Ptr, elem));
+
+        let kind = context.add(Composite::product(name, vec![
+            ptr.field_named("ptr"),
+            size_t.field_named("cap"),
+            size_t.field_named("len"),
+        ]));
+
+        Self { flavor, kind, elem, context }
+    }
+
+    pub fn vec(context: &'kind Context<'kind>, elem: &'kind Kind<'kind>) -> Self {
+        Self::buffer(context, Flavor::Vec, format!("Vec<{elem}>"), elem)
+    }
+
+    pub fn string(context: &'kind Context<'kind>) -> Self {
+        let byte = context.add(Primitive::U8);
+        Self::buffer(context, Flavor::String, "String".to_string(), byte)
+    }
+
+    pub fn boxed(context: &'kind Context<'kind>, elem: &'kind Kind<'kind>) -> Self {
+        let ptr = context.add(Reference::new(reference::Mode::Ref, elem));
+        let kind = context.add(Composite::product(format!("Box<{elem}>"), vec![ptr.field_named("ptr")]));
+
+        Self { flavor: Flavor::Box, kind, elem, context }
+    }
+
+    fn counted(context: &'kind Context<'kind>, flavor: Flavor, name: &str, inner: &str, elem: &'kind Kind<'kind>) -> Self {
+        let size_t = context.add(Primitive::Size);
+        let block = context.add(Composite::product(format!("{inner}<{elem}>"), vec![
+            size_t.field_named("strong"),
+            size_t.field_named("weak"),
+            elem.field_named("value"),
+        ]));
+        let ptr = context.add(Reference::new(reference::Mode::Ref, block));
+        let kind = context.add(Composite::product(format!("{name}<{elem}>"), vec![ptr.field_named("ptr")]));
+
+        Self { flavor, kind, elem: block, context }
+    }
+
+    pub fn rc(context: &'kind Context<'kind>, elem: &'kind Kind<'kind>) -> Self {
+        Self::counted(context, Flavor::Rc, "Rc", "RcBox", elem)
+    }
+
+    pub fn arc(context: &'kind Context<'kind>, elem: &'kind Kind<'kind>) -> Self {
+        Self::counted(context, Flavor::Arc, "Arc", "ArcInner", elem)
+    }
+
+    pub fn vector(context: &'kind Context<'kind>, elem: &'kind Kind<'kind>) -> Self {
+        let ptr = context.add(Reference::new(reference::Mode::Ptr, elem));
+        let kind = context.add(Composite::product(format!("std::vector<{elem}>"), vec![
+            ptr.field_named("_M_start"),
+            ptr.field_named("_M_finish"),
+            ptr.field_named("_M_end_of_storage"),
+        ]));
+
+        Self { flavor: Flavor::Vector, kind, elem, context }
+    }
+
+    pub fn cxx_string(context: &'kind Context<'kind>) -> Self {
+        let char_ = context.add(Primitive::Char);
+        let size_t = context.add(Primitive::Size);
+        let ptr = context.add(Reference::new(reference::Mode::Ptr, char_));
+        let local_buf = context.add(Array::new(char_, SSO_CAPACITY + 1));
+
+        let local = context.add(Composite::sum("_Local", vec![
+            local_buf.field_named("_M_local_buf"),
+            size_t.field_named("_M_allocated_capacity"),
+        ]));
+        let kind = context.add(Composite::product("std::string", vec![
+            ptr.field_named("_M_p"),
+            size_t.field_named("_M_string_length"),
+            local.field_named("_M_local"),
+        ]));
+
+        Self { flavor: Flavor::CxxString, kind, elem: char_, context }
+    }
+
+    /// Whether the container holds one value rather than a run of elements.
+    pub fn is_single(&self) -> bool {
+        matches!(self.flavor, Flavor::Box | Flavor::Rc | Flavor::Arc)
+    }
+}
+
+impl<'kind> MemRibbon<'kind> {
+    /// Allocates the heap memory of the container at `access_string` in a new
+    /// span, and points the container at it.
+    ///
+    /// The span holds `len` elements followed by the unused capacity, whose
+    /// bytes stay undefined. Box, Rc and Arc hold one value and ignore the
+    /// counts, and a `std::string` short enough for its local buffer allocates nothing.
+    ///
+    /// Fails when the place does not hold `container.kind`.
+    pub fn heap(
+        mut self,
+        access_string: &str,
+        container: &Container<'kind>,
+        len: usize,
+        capacity: usize,
+    ) -> Result<Self, Diagnostic> {
+        let _target = self.target.enter();
+        let capacity = capacity.max(len);
+        let name = format!("{}_heap", access_string.replace(|c: char| !c.is_alphanumeric() && c != '_', "_"));
+
+        let place = self.resolve(access_string)?;
+        if !std::ptr::eq(place.kind.underlying(), container.kind.underlying()) {
+            return Err(whole(access_string, access::Error::at(access_string, access::ErrorKind::Mismatch {
+                expected: Box::new(container.kind.clone()),
+                found: Box::new(place.kind.clone()),
+            })));
+        }
+        let field = |comp: &Kind, name: &str| match comp {
+            Kind::Composite(comp) => comp.field_layout(name).map(|(_, slot)| slot.offset as usize),
+            _ => None,
+        };
+        let at = |name: &str| place.address + field(container.kind, name).unwrap();
+        let word = |value: usize| PrimValue::Size(value as u64);
+
+        if container.flavor == Flavor::CxxString && capacity <= SSO_CAPACITY {
+            let span = self.allocation_of(place.address).map(|allocation| allocation.name.to_string());
+            self.write_pointer(at("_M_p"), at("_M_local"), span);
+            self.write_at(at("_M_string_length"), word(len));
+            return Ok(self);
+        }
+
+        let fields = if container.is_single() {
+            vec![container.elem.field_named("value")]
+        } else {
+            // std::string keeps room for its terminating NUL.
+            let spare = capacity - len + usize::from(container.flavor == Flavor::CxxString);
+
+            [(len, "items"), (spare, "spare")].into_iter()
+                .filter(|(count, _)| *count > 0)
+                .map(|(count, name)| container.context.add(Array::new(container.elem, count)).field_named(name))
+                .collect()
+        };
+
+        self = self.span(&name, fields);
+        let Some(Segment::Span(_, size, _)) = self.segments.last() else { unreachable!() };
+        let start = self.base_adr + self.data.len() - size;
+        let stride = container.elem.size_of() as usize;
+
+        let (pointers, counts) = match container.flavor {
+            Flavor::Vec | Flavor::String => (vec![(at("ptr"), start)], vec![(at("cap"), capacity), (at("len"), len)]),
+            Flavor::Box => (vec![(at("ptr"), start)], vec![]),
+            Flavor::Rc | Flavor::Arc => (vec![(at("ptr"), start)], vec![
+                (start + field(container.elem, "strong").unwrap(), 1),
+                (start + field(container.elem, "weak").unwrap(), 1),
+            ]),
+            Flavor::Vector => (vec![
+                (at("_M_start"), start),
+                (at("_M_finish"), start + stride * len),
+                (at("_M_end_of_storage"), start + stride * capacity),
+            ], vec![]),
+            Flavor::CxxString => (vec![(at("_M_p"), start)], vec![
+                (at("_M_string_length"), len),
+                (at("_M_local"), capacity),
+            ]),
+        };
+
+        for (address, value) in pointers {
+            self.write_pointer(address, value, Some(name.clone()));
+        }
+        for (address, value) in counts {
+            self.write_at(address, word(value));
+        }
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::target::Target;
+
+    fn read(ribbon: &MemRibbon, access_string: &str) -> Option<i128> {
+        ribbon.read(&ribbon.at(access_string))?.as_int()
+    }
+
+    #[test]
+    fn vec_heap() {
+        let context = Context::new();
+        let uint32_t = context.add(Primitive::U32);
+        let vec = Container::vec(&context, uint32_t);
+
+        let ribbon = MemRibbon::new(0)
+            .target(Target::lp64())
+            .span("s", vec![vec.kind.field_named("v")])
+            .heap("s.v", &vec, 3, 5)
+            .unwrap();
+
+        assert_eq!(vec.kind.to_string(), "Vec<uint32_t>");
+        assert_eq!(ribbon.at("s.v.ptr").address, 24);
+        assert_eq!(read(&ribbon, "s.v.len"), Some(3));
+        assert_eq!(ribbon.provenance.get(&0).map(String::as_str), Some("s_v_heap"));
+        assert_eq!(ribbon.at("s_v_heap.spare[1]").address, 40);
+        assert_eq!(ribbon.data.len(), 44);
+
+        let ribbon = MemRibbon::new(0)
+            .span("s", vec![uint32_t.field_named("n"), vec.kind.field_named("v")]);
+        let err = ribbon.heap("s.n", &vec, 1, 1).err().unwrap();
+        assert_eq!(err.message, "Expected Vec<uint32_t> at s.n, found uint32_t");
+    }
+
+    #[test]
+    fn rc_and_sso() {
+        let context = Context::new();
+        let rc = Container::rc(&context, context.add(Primitive::U16));
+        let string = Container::cxx_string(&context);
+
+        let ribbon = MemRibbon::new(0)
+            .target(Target::lp64())
+            .span("s", vec![rc.kind.field_named("rc"), string.kind.field_named("short")])
+            .heap("s.rc", &rc, 1, 1)
+            .unwrap()
+            .heap("s.short", &string, 5, 5)
+            .unwrap();
+
+        assert_eq!(rc.elem.to_string(), "RcBox<uint16_t>");
+        assert_eq!(read(&ribbon, "s.rc.ptr.strong"), Some(1));
+        assert_eq!(ribbon.at("s.short._M_p").address, ribbon.at("s.short._M_local._M_local_buf").address);
+        assert_eq!(ribbon.at("s.rc.ptr.value").address, 56);
+        assert_eq!(ribbon.data.len(), 64);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    access::{self, Indirection, Trace},
+    kind::{Kind, Primitive, PrimValue, tagged::Niche},
+};
+
+use std::fmt;
+
+use super::CType;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    /// Each value names exactly one variant.
+    Named,
+    /// Values are bitwise ORs of the variants.
+    Flags,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Named => "enum",
+            Mode::Flags => "flag set",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variant {
+    pub name: String,
+    pub value: i64,
+}
+
+impl Variant {
+    pub fn new(name: impl ToString, value: i64) -> Self {
+        Self { name: name.to_string(), value }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Enum {
+    pub name: String,
+    pub mode: Mode,
+    pub repr: Primitive,
+    pub variants: Vec<Variant>,
+}
+
+impl Enum {
+    pub fn named(name: impl ToString, repr: Primitive, variants: Vec<Variant>) -> Self {
+        Self {
+            name: name.to_string(),
+            mode: Mode::Named,
+            repr,
+            variants,
+        }
+    }
+
+    pub fn flags(name: impl ToString, repr: Primitive, variants: Vec<Variant>) -> Self {
+        Self {
+            name: name.to_string(),
+            mode: Mode::Flags,
+            repr,
+            variants,
+        }
+    }
+
+    /// Values outside the declared variants are invalid for a named enum,
+    /// as with a fieldless Rust enum.
+    pub fn niche(&self) -> Option<Niche> {
+        if self.mode == Mode::Flags {
+            return None;
+        }
+
+        let min = self.variants.iter().map(|variant| variant.value).min()?;
+        let max = self.variants.iter().map(|variant| variant.value).max()?;
+
+        Some(Niche::new(self.repr, min as u64, max as u64))
+    }
+
+    pub fn variant(&self, value: i64) -> Option<&Variant> {
+        self.variants.iter().find(|variant| variant.value == value)
+    }
+
+    /// Names the value as shown in the repr column, such as `RED (2)` or `READ|WRITE`.
+    ///
+    /// Values that no variant accounts for are returned as `Err`, with the
+    /// unknown part marked by a `?`.
+    pub fn decode(&self, value: &PrimValue) -> Result<String, String> {
+        let Some(value) = value.as_int() else {
+            return Err(format!("?{value}"));
+        };
+
+        match self.mode {
+            Mode::Named => match self.variant(value as i64) {
+                Some(variant) => Ok(format!("{} ({value})", variant.name)),
+                None => Err(format!("? ({value})")),
+            },
+            Mode::Flags => self.decode_flags(value as u64),
+        }
+    }
+
+    fn decode_flags(&self, value: u64) -> Result<String, String> {
+        let bits = self.repr.size_of() as u32 * 8;
+        let mut rest = value & u64::MAX.checked_shr(64 - bits).unwrap_or(u64::MAX);
+
+        if rest == 0 {
+            return Ok(self.variant(0).map_or("0".to_string(), |variant| variant.name.clone()));
+        }
+
+        let mut names = Vec::new();
+        for variant in &self.variants {
+            let mask = variant.value as u64;
+            if mask != 0 && rest & mask == mask {
+                names.push(variant.name.clone());
+                rest &= !mask;
+            }
+        }
+
+        if rest == 0 {
+            Ok(names.join("|"))
+        } else {
+            names.push(format!("?{rest:#x}"));
+            Err(names.join("|"))
+        }
+    }
+}
+
+impl<'kind> CType<'kind> for Enum {
+    fn description(&self) -> &dyn fmt::Display {
+        &self.mode
+    }
+
+    fn size_of(&self) -> u64 {
+        self.repr.size_of()
+    }
+
+    fn align_of(&self) -> u64 {
+        self.repr.align_of()
+    }
+
+    fn access_with(&self, indirection: Indirection, trace: Trace<'_, 'kind>) -> access::Result<'kind> {
+        Err(access::Error::at(
+            trace.field_name,
+            access::ErrorKind::operation(&indirection, Kind::Enum(self.clone())),
+        ))
+    }
+}
+
+impl fmt::Display for Enum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn color() -> Enum {
+        Enum::named("Color", Primitive::U8, vec![
+            Variant::new("RED", 2),
+            Variant::new("GREEN", 3),
+        ])
+    }
+
+    fn access() -> Enum {
+        Enum::flags("Access", Primitive::U16, vec![
+            Variant::new("NONE", 0),
+            Variant::new("READ", 1),
+            Variant::new("WRITE", 2),
+            Variant::new("EXEC", 4),
+        ])
+    }
+
+    #[test]
+    fn named() {
+        assert_eq!(color().decode(&PrimValue::U8(2)), Ok("RED (2)".to_string()));
+        assert_eq!(color().decode(&PrimValue::U8(7)), Err("? (7)".to_string()));
+    }
+
+    #[test]
+    fn flags() {
+        assert_eq!(access().decode(&PrimValue::U16(3)), Ok("READ|WRITE".to_string()));
+        assert_eq!(access().decode(&PrimValue::U16(0)), Ok("NONE".to_string()));
+        assert_eq!(access().decode(&PrimValue::U16(0x11)), Err("READ|?0x10".to_string()));
+    }
+}
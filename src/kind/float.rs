@@ -0,0 +1,322 @@
+use std::fmt;
+
+/// Bit layout of a binary floating point format: a sign bit, then a biased
+/// exponent, then the fraction, from the most significant bit down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FloatFormat {
+    pub exponent: u32,
+    pub fraction: u32,
+    /// Whether the leading digit of the significand is stored, as in the x87
+    /// 80-bit format, rather than implied by the exponent.
+    pub explicit_int: bool,
+}
+
+/// What a floating point bit pattern stands for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Class {
+    Zero,
+    Subnormal,
+    Normal,
+    Infinite,
+    Nan,
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Class::Zero => "zero",
+            Class::Subnormal => "subnormal",
+            Class::Normal => "normal",
+            Class::Infinite => "infinity",
+            Class::Nan => "NaN",
+        })
+    }
+}
+
+/// The fields of a floating point bit pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FloatParts {
+    pub negative: bool,
+    /// The stored, biased exponent.
+    pub exponent: u32,
+    /// The stored fraction, including the integer bit of formats that keep it.
+    pub fraction: u128,
+    pub class: Class,
+}
+
+/// Rounds `value * 2^shift` to the nearest integer, ties to even.
+fn shift_round(value: u128, shift: i32) -> u128 {
+    if shift >= 0 {
+        return value << shift;
+    }
+
+    let shift = shift.unsigned_abs();
+    if shift >= 128 {
+        return 0;
+    }
+
+    let quotient = value >> shift;
+    let remainder = value & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+
+    if remainder > half || (remainder == half && quotient & 1 == 1) {
+        quotient + 1
+    } else {
+        quotient
+    }
+}
+
+impl FloatFormat {
+    pub const F16: Self = Self { exponent: 5, fraction: 10, explicit_int: false };
+    pub const BF16: Self = Self { exponent: 8, fraction: 7, explicit_int: false };
+    pub const F32: Self = Self { exponent: 8, fraction: 23, explicit_int: false };
+    pub const F64: Self = Self { exponent: 11, fraction: 52, explicit_int: false };
+    pub const X87: Self = Self { exponent: 15, fraction: 63, explicit_int: true };
+    pub const F128: Self = Self { exponent: 15, fraction: 112, explicit_int: false };
+
+    pub fn bias(&self) -> i32 {
+        (1 << (self.exponent - 1)) - 1
+    }
+
+    /// Bits taken by the fraction, counting a stored integer bit.
+    pub fn significand_bits(&self) -> u32 {
+        self.fraction + self.explicit_int as u32
+    }
+
+    pub fn bits(&self) -> u32 {
+        1 + self.exponent + self.significand_bits()
+    }
+
+    /// Bytes holding the value, before any padding the target adds.
+    pub fn byte_len(&self) -> usize {
+        self.bits().div_ceil(8) as usize
+    }
+
+    fn max_exponent(&self) -> u32 {
+        (1 << self.exponent) - 1
+    }
+
+    pub fn parts(&self, bits: u128) -> FloatParts {
+        let significand_bits = self.significand_bits();
+        let fraction = bits & ((1 << significand_bits) - 1);
+        let exponent = (bits >> significand_bits) as u32 & self.max_exponent();
+        let negative = bits >> (self.bits() - 1) & 1 != 0;
+        let stored_fraction = fraction & ((1 << self.fraction) - 1);
+
+        let class = match exponent {
+            0 if fraction == 0 => Class::Zero,
+            0 => Class::Subnormal,
+            max if max == self.max_exponent() && stored_fraction == 0 => Class::Infinite,
+            max if max == self.max_exponent() => Class::Nan,
+            _ => Class::Normal,
+        };
+
+        FloatParts { negative, exponent, fraction, class }
+    }
+
+    /// The value of `bits`, rounded to the nearest `f64`.
+    pub fn to_f64(&self, bits: u128) -> f64 {
+        let parts = self.parts(bits);
+        let sign = if parts.negative { -1.0 } else { 1.0 };
+
+        let magnitude = match parts.class {
+            Class::Zero => 0.0,
+            Class::Infinite => f64::INFINITY,
+            Class::Nan => f64::NAN,
+            Class::Subnormal | Class::Normal => {
+                // Scaled in two steps, since wider formats reach powers of
+                // two beyond f64's range even for values within it.
+                let exponent = parts.exponent.max(1) as i32 - self.bias();
+                self.significand(&parts) * 2f64.powi(exponent / 2) * 2f64.powi(exponent - exponent / 2)
+            }
+        };
+
+        sign * magnitude
+    }
+
+    /// The significand of a finite value, between 1 and 2 unless it is subnormal.
+    fn significand(&self, parts: &FloatParts) -> f64 {
+        let implicit = match (parts.class, self.explicit_int) {
+            (Class::Normal, false) => 1 << self.fraction,
+            _ => 0,
+        };
+        (parts.fraction | implicit) as f64 / 2f64.powi(self.fraction as i32)
+    }
+
+    /// The bit pattern nearest to `value`, rounding ties to even.
+    pub fn from_f64(&self, value: f64) -> u128 {
+        let sign = (value.is_sign_negative() as u128) << (self.bits() - 1);
+        let exponent_shift = self.significand_bits();
+        let max_exponent = self.max_exponent() as u128;
+        let int_bit = (self.explicit_int as u128) << self.fraction;
+
+        if value.is_nan() {
+            return sign | max_exponent << exponent_shift | int_bit | 1 << (self.fraction - 1);
+        }
+        if value.is_infinite() {
+            return sign | max_exponent << exponent_shift | int_bit;
+        }
+        if value == 0.0 {
+            return sign;
+        }
+
+        // value = significand * 2^(exponent - 52), with the top bit of significand at bit 52.
+        let raw = value.abs().to_bits();
+        let (mut significand, mut exponent) = match (raw >> 52) as i32 {
+            0 => (raw as u128, -1022),
+            biased => ((raw & ((1 << 52) - 1) | 1 << 52) as u128, biased - 1023),
+        };
+        while significand >> 52 == 0 {
+            significand <<= 1;
+            exponent -= 1;
+        }
+
+        let mut biased = exponent + self.bias();
+        let fraction = if biased > 0 {
+            let mut fraction = shift_round(significand, self.fraction as i32 - 52);
+            if fraction >> (self.fraction + 1) != 0 {
+                fraction >>= 1;
+                biased += 1;
+            }
+            fraction
+        } else {
+            let fraction = shift_round(significand, exponent + self.bias() - 1 + self.fraction as i32 - 52);
+            biased = (fraction >> self.fraction) as i32;
+            fraction
+        };
+
+        if biased as u128 >= max_exponent {
+            return sign | max_exponent << exponent_shift | int_bit;
+        }
+
+        let fraction = match self.explicit_int {
+            true => fraction,
+            false => fraction & ((1 << self.fraction) - 1),
+        };
+
+        sign | (biased as u128) << exponent_shift | fraction
+    }
+
+    /// The value of `bits` as sign, power of two and significand, such as `-1.5 × 2^3`.
+    pub fn describe(&self, bits: u128) -> String {
+        let parts = self.parts(bits);
+        let sign = if parts.negative { "-" } else { "+" };
+
+        match parts.class {
+            Class::Zero => format!("{sign}0"),
+            Class::Infinite => format!("{sign}inf"),
+            Class::Nan => "NaN".to_string(),
+            Class::Subnormal | Class::Normal => {
+                let exponent = parts.exponent.max(1) as i32 - self.bias();
+                let significand = self.significand(&parts);
+                format!("{sign}{significand} × 2^{exponent}")
+            }
+        }
+    }
+
+    /// The biased exponent and class of `bits`, as drawn under the byte column.
+    pub fn caption(&self, bits: u128) -> String {
+        let parts = self.parts(bits);
+        format!("e={}-{} {}", parts.exponent, self.bias(), parts.class)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        kind::{CType, leak, primitive::{Primitive, PrimValue}},
+        mem_ribbon::MemRibbon,
+        target::{Endian, Target},
+    };
+
+    #[test]
+    fn matches_native() {
+        for value in [1.5f32, -0.1, 3.0e38, 1.0e-40, 0.0, f32::INFINITY] {
+            let bits = FloatFormat::F32.from_f64(value as f64);
+            assert_eq!(bits, value.to_bits() as u128);
+            assert_eq!(FloatFormat::F32.to_f64(bits), value as f64);
+        }
+    }
+
+    #[test]
+    fn half() {
+        assert_eq!(FloatFormat::F16.from_f64(1.0), 0x3C00);
+        assert_eq!(FloatFormat::F16.from_f64(-2.0), 0xC000);
+        assert_eq!(FloatFormat::F16.from_f64(65504.0), 0x7BFF);
+        assert_eq!(FloatFormat::F16.from_f64(1.0e6), 0x7C00);
+        assert_eq!(FloatFormat::F16.parts(0x0001).class, Class::Subnormal);
+        assert_eq!(FloatFormat::F16.to_f64(0x0001), 2f64.powi(-24));
+        assert_eq!(FloatFormat::BF16.from_f64(1.0), 0x3F80);
+    }
+
+    #[test]
+    fn extended() {
+        let one = FloatFormat::X87.from_f64(1.0);
+        assert_eq!(one, 0x3FFF_8000_0000_0000_0000);
+        assert_eq!(FloatFormat::X87.to_f64(one), 1.0);
+        assert_eq!(FloatFormat::F128.from_f64(-2.0), 0xC000 << 112);
+        assert_eq!(FloatFormat::F128.to_f64(FloatFormat::F128.from_f64(0.1)), 0.1);
+        assert_eq!(FloatFormat::X87.parts(FloatFormat::X87.from_f64(f64::NAN)).class, Class::Nan);
+    }
+
+    #[test]
+    fn extended_range() {
+        for value in [1.0e-300, -2.5e-308, 5.0e-324, 1.0e300, f64::MAX] {
+            assert_eq!(FloatFormat::F128.to_f64(FloatFormat::F128.from_f64(value)), value);
+            assert_eq!(FloatFormat::X87.to_f64(FloatFormat::X87.from_f64(value)), value);
+        }
+
+        let bits = FloatFormat::F128.from_f64(1.0e-300);
+        assert_eq!(FloatFormat::F128.describe(bits), format!("+{} × 2^-997", 1.0e-300 / 2f64.powi(-997)));
+        assert_eq!(FloatFormat::F128.describe(1 << 112), "+1 × 2^-16382");
+    }
+
+    #[test]
+    fn breakdown() {
+        let bits = FloatFormat::F32.from_f64(-6.0);
+        assert_eq!(FloatFormat::F32.describe(bits), "-1.5 × 2^2");
+        assert_eq!(FloatFormat::F32.caption(bits), "e=129-127 normal");
+    }
+
+    #[test]
+    fn long_double() {
+        let sizes = [Target::lp64(), Target::i386(), Target::llp64(), Target::avr(), Target::ppc32()]
+            .map(|target| (target.size_of(Primitive::F80), target.align_of(Primitive::F80)));
+        assert_eq!(sizes, [(16, 16), (12, 4), (8, 8), (8, 1), (8, 8)]);
+
+        let _target = Target::llp64().enter();
+        assert_eq!(Primitive::F80.float_format(), Some(FloatFormat::F64));
+        let value = PrimValue::float(Primitive::F80, 1.5).unwrap();
+        assert_eq!(value.to_bytes(Endian::Little), 1.5f64.to_le_bytes());
+        assert_eq!(value.to_string(), "1.5");
+    }
+
+    #[test]
+    fn extended_floats() {
+        assert_eq!(Primitive::F16.size_of(), 2);
+        assert_eq!(Primitive::BF16.align_of(), 2);
+
+        let lp64 = Target::lp64();
+        assert_eq!((lp64.size_of(Primitive::F80), lp64.align_of(Primitive::F80)), (16, 16));
+        assert_eq!((lp64.size_of(Primitive::F128), lp64.align_of(Primitive::F128)), (16, 16));
+
+        let i386 = Target::i386();
+        assert_eq!((i386.size_of(Primitive::F80), i386.align_of(Primitive::F80)), (12, 4));
+        assert_eq!(i386.align_of(Primitive::F128), 16);
+
+        let long_double = leak(Primitive::F80);
+        let mut ribbon = MemRibbon::new(0)
+            .target(i386)
+            .break_down_floats()
+            .span("span", vec![long_double.field_named("x")]);
+
+        ribbon.set("span.x", PrimValue::float(Primitive::F80, -6.0).unwrap()).unwrap();
+        let place = ribbon.at("span.x");
+        assert_eq!(ribbon.read(&place).unwrap().to_string(), "-6");
+        assert_eq!(ribbon.bytes_at(8, 2), Some(vec![0x01, 0xC0]));
+
+        let fields = long_double.base_fields(None, &mut 0);
+        assert_eq!(fields[0].repr(&ribbon), Ok("-1.5 × 2^2".to_string()));
+    }
+}
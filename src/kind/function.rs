@@ -0,0 +1,120 @@
+use crate::{
+    access::{self, Indirection, Trace},
+    kind::{Kind, Primitive, PrimValue, CType},
+    mem_ribbon::MemRibbon,
+};
+
+use std::fmt;
+
+/// Language a function signature is written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Syntax {
+    /// `int (*)(char *, size_t)`
+    #[default]
+    C,
+    /// `fn(&str, usize) -> i32`, which is never null.
+    Rust,
+}
+
+/// A pointer to code, drawn with its signature and shown by the name of the
+/// symbol it points at.
+#[derive(Clone)]
+pub struct Function<'kind> {
+    pub syntax: Syntax,
+    pub params: Vec<&'kind Kind<'kind>>,
+    pub ret: Option<&'kind Kind<'kind>>,
+}
+
+impl<'kind> Function<'kind> {
+    pub fn new(params: Vec<&'kind Kind<'kind>>, ret: Option<&'kind Kind<'kind>>) -> Self {
+        Self { syntax: Syntax::C, params, ret }
+    }
+
+    pub fn rust(params: Vec<&'kind Kind<'kind>>, ret: Option<&'kind Kind<'kind>>) -> Self {
+        Self { syntax: Syntax::Rust, params, ret }
+    }
+
+    /// Names the code address `value` by the symbol it points at, such as `-> handle_irq`.
+    ///
+    /// Addresses with no symbol are returned as `Err`.
+    pub fn decode(ribbon: &MemRibbon, value: &PrimValue) -> Result<String, String> {
+        let address = value.as_int().ok_or_else(|| format!("?{value}"))? as usize;
+
+        match ribbon.symbol_at(address) {
+            Some(symbol) => Ok(format!("-> {symbol}")),
+            None if address == 0 => Ok("NULL".to_string()),
+            None => Err(format!("-> {address:#x}")),
+        }
+    }
+}
+
+impl<'kind> CType<'kind> for Function<'kind> {
+    fn description(&self) -> &dyn fmt::Display {
+        &"function pointer"
+    }
+
+    fn size_of(&self) -> u64 {
+        Primitive::Size.size_of()
+    }
+
+    fn align_of(&self) -> u64 {
+        Primitive::Size.align_of()
+    }
+
+    fn access_with(&self, indirection: Indirection, trace: Trace<'_, 'kind>) -> access::Result<'kind> {
+        Err(access::Error::at(
+            trace.field_name,
+            access::ErrorKind::operation(&indirection, Kind::Function(self.clone())),
+        ))
+    }
+}
+
+impl fmt::Display for Function<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params = self.params.iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match self.syntax {
+            Syntax::C => {
+                let ret = self.ret.map_or("void".to_string(), ToString::to_string);
+                let params = if params.is_empty() { "void".to_string() } else { params };
+                write!(f, "{ret} (*)({params})")
+            }
+            Syntax::Rust => {
+                write!(f, "fn({params})")?;
+                match self.ret {
+                    Some(ret) => write!(f, " -> {ret}"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kind::{leak, reference::{Reference, Mode}};
+
+    #[test]
+    fn signature() {
+        let int = leak(Primitive::I32);
+        let buf = leak(Reference::new(Mode::Ptr, leak(Primitive::Char)));
+
+        assert_eq!(Function::new(vec![buf, int], Some(int)).to_string(), "int32_t (*)(char*, int32_t)");
+        assert_eq!(Function::new(vec![], None).to_string(), "void (*)(void)");
+        assert_eq!(Function::rust(vec![int], Some(int)).to_string(), "fn(int32_t) -> int32_t");
+        assert_eq!(Function::rust(vec![], None).to_string(), "fn()");
+    }
+
+    #[test]
+    fn symbols() {
+        let ribbon = MemRibbon::new(0).symbol(0x4010, "handle_irq");
+
+        assert_eq!(Function::decode(&ribbon, &PrimValue::Size(0x4010)), Ok("-> handle_irq".to_string()));
+        assert_eq!(Function::decode(&ribbon, &PrimValue::Size(0)), Ok("NULL".to_string()));
+        assert_eq!(Function::decode(&ribbon, &PrimValue::Size(0x4020)), Err("-> 0x4020".to_string()));
+    }
+}
@@ -10,22 +10,130 @@ pub mod reference;
 pub mod composite;
 pub mod array;
 pub mod primitive;
+pub mod float;
+pub mod text;
+pub mod enumeration;
+pub mod tagged;
+pub mod function;
+pub mod qualified;
+pub mod container;
+pub mod vector;
 
 use reference::Reference;
 use composite::Composite;
 use primitive::{Primitive, PrimValue};
+use enumeration::Enum;
+use tagged::{Tagged, Niche};
+use function::Function;
+use qualified::{Qualified, Qualifiers};
+use vector::Vector;
 
-use self::{composite::Field, array::Array};
+use self::{composite::{Field, Bits}, array::Array};
 
 #[enum_dispatch]
 pub trait CType<'kind>: Sized + Display {
     fn description(&self) -> &dyn Display;
-    fn size_of(&self) -> u16;
-    fn align_of(&self) -> u16;
-    fn access_with(&self, indirection: Indirection, trace: Trace<'kind>) -> access::Result<'kind>;
+    fn size_of(&self) -> u64;
+    fn align_of(&self) -> u64;
+    fn access_with(&self, indirection: Indirection, trace: Trace<'_, 'kind>) -> access::Result<'kind>;
     fn display(&self) -> &dyn Display { self }
 }
 
+/// How the value of a base field is named in the repr column.
+#[derive(Clone, Copy, Default)]
+pub enum Decode<'kind> {
+    #[default]
+    Value,
+    Enum(&'kind Enum),
+    Tag(&'kind Tagged<'kind>),
+    /// A code address, named by the ribbon's symbol table.
+    Symbol,
+    /// One word of a fat reference, shown after its name.
+    Label(&'static str),
+    /// The vtable pointer of a trait object, named by the ribbon's symbol table.
+    Vtable,
+    /// A data pointer, described by the span it points into.
+    Pointer,
+    /// The given lane of a SIMD vector, shown alongside the other lanes.
+    Lane(usize),
+    /// The given byte of a `char` or `u8` array, shown as part of the whole
+    /// string when the ribbon renders strings.
+    Text(usize),
+    /// A `char*`, described by the string it points at when the ribbon renders strings.
+    CString,
+}
+
+impl<'kind> Decode<'kind> {
+    /// The text for `value`, which is an `Err` when the value names no variant.
+    pub fn text(&self, value: &PrimValue) -> Result<String, String> {
+        match self {
+            Decode::Value | Decode::Lane(_) | Decode::Text(_) if value.is_valid() => Ok(value.to_string()),
+            Decode::Value | Decode::Lane(_) | Decode::Text(_) => Err(value.to_string()),
+            Decode::Enum(enumeration) => enumeration.decode(value),
+            Decode::Tag(tagged) => tagged.decode(value),
+            Decode::Symbol => Ok(format!("-> {:#x}", value.as_int().unwrap_or_default())),
+            Decode::Label(label) => Ok(format!("{label}: {value}")),
+            Decode::Vtable => Ok(format!("vtable -> {:#x}", value.as_int().unwrap_or_default())),
+            Decode::Pointer | Decode::CString => Ok(format!("-> {:#x}", value.as_int().unwrap_or_default())),
+        }
+    }
+}
+
+/// A primitive stored inside a kind, as drawn in the repr column of a ribbon.
+#[derive(Clone, Copy)]
+pub struct BaseField<'kind> {
+    pub address: usize,
+    pub prim: Primitive,
+    pub bits: Option<Bits>,
+    pub decode: Decode<'kind>,
+}
+
+impl<'kind> BaseField<'kind> {
+    pub fn new(address: usize, prim: Primitive) -> Self {
+        Self { address, prim, bits: None, decode: Decode::Value }
+    }
+
+    /// A single field for `prim` at `address`, advancing `address` past it.
+    pub fn scalar(address: &mut usize, prim: Primitive) -> Vec<Self> {
+        let field = Self::new(*address, prim);
+        *address += prim.size_of() as usize;
+        vec![field]
+    }
+
+    pub fn byte_len(&self) -> usize {
+        match self.bits {
+            Some(bits) => bits.byte_len(),
+            None => self.prim.size_of() as usize,
+        }
+    }
+
+    pub fn parse(&self, ribbon: &MemRibbon) -> Option<PrimValue> {
+        match self.bits {
+            Some(bits) => ribbon.read_bits(self.address, bits, self.prim),
+            None => self.prim.parse_at(ribbon, self.address),
+        }
+    }
+
+    /// The text of the repr flag, which is an `Err` when an enum value has no name.
+    pub fn repr(&self, ribbon: &MemRibbon) -> Result<String, String> {
+        let value = self.parse(ribbon).ok_or_else(|| "???".to_string())?;
+        if let (true, None, Some(format)) = (ribbon.float_breakdown, self.bits, self.prim.float_format()) {
+            return Ok(format.describe(value.float_bits().unwrap_or_default()));
+        }
+
+        match self.decode {
+            Decode::Symbol => Function::decode(ribbon, &value),
+            Decode::Vtable => Function::decode(ribbon, &value)
+                .map(|text| format!("vtable {text}"))
+                .map_err(|text| format!("vtable {text}")),
+            Decode::Pointer => ribbon.describe_pointer(self.address, value.as_int().unwrap_or_default() as usize),
+            Decode::CString if ribbon.strings => ribbon.describe_string(self.address, value.as_int().unwrap_or_default() as usize),
+            Decode::CString => ribbon.describe_pointer(self.address, value.as_int().unwrap_or_default() as usize),
+            decode => decode.text(&value),
+        }
+    }
+}
+
 #[derive(Clone)]
 #[enum_dispatch(CType)]
 pub enum Kind<'kind> {
@@ -34,10 +142,15 @@ pub enum Kind<'kind> {
     Composite(Composite<'kind>),
     Array(Array<'kind>),
     Alias(Alias<'kind>),
+    Enum(Enum),
+    Tagged(Tagged<'kind>),
+    Function(Function<'kind>),
+    Qualified(Qualified<'kind>),
+    Vector(Vector<'kind>),
 }
 
 impl<'kind> Kind<'kind> {
-    pub fn align_pad(&self, offset: u16) -> u16 {
+    pub fn align_pad(&self, offset: u64) -> u64 {
         let align = self.align_of();
         let remainder = offset % align;
         if remainder == 0 {
@@ -47,19 +160,149 @@ impl<'kind> Kind<'kind> {
         }
     }
 
-    pub fn base_fields(&self, address: &mut usize) -> Vec<(usize, Primitive)> {
-        *address += self.align_pad(*address as u16) as usize;
+    /// The primitive this kind is stored as, looking through aliases.
+    pub fn primitive(&self) -> Option<Primitive> {
+        match self {
+            Kind::Primitive(prim) => Some(*prim),
+            Kind::Alias(alias) => alias.kind.primitive(),
+            Kind::Enum(enumeration) => Some(enumeration.repr),
+            Kind::Function(_) => Some(Primitive::Size),
+            Kind::Qualified(qualified) => qualified.kind.primitive(),
+            _ => None,
+        }
+    }
+
+    /// How values of this primitive-like kind are named, looking through aliases.
+    pub fn decode(&'kind self) -> Decode<'kind> {
+        match self {
+            Kind::Enum(enumeration) => Decode::Enum(enumeration),
+            Kind::Alias(alias) => alias.kind.decode(),
+            Kind::Qualified(qualified) => qualified.kind.decode(),
+            _ => Decode::Value,
+        }
+    }
+
+    /// Whether this kind is one byte of a string: a `char` or `u8`, looking
+    /// through aliases and qualifiers.
+    pub fn is_text(&'kind self) -> bool {
+        matches!(self.decode(), Decode::Value)
+            && matches!(self.primitive(), Some(Primitive::Char | Primitive::U8))
+    }
+
+    /// The invalid values within this kind that an enum could store its tag in.
+    pub fn niche(&self) -> Option<Niche> {
+        match self {
+            Kind::Primitive(Primitive::Bool) => Some(Niche::new(Primitive::Bool, 0, 1)),
+            Kind::Primitive(Primitive::RustChar) => Some(Niche::new(Primitive::RustChar, 0, char::MAX as u64)),
+            Kind::Primitive(_) => None,
+            Kind::Reference(refr) => match refr.mode {
+                reference::Mode::Ref | reference::Mode::Box => Some(Niche::new(Primitive::Size, 1, u64::MAX)),
+                reference::Mode::Ptr => None,
+            },
+            Kind::Composite(comp) => comp.niche(),
+            Kind::Array(array) => array.kind.niche().filter(|_| array.size > 0),
+            Kind::Alias(alias) => alias.kind.niche(),
+            Kind::Enum(enumeration) => enumeration.niche(),
+            Kind::Tagged(tagged) => tagged.layout().niche,
+            Kind::Function(function) => match function.syntax {
+                function::Syntax::Rust => Some(Niche::new(Primitive::Size, 1, u64::MAX)),
+                function::Syntax::C => None,
+            },
+            Kind::Qualified(qualified) => qualified.kind.niche(),
+            Kind::Vector(_) => None,
+        }
+    }
+
+    /// The primitives stored in a value of this kind placed at `address`,
+    /// advancing `address` past the value.
+    ///
+    /// Arrays whose length is read from memory are only listed when `ribbon` is given.
+    pub fn base_fields(&'kind self, ribbon: Option<&MemRibbon>, address: &mut usize) -> Vec<BaseField<'kind>> {
+        match self {
+            Kind::Primitive(prim) => BaseField::scalar(address, *prim),
+            Kind::Reference(refr) => refr.base_fields(address),
+            Kind::Composite(comp) => comp.base_fields(ribbon, address),
+            Kind::Array(x) => x.base_fields(ribbon, address),
+            Kind::Vector(x) => x.base_fields(ribbon, address),
+            Kind::Alias(x) => x.kind.base_fields(ribbon, address),
+            Kind::Enum(x) => {
+                let mut fields = BaseField::scalar(address, x.repr);
+                fields[0].decode = Decode::Enum(x);
+                fields
+            }
+            Kind::Tagged(x) => x.base_fields(address),
+            Kind::Function(_) => {
+                let mut fields = BaseField::scalar(address, Primitive::Size);
+                fields[0].decode = Decode::Symbol;
+                fields
+            }
+            Kind::Qualified(x) => {
+                let start = *address;
+                let fields = x.kind.base_fields(ribbon, address);
+                *address = start + x.size_of() as usize;
+                fields
+            }
+        }
+    }
+
+    /// Name of a type held by value in this kind that is declared but not yet defined.
+    ///
+    /// References and function pointers may point to incomplete types, so they are not followed.
+    pub fn incomplete(&self) -> Option<String> {
+        match self {
+            Kind::Composite(comp) if !comp.defined.get() => Some(comp.name.clone()),
+            Kind::Composite(comp) => comp.fields.borrow().iter()
+                .find_map(|field| field.kind.incomplete()),
+            Kind::Array(array) => array.kind.incomplete(),
+            Kind::Alias(alias) => alias.kind.incomplete(),
+            Kind::Qualified(qualified) => qualified.kind.incomplete(),
+            Kind::Tagged(tagged) => tagged.variants.iter()
+                .flat_map(|variant| &variant.fields)
+                .find_map(|field| field.kind.incomplete()),
+            _ => None,
+        }
+    }
+
+    /// Bytes taken by a value of this kind at `address`, counting the
+    /// elements of arrays whose length is read from `ribbon`.
+    pub fn extent(&self, ribbon: &MemRibbon, address: usize) -> usize {
+        match self {
+            Kind::Composite(comp) => comp.extent(ribbon, address),
+            Kind::Alias(alias) => alias.kind.extent(ribbon, address),
+            Kind::Qualified(qualified) => qualified.kind.extent(ribbon, address)
+                .max(qualified.size_of() as usize),
+            _ => self.size_of() as usize,
+        }
+    }
+
+    /// Qualifiers on the outside of this kind, looking through aliases.
+    pub fn qualifiers(&self) -> Qualifiers {
+        match self {
+            Kind::Qualified(qualified) => qualified.qualifiers | qualified.kind.qualifiers(),
+            Kind::Alias(alias) => alias.kind.qualifiers(),
+            _ => Qualifiers::default(),
+        }
+    }
+
+    /// This kind without its qualifiers, as drawn and accessed.
+    pub fn unqualified(&self) -> &Self {
+        match self {
+            Kind::Qualified(qualified) => qualified.kind.unqualified(),
+            _ => self,
+        }
+    }
+
+    /// This kind seen through its typedefs and qualifiers.
+    pub fn underlying(&self) -> &Self {
         match self {
-            Kind::Primitive(prim) => vec![(*address, *prim)],
-            Kind::Reference(_) => vec![(*address, Primitive::Size)],
-            Kind::Composite(comp) => comp.base_fields(address),
-            Kind::Array(x) => x.base_fields(address),
-            Kind::Alias(x) => x.kind.base_fields(address),
+            Kind::Alias(alias) => alias.kind.underlying(),
+            Kind::Qualified(qualified) => qualified.kind.underlying(),
+            _ => self,
         }
     }
 
-    pub fn get_place_value(&'kind self, trace: Trace<'kind>) -> access::Result<'kind> {
-        let place_value = if let Kind::Reference(refr) = self {
+    pub fn get_place_value(&'kind self, trace: Trace<'_, 'kind>) -> access::Result<'kind> {
+        let place_value = if let (Kind::Reference(refr @ Reference { meta: reference::Meta::Thin, .. }), true) = (self, trace.follow_pointer) {
             let parsed_size = Primitive::Size
                 .parse_at(trace.ribbon, trace.address)
                 .ok_or_else(|| access::Error::at(
@@ -74,19 +317,23 @@ impl<'kind> Kind<'kind> {
 
             PlaceValue {
                 kind: refr.kind,
-                address: address as usize
+                address: address as usize,
+                bits: None,
+                qualifiers: refr.kind.qualifiers(),
             }
         } else {
             PlaceValue{
                 kind: self,
                 address: trace.address,
+                bits: None,
+                qualifiers: trace.qualifiers | self.qualifiers(),
             }
         };
 
         Ok(place_value)
     }
 
-    pub fn access(&'kind self, mut trace: Trace<'kind>) -> access::Result<'kind> {
+    pub fn access(&'kind self, mut trace: Trace<'_, 'kind>) -> access::Result<'kind> {
         match trace.path.pop_front() {
             None => self.get_place_value(trace),
             Some(indirection) => {
@@ -116,6 +363,12 @@ impl<'kind> Kind<'kind> {
     }
 }
 
+/// Allocates a kind for the rest of the test run, for tests that build kinds without a [`Context`](crate::context::Context).
+#[cfg(test)]
+pub(crate) fn leak<'kind>(kind: impl Into<Kind<'kind>>) -> &'kind Kind<'kind> {
+    Box::leak(Box::new(kind.into()))
+}
+
 impl fmt::Display for Kind<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.display().fmt(f)
@@ -142,15 +395,15 @@ impl<'kind> CType<'kind> for Alias<'kind> {
         self.kind.description()
     }
 
-    fn size_of(&self) -> u16 {
+    fn size_of(&self) -> u64 {
         self.kind.size_of()
     }
 
-    fn align_of(&self) -> u16 {
+    fn align_of(&self) -> u64 {
         self.kind.align_of()
     }
 
-    fn access_with(&self, indirection: Indirection, trace: Trace<'kind>) -> access::Result<'kind> {
+    fn access_with(&self, indirection: Indirection, trace: Trace<'_, 'kind>) -> access::Result<'kind> {
         self.kind.access_with(indirection, trace)
     }
 }
@@ -1,38 +1,93 @@
 use std::fmt;
-use pod::Pod;
 
 use crate::{
     access::{self, Trace, Indirection},
     kind::Kind,
     mem_ribbon::MemRibbon,
+    target::{Target, Endian},
 };
 
-use super::CType;
+use super::{CType, float::FloatFormat};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Primitive {
     Bool, Char,
-    U8, U16, U32, U64,
-    I8, I16, I32, I64,
+    U8, U16, U32, U64, U128,
+    I8, I16, I32, I64, I128,
              F32, F64,
+    /// IEEE-754 half precision, `_Float16`.
+    F16,
+    /// The truncated single precision `__bf16`, with a float's exponent range.
+    BF16,
+    /// The x87 80-bit extended `long double`, padded to the target's width.
+    F80,
+    /// IEEE-754 quadruple precision, `_Float128`.
+    F128,
+    Long, ULong,
     Size,
+    /// `wchar_t`, as wide as the target makes it.
+    WChar,
+    /// A UTF-16 code unit, `char16_t`.
+    Char16,
+    /// A UTF-32 code unit, `char32_t`.
+    Char32,
+    /// A Rust `char`, which must hold a Unicode scalar value.
+    RustChar,
+    /// The pointer-sized integers `ptrdiff_t`, `intptr_t`, `uintptr_t` and `ssize_t`.
+    PtrDiff, IntPtr, UIntPtr, SSize,
 }
 
 impl Primitive {
-    pub fn parse_at(&self, ribbon: &MemRibbon, address: usize) -> Option<PrimValue> {
-        let mut value = PrimValue::from(*self);
+    pub const ALL: [Primitive; 29] = {
+        use Primitive::*;
+        [
+            Bool, Char,
+            U8, U16, U32, U64, U128,
+            I8, I16, I32, I64, I128,
+            F32, F64, F16, BF16, F80, F128,
+            Long, ULong, Size,
+            WChar, Char16, Char32, RustChar,
+            PtrDiff, IntPtr, UIntPtr, SSize,
+        ]
+    };
 
-        let dest_slice = value.bytes_mut();
-        let source_slice = ribbon.bytes_at(
-            address - ribbon.base_adr,
-            dest_slice.len(),
-        )?;
+    /// The C primitive spelled `name`, such as `uint8_t` or `size_t`.
+    pub fn named(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|prim| prim.to_string() == name)
+    }
 
-        for (dst, src) in dest_slice.iter_mut().zip(source_slice) {
-            *dst = src;
-        }
+    pub fn is_signed(&self) -> bool {
+        use Primitive::*;
+        matches!(self, I8 | I16 | I32 | I64 | I128 | Long | PtrDiff | IntPtr | SSize) || self.is_float()
+    }
 
-        Some(value)
+    /// Whether this is a signed integer whose width depends on the target.
+    fn is_target_signed(&self) -> bool {
+        use Primitive::*;
+        matches!(self, Long | PtrDiff | IntPtr | SSize)
+    }
+
+    pub fn is_float(&self) -> bool {
+        self.float_format().is_some()
+    }
+
+    /// The bit layout of a floating point primitive.
+    pub fn float_format(&self) -> Option<FloatFormat> {
+        Some(match self {
+            Primitive::F16 => FloatFormat::F16,
+            Primitive::BF16 => FloatFormat::BF16,
+            Primitive::F32 => FloatFormat::F32,
+            Primitive::F64 => FloatFormat::F64,
+            Primitive::F80 => Target::with_active(|target| target.long_double_format),
+            Primitive::F128 => FloatFormat::F128,
+            _ => return None,
+        })
+    }
+
+    pub fn parse_at(&self, ribbon: &MemRibbon, address: usize) -> Option<PrimValue> {
+        let _target = ribbon.target.enter();
+        let bytes = ribbon.bytes_at(address, self.size_of() as usize)?;
+        Some(PrimValue::from_bytes(*self, &bytes, ribbon.target.endian))
     }
 }
 
@@ -41,23 +96,15 @@ impl<'kind> CType<'kind> for Primitive {
         self
     }
 
-    fn size_of(&self) -> u16 {
-        use Primitive::*;
-        match self {
-            Bool => 1,
-            Char => 1,
-            U8   => 1, U16 => 2, U32 => 4, U64 => 8,
-            I8   => 1, I16 => 2, I32 => 4, I64 => 8,
-            F32  => 4, F64 => 8,
-            Size => 4,
-        }
+    fn size_of(&self) -> u64 {
+        Target::with_active(|target| target.size_of(*self))
     }
 
-    fn align_of(&self) -> u16 {
-        self.size_of()
+    fn align_of(&self) -> u64 {
+        Target::with_active(|target| target.align_of(*self))
     }
 
-    fn access_with(&self, indirection: Indirection, trace: Trace<'kind>) -> access::Result<'kind> {
+    fn access_with(&self, indirection: Indirection, trace: Trace<'_, 'kind>) -> access::Result<'kind> {
         Err(access::Error::at(
             trace.field_name,
             access::ErrorKind::operation(&indirection, Kind::Primitive(*self)),
@@ -74,8 +121,17 @@ impl fmt::Display for Primitive {
             Char => "char",
             U8   => "uint8_t", U16 => "uint16_t", U32 => "uint32_t", U64 => "uint64_t",
             I8   => "int8_t",  I16 => "int16_t",  I32 => "int32_t",  I64 => "int64_t",
+            U128 => "unsigned __int128", I128 => "__int128",
             F32  => "float",   F64 => "double",
+            F16  => "_Float16", BF16 => "__bf16",
+            F80  => "long double", F128 => "_Float128",
+            Long => "long",    ULong => "unsigned long",
             Size => "size_t",
+            WChar => "wchar_t", Char16 => "char16_t", Char32 => "char32_t",
+            // Named apart from C's one-byte char, which it may be drawn beside.
+            RustChar => "Rust char",
+            PtrDiff => "ptrdiff_t", IntPtr => "intptr_t", UIntPtr => "uintptr_t",
+            SSize => "ssize_t",
         })
     }
 
@@ -87,43 +143,193 @@ pub enum PrimValue {
     U8(u8), U16(u16), U32(u32), U64(u64),
     I8(i8), I16(i16), I32(i32), I64(i64),
                       F32(f32), F64(f64),
-    Size(u32),
+    /// Floats without a native Rust type, kept as their bit patterns.
+    F16(u16), BF16(u16), F80(u128), F128(u128),
+    Long(i64), ULong(u64),
+    Size(u64),
+    U128(u128), I128(i128),
+    /// Character code units, kept as their raw values.
+    WChar(u32), Char16(u16), Char32(u32), RustChar(u32),
+    PtrDiff(i64), IntPtr(i64), UIntPtr(u64), SSize(i64),
 }
 
 impl PrimValue {
-    pub fn bytes(&self) -> &[u8] {
+    /// The floating point `prim` nearest to `value`.
+    pub fn float(prim: Primitive, value: f64) -> Option<Self> {
+        let bits = prim.float_format()?.from_f64(value);
+
+        Some(match prim {
+            Primitive::F16 => PrimValue::F16(bits as u16),
+            Primitive::BF16 => PrimValue::BF16(bits as u16),
+            Primitive::F32 => PrimValue::F32(value as f32),
+            Primitive::F64 => PrimValue::F64(value),
+            Primitive::F80 => PrimValue::F80(bits),
+            Primitive::F128 => PrimValue::F128(bits),
+            _ => unreachable!(),
+        })
+    }
+
+    /// The bit pattern of a floating point value.
+    pub fn float_bits(&self) -> Option<u128> {
+        use PrimValue::*;
+
+        Some(match *self {
+            F16(x) | BF16(x) => x as u128,
+            F32(x) => x.to_bits() as u128,
+            F64(x) => x.to_bits() as u128,
+            F80(x) | F128(x) => x,
+            _ => return None,
+        })
+    }
+
+    pub fn primitive(&self) -> Primitive {
         use PrimValue::*;
 
         match self {
-            Bool(x) | Char(x) | U8(x) => x.as_bytes(),
-            U32(x) | Size(x) => x.as_bytes(),
-            U16(x) => x.as_bytes(),
-            U64(x) => x.as_bytes(),
-            I8(x)  => x.as_bytes(),
-            I16(x) => x.as_bytes(),
-            I32(x) => x.as_bytes(),
-            I64(x) => x.as_bytes(),
-            F32(x) => x.as_bytes(),
-            F64(x) => x.as_bytes(),
+            Bool(_)  => Primitive::Bool,
+            Char(_)  => Primitive::Char,
+            U8(_)    => Primitive::U8,
+            U16(_)   => Primitive::U16,
+            U32(_)   => Primitive::U32,
+            U64(_)   => Primitive::U64,
+            I8(_)    => Primitive::I8,
+            I16(_)   => Primitive::I16,
+            I32(_)   => Primitive::I32,
+            I64(_)   => Primitive::I64,
+            F32(_)   => Primitive::F32,
+            F64(_)   => Primitive::F64,
+            F16(_)   => Primitive::F16,
+            BF16(_)  => Primitive::BF16,
+            F80(_)   => Primitive::F80,
+            F128(_)  => Primitive::F128,
+            Long(_)  => Primitive::Long,
+            ULong(_) => Primitive::ULong,
+            Size(_)  => Primitive::Size,
+            U128(_)  => Primitive::U128,
+            I128(_)  => Primitive::I128,
+            WChar(_) => Primitive::WChar,
+            Char16(_) => Primitive::Char16,
+            Char32(_) => Primitive::Char32,
+            RustChar(_) => Primitive::RustChar,
+            PtrDiff(_) => Primitive::PtrDiff,
+            IntPtr(_) => Primitive::IntPtr,
+            UIntPtr(_) => Primitive::UIntPtr,
+            SSize(_) => Primitive::SSize,
         }
     }
 
-    fn bytes_mut(&mut self) -> &mut [u8] {
+    /// Whether the bits form a value of the primitive. A Rust `char` must be a
+    /// Unicode scalar value; every other bit pattern is valid.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            PrimValue::RustChar(x) => char::from_u32(x).is_some(),
+            _ => true,
+        }
+    }
+
+    /// The value as an integer, or `None` for floating point values and
+    /// `unsigned __int128` values beyond the range of `i128`.
+    pub fn as_int(&self) -> Option<i128> {
         use PrimValue::*;
 
-        match self {
-            Bool(x) | Char(x) | U8(x) => x.as_bytes_mut(),
-            U32(x) | Size(x) => x.as_bytes_mut(),
-            U16(x) => x.as_bytes_mut(),
-            U64(x) => x.as_bytes_mut(),
-            I8(x)  => x.as_bytes_mut(),
-            I16(x) => x.as_bytes_mut(),
-            I32(x) => x.as_bytes_mut(),
-            I64(x) => x.as_bytes_mut(),
-            F32(x) => x.as_bytes_mut(),
-            F64(x) => x.as_bytes_mut(),
+        Some(match *self {
+            Bool(x) | Char(x) | U8(x) => x as i128,
+            U16(x) | Char16(x) => x as i128,
+            U32(x) | WChar(x) | Char32(x) | RustChar(x) => x as i128,
+            U64(x) | ULong(x) | Size(x) | UIntPtr(x) => x as i128,
+            I8(x)  => x as i128,
+            I16(x) => x as i128,
+            I32(x) => x as i128,
+            I64(x) | Long(x) | PtrDiff(x) | IntPtr(x) | SSize(x) => x as i128,
+            I128(x) => x,
+            U128(x) => return i128::try_from(x).ok(),
+            F32(_) | F64(_) | F16(_) | BF16(_) | F80(_) | F128(_) => return None,
+        })
+    }
+
+    /// Decodes a value of the given primitive from bytes stored in `endian` order.
+    pub fn from_bytes(prim: Primitive, bytes: &[u8], endian: Endian) -> Self {
+        use PrimValue::*;
+
+        let mut le = bytes.to_vec();
+        endian.reorder(&mut le);
+
+        let negative = prim.is_target_signed()
+            && le.last().is_some_and(|byte| byte & 0x80 != 0);
+        le.resize(16, if negative { 0xFF } else { 0x00 });
+
+        match prim {
+            Primitive::Bool  => Bool(le[0]),
+            Primitive::Char  => Char(le[0]),
+            Primitive::U8    => U8(le[0]),
+            Primitive::U16   => U16(u16::from_le_bytes(array(&le))),
+            Primitive::U32   => U32(u32::from_le_bytes(array(&le))),
+            Primitive::U64   => U64(u64::from_le_bytes(array(&le))),
+            Primitive::I8    => I8(le[0] as i8),
+            Primitive::I16   => I16(i16::from_le_bytes(array(&le))),
+            Primitive::I32   => I32(i32::from_le_bytes(array(&le))),
+            Primitive::I64   => I64(i64::from_le_bytes(array(&le))),
+            Primitive::F32   => F32(f32::from_le_bytes(array(&le))),
+            Primitive::F64   => F64(f64::from_le_bytes(array(&le))),
+            Primitive::F16   => F16(u16::from_le_bytes(array(&le))),
+            Primitive::BF16  => BF16(u16::from_le_bytes(array(&le))),
+            Primitive::F80   => F80(u128::from_le_bytes(array(&le)) & ((1 << 80) - 1)),
+            Primitive::F128  => F128(u128::from_le_bytes(array(&le))),
+            Primitive::Long  => Long(i64::from_le_bytes(array(&le))),
+            Primitive::ULong => ULong(u64::from_le_bytes(array(&le))),
+            Primitive::Size  => Size(u64::from_le_bytes(array(&le))),
+            Primitive::U128  => U128(u128::from_le_bytes(array(&le))),
+            Primitive::I128  => I128(i128::from_le_bytes(array(&le))),
+            Primitive::WChar => WChar(u32::from_le_bytes(array(&le))),
+            Primitive::Char16 => Char16(u16::from_le_bytes(array(&le))),
+            Primitive::Char32 => Char32(u32::from_le_bytes(array(&le))),
+            Primitive::RustChar => RustChar(u32::from_le_bytes(array(&le))),
+            Primitive::PtrDiff => PtrDiff(i64::from_le_bytes(array(&le))),
+            Primitive::IntPtr => IntPtr(i64::from_le_bytes(array(&le))),
+            Primitive::UIntPtr => UIntPtr(u64::from_le_bytes(array(&le))),
+            Primitive::SSize => SSize(i64::from_le_bytes(array(&le))),
         }
     }
+
+    /// Encodes the value in `endian` order, truncated to the width of its
+    /// primitive on the active target.
+    pub fn to_bytes(&self, endian: Endian) -> Vec<u8> {
+        use PrimValue::*;
+
+        let mut bytes = match *self {
+            Bool(x) | Char(x) | U8(x) => vec![x],
+            U16(x) | Char16(x) => x.to_le_bytes().to_vec(),
+            U32(x) | WChar(x) | Char32(x) | RustChar(x) => x.to_le_bytes().to_vec(),
+            U64(x) | ULong(x) | Size(x) | UIntPtr(x) => x.to_le_bytes().to_vec(),
+            U128(x) => x.to_le_bytes().to_vec(),
+            I8(x)  => x.to_le_bytes().to_vec(),
+            I16(x) => x.to_le_bytes().to_vec(),
+            I32(x) => x.to_le_bytes().to_vec(),
+            I64(x) | Long(x) | PtrDiff(x) | IntPtr(x) | SSize(x) => x.to_le_bytes().to_vec(),
+            I128(x) => x.to_le_bytes().to_vec(),
+            F32(x) => x.to_le_bytes().to_vec(),
+            F64(x) => x.to_le_bytes().to_vec(),
+            F16(x) | BF16(x) => x.to_le_bytes().to_vec(),
+            F80(x) | F128(x) => x.to_le_bytes().to_vec(),
+        };
+
+        bytes.truncate(self.primitive().size_of() as usize);
+        endian.reorder(&mut bytes);
+        bytes
+    }
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    bytes[..N].try_into().unwrap()
+}
+
+/// Writes a code unit as the character it encodes, or as `U+XXXX` when it is
+/// a surrogate or beyond Unicode.
+fn write_char(f: &mut fmt::Formatter<'_>, code: u32) -> fmt::Result {
+    match char::from_u32(code) {
+        Some(ch) => write!(f, "{ch:?}"),
+        None => write!(f, "U+{code:04X}"),
+    }
 }
 
 impl fmt::Display for PrimValue {
@@ -143,7 +349,19 @@ impl fmt::Display for PrimValue {
             I64(x)  => write!(f, "{}", x),
             F32(x)  => write!(f, "{}", x),
             F64(x)  => write!(f, "{}", x),
+            F16(_) | BF16(_) | F80(_) | F128(_) => {
+                let format = self.primitive().float_format().unwrap();
+                write!(f, "{}", format.to_f64(self.float_bits().unwrap()))
+            }
+            Long(x) => write!(f, "{}", x),
+            ULong(x) => write!(f, "{}", x),
             Size(x) => write!(f, "{}", x),
+            U128(x) => write!(f, "{}", x),
+            I128(x) => write!(f, "{}", x),
+            WChar(x) | Char32(x) | RustChar(x) => write_char(f, x),
+            Char16(x) => write_char(f, x as u32),
+            PtrDiff(x) | IntPtr(x) | SSize(x) => write!(f, "{}", x),
+            UIntPtr(x) => write!(f, "{:#x}", x),
         }
     }
 }
@@ -164,7 +382,23 @@ impl From<Primitive> for PrimValue {
             Primitive::I64  => I64 (0),
             Primitive::F32  => F32 (0.0),
             Primitive::F64  => F64 (0.0),
+            Primitive::F16  => F16 (0),
+            Primitive::BF16 => BF16(0),
+            Primitive::F80  => F80 (0),
+            Primitive::F128 => F128(0),
+            Primitive::Long => Long(0),
+            Primitive::ULong => ULong(0),
             Primitive::Size => Size(0),
+            Primitive::U128 => U128(0),
+            Primitive::I128 => I128(0),
+            Primitive::WChar => WChar(0),
+            Primitive::Char16 => Char16(0),
+            Primitive::Char32 => Char32(0),
+            Primitive::RustChar => RustChar(0),
+            Primitive::PtrDiff => PtrDiff(0),
+            Primitive::IntPtr => IntPtr(0),
+            Primitive::UIntPtr => UIntPtr(0),
+            Primitive::SSize => SSize(0),
         }
     }
 }
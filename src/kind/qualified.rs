@@ -0,0 +1,200 @@
+use crate::{
+    access::{self, Indirection, Trace},
+    kind::{Kind, CType},
+    target::{Target, AtomicAbi},
+};
+
+use std::{fmt, ops::BitOr};
+
+/// The C type qualifiers applied to a kind.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Qualifiers {
+    pub constant: bool,
+    pub volatile: bool,
+    pub atomic: bool,
+}
+
+impl Qualifiers {
+    pub const CONST: Self = Self { constant: true, volatile: false, atomic: false };
+    pub const VOLATILE: Self = Self { constant: false, volatile: true, atomic: false };
+    pub const ATOMIC: Self = Self { constant: false, volatile: false, atomic: true };
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl BitOr for Qualifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self {
+            constant: self.constant || rhs.constant,
+            volatile: self.volatile || rhs.volatile,
+            atomic: self.atomic || rhs.atomic,
+        }
+    }
+}
+
+impl fmt::Display for Qualifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (self.constant, "const"),
+            (self.volatile, "volatile"),
+            (self.atomic, "_Atomic"),
+        ];
+
+        let text = names.iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        f.write_str(&text)
+    }
+}
+
+/// A kind with qualifiers, such as `const uint8_t` or `uint8_t* const`.
+#[derive(Clone, Copy)]
+pub struct Qualified<'kind> {
+    pub kind: &'kind Kind<'kind>,
+    pub qualifiers: Qualifiers,
+}
+
+impl<'kind> Qualified<'kind> {
+    pub fn new(kind: &'kind Kind<'kind>, qualifiers: Qualifiers) -> Self {
+        Self { kind, qualifiers }
+    }
+
+    pub fn constant(kind: &'kind Kind<'kind>) -> Self {
+        Self::new(kind, Qualifiers::CONST)
+    }
+
+    pub fn volatile(kind: &'kind Kind<'kind>) -> Self {
+        Self::new(kind, Qualifiers::VOLATILE)
+    }
+
+    pub fn atomic(kind: &'kind Kind<'kind>) -> Self {
+        Self::new(kind, Qualifiers::ATOMIC)
+    }
+
+    /// Size and alignment, which `_Atomic` may raise on targets with lock-free atomics.
+    fn layout(&self) -> (u64, u64) {
+        let (size, align) = (self.kind.size_of(), self.kind.align_of());
+        if !self.qualifiers.atomic {
+            return (size, align);
+        }
+
+        match Target::with_active(|target| target.atomics) {
+            AtomicAbi::Plain => (size, align),
+            AtomicAbi::LockFree { max_size } => {
+                match size.checked_next_power_of_two() {
+                    Some(rounded) if rounded <= max_size => (rounded, align.max(rounded)),
+                    _ => (size, align),
+                }
+            }
+        }
+    }
+}
+
+impl<'kind> CType<'kind> for Qualified<'kind> {
+    fn description(&self) -> &dyn fmt::Display {
+        self.kind.description()
+    }
+
+    fn size_of(&self) -> u64 {
+        self.layout().0
+    }
+
+    fn align_of(&self) -> u64 {
+        self.layout().1
+    }
+
+    fn access_with(&self, indirection: Indirection, mut trace: Trace<'_, 'kind>) -> access::Result<'kind> {
+        trace.qualifiers = trace.qualifiers | self.qualifiers;
+        self.kind.access_with(indirection, trace)
+    }
+}
+
+/// Qualifiers of a pointer follow its `*`, while all others lead the type.
+impl fmt::Display for Qualified<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Kind::Reference(_) | Kind::Function(_) => write!(f, "{} {}", self.kind, self.qualifiers),
+            _ => write!(f, "{} {}", self.qualifiers, self.kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kind::{
+        leak,
+        primitive::Primitive,
+        reference::{self, Reference},
+    };
+
+    #[test]
+    fn display() {
+        let uint8_t = leak(Primitive::U8);
+        let const_u8 = leak(Qualified::constant(uint8_t));
+        let ptr = leak(Reference::new(reference::Mode::Ptr, uint8_t));
+
+        assert_eq!(Reference::new(reference::Mode::Ptr, const_u8).to_string(), "const uint8_t*");
+        assert_eq!(Qualified::constant(ptr).to_string(), "uint8_t* const");
+        assert_eq!(
+            Qualified::new(uint8_t, Qualifiers::CONST | Qualifiers::VOLATILE).to_string(),
+            "const volatile uint8_t",
+        );
+    }
+
+    #[test]
+    fn atomic_layout() {
+        let triple = leak(crate::kind::array::Array::new(leak(Primitive::U8), 3));
+        let long_long = leak(Primitive::I64);
+
+        let _target = Target::i386().enter();
+        assert_eq!(Qualified::atomic(triple).size_of(), 4);
+        assert_eq!(Qualified::atomic(triple).align_of(), 4);
+        assert_eq!(long_long.align_of(), 4);
+        assert_eq!(Qualified::atomic(long_long).align_of(), 8);
+
+        let _target = Target::ilp32().enter();
+        assert_eq!(Qualified::atomic(triple).size_of(), 3);
+    }
+
+    #[test]
+    fn const_writes() {
+        use crate::{kind::{composite::Composite, primitive::PrimValue}, mem_ribbon::MemRibbon};
+
+        let uint32_t = leak(Primitive::U32);
+        let point = leak(Composite::product("point", vec![
+            uint32_t.field_named("x"),
+            leak(Qualified::constant(uint32_t)).field_named("y"),
+        ]));
+        let const_point = leak(Qualified::constant(point));
+        let to_const = leak(Reference::new(reference::Mode::Ptr, const_point));
+        let const_ptr = leak(Qualified::constant(leak(Reference::new(reference::Mode::Ptr, point))));
+
+        let mut ribbon = MemRibbon::new(0)
+            .span("s", vec![
+                point.field_named("p"),
+                const_point.field_named("c"),
+                to_const.field_named("to_const"),
+                const_ptr.field_named("const_ptr"),
+            ]);
+        ribbon.write_at(16, PrimValue::Size(0));
+        ribbon.write_at(20, PrimValue::Size(0));
+
+        assert!(ribbon.set("s.p.x", PrimValue::U32(1)).is_ok());
+        assert!(ribbon.set("s.p.y", PrimValue::U32(1)).is_err());
+        assert_eq!(
+            ribbon.set("s.c.x", PrimValue::U32(1)).err().unwrap().message,
+            "Cannot write to s.c.x, which is const",
+        );
+        assert!(ribbon.set("s.to_const->x", PrimValue::U32(1)).is_err());
+        assert!(ribbon.set("s.const_ptr->x", PrimValue::U32(1)).is_ok());
+        assert!(ribbon.set("s.const_ptr", PrimValue::Size(8)).is_err());
+    }
+}
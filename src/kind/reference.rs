@@ -1,6 +1,7 @@
 use crate::{
     access::{self, Indirection, Trace},
-    kind::{PrimValue, Kind, Primitive, CType},
+    kind::{PrimValue, Kind, Primitive, CType, BaseField, Decode, function::Syntax},
+    mem_ribbon::MemRibbon,
 };
 
 use std::fmt;
@@ -9,6 +10,8 @@ use std::fmt;
 pub enum Mode {
     Ref,
     Ptr,
+    /// An owning, non-null `Box<T>`, accessed like a reference.
+    Box,
 }
 
 impl fmt::Display for Mode {
@@ -16,25 +19,124 @@ impl fmt::Display for Mode {
         f.write_str(match self {
             Mode::Ref => "reference",
             Mode::Ptr => "pointer",
+            Mode::Box => "box",
         })
     }
 }
 
+/// What is stored after the data pointer of a reference to an unsized type.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Meta<'kind> {
+    /// A plain one-word pointer.
+    #[default]
+    Thin,
+    /// `[T]`, followed by the number of elements.
+    Slice,
+    /// `str`, followed by the number of bytes.
+    Str,
+    /// `dyn Trait`, followed by a pointer to the vtable of the named trait.
+    Dyn(&'kind str),
+}
+
 #[derive(Clone, Copy)]
 pub struct Reference<'kind> {
     pub mode: Mode,
     pub kind: &'kind Kind <'kind>,
+    pub meta: Meta<'kind>,
+    /// Whether a thin reference is spelled `T*` or `*const T`.
+    pub syntax: Syntax,
 }
 
 impl<'kind> Reference<'kind> {
     pub fn new(mode: Mode, kind: &'kind Kind<'kind>) -> Self {
-        Self { mode, kind }
+        Self { mode, kind, meta: Meta::Thin, syntax: Syntax::C }
+    }
+
+    /// A reference to a run of `kind`, such as `&[T]` or `*const [T]`.
+    pub fn slice(mode: Mode, kind: &'kind Kind<'kind>) -> Self {
+        Self { mode, kind, meta: Meta::Slice, syntax: Syntax::Rust }
+    }
+
+    /// A reference to UTF-8 text, such as `&str`, indexed as the bytes of `byte`.
+    pub fn str(mode: Mode, byte: &'kind Kind<'kind>) -> Self {
+        Self { mode, kind: byte, meta: Meta::Str, syntax: Syntax::Rust }
+    }
+
+    /// A trait object such as `Box<dyn Trait>`, which is accessed as the
+    /// concrete `kind` it points at.
+    pub fn dyn_trait(mode: Mode, trait_name: &'kind str, kind: &'kind Kind<'kind>) -> Self {
+        Self { mode, kind, meta: Meta::Dyn(trait_name), syntax: Syntax::Rust }
+    }
+
+    /// The same reference spelled in Rust, as `&T`, `*const T` or `Box<T>`.
+    pub fn rust(self) -> Self {
+        Self { syntax: Syntax::Rust, ..self }
+    }
+
+    pub fn is_fat(&self) -> bool {
+        self.meta != Meta::Thin
+    }
+
+    /// The data pointer and the word after it of a fat reference at `address`.
+    pub fn words(&self, ribbon: &MemRibbon, address: usize) -> Option<(usize, usize)> {
+        let word = Primitive::Size.size_of() as usize;
+        let data = Primitive::Size.parse_at(ribbon, address)?.as_int()?;
+        let meta = Primitive::Size.parse_at(ribbon, address + word)?.as_int()?;
+        Some((data as usize, meta as usize))
+    }
+
+    /// The data pointer, labelled `ptr`, then the length or vtable word of a fat reference.
+    pub fn base_fields(&self, address: &mut usize) -> Vec<BaseField<'kind>> {
+        let mut fields = BaseField::scalar(address, Primitive::Size);
+        let decode = match self.meta {
+            Meta::Thin if self.kind.is_text() => {
+                fields[0].decode = Decode::CString;
+                return fields;
+            }
+            Meta::Thin => {
+                fields[0].decode = Decode::Pointer;
+                return fields;
+            }
+            Meta::Slice | Meta::Str => Decode::Label("len"),
+            Meta::Dyn(_) => Decode::Vtable,
+        };
+
+        fields[0].decode = Decode::Label("ptr");
+        fields.extend(BaseField::scalar(address, Primitive::Size));
+        fields[1].decode = decode;
+        fields
+    }
+
+    /// Indexes a slice or string, checking the index against the stored length.
+    pub fn access_slice(
+        &self,
+        indirection: Indirection,
+        mut trace: Trace<'_, 'kind>,
+    ) -> access::Result<'kind> {
+        let Indirection::Index(idx) = indirection else {
+            return Err(access::Error::at(
+                trace.field_name,
+                access::ErrorKind::operation(&indirection, Kind::Reference(*self)),
+            ));
+        };
+
+        let (data, len) = self.words(trace.ribbon, trace.address)
+            .ok_or_else(|| access::Error::at(
+                &trace.field_name,
+                access::ErrorKind::Deref { old_addr: trace.address },
+            ))?;
+
+        let stride = self.kind.size_of() as usize;
+        trace.address = data;
+        trace.address = trace.check_index(idx, len, stride)?;
+        trace.qualifiers = self.kind.qualifiers();
+        self.kind.access(trace)
     }
 
     pub fn access_ref(
         &self,
         indirection: Indirection,
-        mut trace: Trace<'kind>,
+        mut trace: Trace<'_, 'kind>,
     ) -> access::Result<'kind> {
         if !indirection.is_field() {
             return Err(access::Error::at(
@@ -53,8 +155,9 @@ impl<'kind> Reference<'kind> {
             PrimValue::Size(addr) => addr as usize,
             _ => unreachable!(),
         };
+        trace.qualifiers = self.kind.qualifiers();
 
-        match self.kind {
+        match self.kind.unqualified() {
             Kind::Composite(comp) => comp.access_with(indirection, trace),
             _ => Err(access::Error::at(
                 trace.field_name,
@@ -63,13 +166,18 @@ impl<'kind> Reference<'kind> {
         }
     }
 
+    /// Follows a thin pointer, which must stay within the span it was derived from.
+    ///
+    /// Indices are also checked against the span it points into.
     pub fn access_ptr(
         &self,
         indirection: Indirection,
-        mut trace: Trace<'kind>,
+        mut trace: Trace<'_, 'kind>,
     ) -> access::Result<'kind> {
         let old_addr = trace.address;
         let ptr_val = Primitive::Size.parse_at(trace.ribbon, old_addr);
+        let ribbon = trace.ribbon;
+        let provenance = ribbon.provenance.get(&old_addr).map(String::as_str);
 
         trace.address = match ptr_val {
             None => return Err(access::Error::at(
@@ -79,18 +187,31 @@ impl<'kind> Reference<'kind> {
             Some(PrimValue::Size(adr)) => adr as usize,
             Some(_) => unreachable!(),
         };
+        trace.qualifiers = Default::default();
 
         match indirection {
-            Indirection::Arrow(field) => match self.kind {
-                Kind::Composite(comp) => comp.access_with(Indirection::Field(field), trace),
+            Indirection::Arrow(field) => match self.kind.unqualified() {
+                Kind::Composite(comp) => {
+                    trace.check_provenance(provenance, comp.size_of() as usize)?;
+                    trace.qualifiers = self.kind.qualifiers();
+                    comp.access_with(Indirection::Field(field), trace)
+                }
                 kind => Err(access::Error::at(
                     trace.field_name,
-                    access::ErrorKind::Arrow { kind: kind.clone() },
+                    access::ErrorKind::Arrow { kind: Box::new(kind.clone()) },
                 )),
             }
-            Indirection::Deref => self.kind.access(trace),
+            Indirection::Deref => {
+                trace.check_provenance(provenance, self.kind.size_of() as usize)?;
+                self.kind.access(trace)
+            }
             Indirection::Index(idx) => {
-                trace.address += self.kind.size_of() as usize * idx;
+                let stride = self.kind.size_of() as usize;
+                // A pointer outside every span is left for the provenance check to report.
+                let len = trace.ribbon.span_end(trace.address)
+                    .map_or(usize::MAX, |end| (end - trace.address).checked_div(stride).unwrap_or_default());
+                trace.address = trace.check_index(idx, len, stride)?;
+                trace.check_provenance(provenance, stride)?;
                 self.kind.access(trace)
             }
             Indirection::Field(_) => Err(access::Error::at(
@@ -106,29 +227,183 @@ impl<'kind> CType<'kind> for Reference<'kind> {
         &self.mode
     }
 
-    fn align_of(&self) -> u16 {
-        Primitive::Size.size_of()
+    fn align_of(&self) -> u64 {
+        Primitive::Size.align_of()
     }
 
-    fn size_of(&self) -> u16 {
-        Primitive::Size.size_of()
+    fn size_of(&self) -> u64 {
+        match self.meta {
+            Meta::Thin => Primitive::Size.size_of(),
+            _ => 2 * Primitive::Size.size_of(),
+        }
     }
     
-    fn access_with(&self, indirection: Indirection, trace: Trace<'kind>) -> access::Result<'kind> {
-        match self.mode {
-            Mode::Ref => self.access_ref(indirection, trace),
-            Mode::Ptr => self.access_ptr(indirection, trace),
+    fn access_with(&self, indirection: Indirection, trace: Trace<'_, 'kind>) -> access::Result<'kind> {
+        match (self.meta, self.mode) {
+            (Meta::Slice | Meta::Str, _) => self.access_slice(indirection, trace),
+            (_, Mode::Ref | Mode::Box) => self.access_ref(indirection, trace),
+            (_, Mode::Ptr) => self.access_ptr(indirection, trace),
         }
     }
 }
 
 impl fmt::Display for Reference<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let ref_type = match self.mode {
-            Mode::Ref => "&",
-            Mode::Ptr => "*",
+        let pointee = match self.meta {
+            Meta::Thin if self.syntax == Syntax::Rust => self.kind.to_string(),
+            Meta::Thin => {
+                return match self.mode {
+                    Mode::Ref => write!(f, "{}&", self.kind),
+                    Mode::Ptr => write!(f, "{}*", self.kind),
+                    Mode::Box => write!(f, "Box<{}>", self.kind),
+                };
+            }
+            Meta::Slice => format!("[{}]", self.kind),
+            Meta::Str => "str".to_string(),
+            Meta::Dyn(trait_name) => format!("dyn {trait_name}"),
         };
 
-        write!(f, "{}{}", self.kind, ref_type)
+        match self.mode {
+            Mode::Ref => write!(f, "&{pointee}"),
+            Mode::Ptr => write!(f, "*const {pointee}"),
+            Mode::Box => write!(f, "Box<{pointee}>"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{context::Context, kind::{composite::Composite, leak}, target::Target};
+
+    #[test]
+    fn fat_layout() {
+        let uint8_t = leak(Primitive::U8);
+        let unit = leak(Composite::product("Unit", vec![]));
+
+        assert_eq!(Reference::slice(Mode::Ref, uint8_t).to_string(), "&[uint8_t]");
+        assert_eq!(Reference::slice(Mode::Ptr, uint8_t).to_string(), "*const [uint8_t]");
+        assert_eq!(Reference::str(Mode::Ref, uint8_t).to_string(), "&str");
+        assert_eq!(Reference::dyn_trait(Mode::Box, "Any", unit).to_string(), "Box<dyn Any>");
+
+        let word = Primitive::Size.size_of();
+        assert_eq!(Reference::new(Mode::Ref, uint8_t).size_of(), word);
+        assert_eq!(Reference::str(Mode::Ref, uint8_t).size_of(), 2 * word);
+        assert_eq!(Reference::str(Mode::Ref, uint8_t).align_of(), Primitive::Size.align_of());
+    }
+
+    #[test]
+    fn avr_alignment() {
+        let char_ = leak(Primitive::Char);
+        let thin = leak(Reference::new(Mode::Ptr, char_));
+        let fat = leak(Reference::slice(Mode::Ref, char_));
+        let holder = |name: &str, ptr: &'static Kind<'static>| Composite::product(name, vec![
+            char_.field_named("c"),
+            ptr.field_named("p"),
+        ]);
+
+        let _target = Target::avr().enter();
+        assert_eq!(holder("thin", thin).size_of(), 3);
+        assert_eq!(holder("fat", fat).size_of(), 5);
+        assert_eq!(fat.align_of(), 1);
+    }
+
+    #[test]
+    fn slice_bounds() {
+        let uint32_t = leak(Primitive::U32);
+        let slice = leak(Reference::slice(Mode::Ref, uint32_t));
+
+        let _target = Target::lp64().enter();
+        let mut ribbon = MemRibbon::new(0)
+            .target(Target::lp64())
+            .span("s", vec![slice.field_named("items")])
+            .span("data", vec![leak(crate::kind::array::Array::new(uint32_t, 4)).field_named("data")]);
+        ribbon.write_at(0, PrimValue::Size(16));
+        ribbon.write_at(8, PrimValue::Size(3));
+
+        assert_eq!(ribbon.get("s.items[2]".parse().unwrap()).unwrap().address, 24);
+        assert!(matches!(
+            ribbon.get("s.items[3]".parse().unwrap()).err().unwrap().kind,
+            access::ErrorKind::OutOfBounds { index: 3, len: 3 },
+        ));
+
+        let fields = slice.base_fields(None, &mut 0);
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].repr(&ribbon), Ok("ptr: 16".to_string()));
+        assert_eq!(fields[1].repr(&ribbon), Ok("len: 3".to_string()));
+    }
+
+    #[test]
+    fn provenance() {
+        let uint32_t = leak(Primitive::U32);
+        let ptr = leak(Reference::new(Mode::Ptr, uint32_t));
+        let context = Context::new();
+
+        let mut ribbon = MemRibbon::new(0)
+            .span("a", vec![leak(crate::kind::array::Array::new(uint32_t, 2)).field_named("arr")])
+            .span("b", vec![uint32_t.field_named("x"), ptr.field_named("p")]);
+        ribbon.skip(4);
+        ribbon = ribbon.span("c", vec![ptr.field_named("q")]);
+
+        ribbon.point(&context, "b.p", "&a.arr[1]").unwrap();
+        assert_eq!(ribbon.at("b.p[0]").address, 4);
+        assert_eq!(ribbon.provenance.get(&12).map(String::as_str), Some("a"));
+        assert_eq!(
+            ptr.base_fields(None, &mut 12)[0].repr(&ribbon),
+            Ok("points into span a at offset 4".to_string()),
+        );
+
+        ribbon.write_at(12, PrimValue::Size(0));
+        assert!(ribbon.provenance.is_empty());
+        assert!(ribbon.get("b.p[2]".parse().unwrap()).is_err());
+
+        ribbon.write_at(20, PrimValue::Size(18));
+        assert!(matches!(
+            ribbon.get("c.q[0]".parse().unwrap()).err().unwrap().kind,
+            access::ErrorKind::Dangling { address: 18 },
+        ));
+        assert_eq!(ptr.base_fields(None, &mut 20)[0].repr(&ribbon), Err("dangling".to_string()));
+
+        // A pointer cast from an integer may point anywhere, while one stepped
+        // out of the array it was taken from, which only overrun mode allows, may not.
+        ribbon.point(&context, "c.q", "(uint32_t *)8").unwrap();
+        assert_eq!(ribbon.at("c.q[0]").address, 8);
+        assert!(ribbon.point(&context, "c.q", "&(&a.arr[1])[1]").is_err());
+        ribbon.allow_overrun = true;
+        ribbon.point(&context, "c.q", "&(&a.arr[1])[1]").unwrap();
+        ribbon.allow_overrun = false;
+        assert_eq!(ribbon.provenance.get(&20).map(String::as_str), Some("a"));
+        assert!(matches!(
+            ribbon.get("c.q[0]".parse().unwrap()).err().unwrap().kind,
+            access::ErrorKind::Foreign { address: 8, .. },
+        ));
+    }
+
+    #[test]
+    fn c_string() {
+        let char_ = leak(Primitive::Char);
+        let text = leak(crate::kind::array::Array::new(char_, 8));
+        let ptr = leak(Reference::new(Mode::Ptr, char_));
+
+        let mut ribbon = MemRibbon::new(0)
+            .render_strings()
+            .span("s", vec![text.field_named("buf")])
+            .span("t", vec![ptr.field_named("p")]);
+        ribbon.set_bytes("s.buf", "héllo\0".as_bytes()).unwrap();
+        ribbon.write_at(8, PrimValue::Size(1));
+
+        let fields = ptr.base_fields(None, &mut 8);
+        assert_eq!(fields[0].repr(&ribbon), Ok("-> \"éllo\\0\"".to_string()));
+
+        ribbon.set_bytes("s.buf", b"overflow").unwrap();
+        assert_eq!(
+            ribbon.set_bytes("s.buf", b"overflows").err().unwrap().message,
+            "Index 8 out of bounds for s.buf of length 8",
+        );
+        assert_eq!(ribbon.bytes_at(8, 4), Some(vec![1, 0, 0, 0]));
+        assert_eq!(fields[0].repr(&ribbon), Err("-> \"verflow\" no NUL".to_string()));
+
+        let buf = text.base_fields(None, &mut 0);
+        assert!(buf.iter().enumerate().all(|(index, field)| matches!(field.decode, Decode::Text(i) if i == index)));
     }
 }
@@ -0,0 +1,541 @@
+use crate::{
+    access::{self, Indirection, Trace},
+    kind::{Kind, Primitive, PrimValue, BaseField, Decode, composite::Field},
+    mem_ribbon::MemRibbon,
+    target::Endian,
+};
+
+use std::{fmt, ops::Range};
+
+use super::CType;
+
+/// Invalid bit patterns of a primitive stored inside a kind.
+///
+/// The valid values are `start..=end`, wrapping around at the width of `prim`,
+/// so `&T` is valid from 1 up to the pointer maximum and `bool` from 0 to 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Niche {
+    pub offset: u64,
+    pub prim: Primitive,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Niche {
+    pub fn new(prim: Primitive, start: u64, end: u64) -> Self {
+        let mut niche = Self { offset: 0, prim, start: 0, end: 0 };
+        niche.start = start & niche.mask();
+        niche.end = end & niche.mask();
+        niche
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.prim.size_of() as u32 * 8).min(63)
+    }
+
+    pub fn at(mut self, offset: u64) -> Self {
+        self.offset += offset;
+        self
+    }
+
+    /// Number of invalid values left in the niche.
+    pub fn available(&self) -> u64 {
+        self.start.wrapping_sub(self.end).wrapping_sub(1) & self.mask()
+    }
+
+    /// Claims `count` invalid values, returning the first of them and the niche that remains.
+    pub fn reserve(&self, count: u64) -> Option<(u64, Niche)> {
+        if count > self.available() {
+            return None;
+        }
+
+        let first = self.end.wrapping_add(1) & self.mask();
+        let end = self.end.wrapping_add(count) & self.mask();
+
+        Some((first, Niche { end, ..*self }))
+    }
+
+    fn relative(&self, value: u64, first: u64) -> u64 {
+        value.wrapping_sub(first) & self.mask()
+    }
+}
+
+/// How the active variant of a tagged enum is stored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tag {
+    /// The variant index, stored at offset 0 ahead of every payload.
+    Direct(Primitive),
+    /// The variants in `variants` other than `dataful` are stored as the
+    /// invalid values of `niche`, counting up from `first`.
+    Niche {
+        dataful: usize,
+        niche: Niche,
+        first: u64,
+        variants: Range<usize>,
+    },
+    /// An enum with a single variant needs no tag at all.
+    Single,
+}
+
+/// Placement of the tag and of every variant's fields on the active target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaggedLayout {
+    pub tag: Tag,
+    pub offsets: Vec<Vec<u64>>,
+    pub size: u64,
+    pub align: u64,
+    pub niche: Option<Niche>,
+}
+
+/// Rounds `value` up to a multiple of `align`, saturating for kinds too large for any target.
+fn round_up(value: u64, align: u64) -> u64 {
+    value.checked_next_multiple_of(align.max(1)).unwrap_or(u64::MAX)
+}
+
+#[derive(Clone)]
+pub struct Variant<'kind> {
+    pub name: String,
+    pub fields: Vec<Field<'kind>>,
+}
+
+impl<'kind> Variant<'kind> {
+    pub fn new(name: impl ToString, fields: Vec<Field<'kind>>) -> Self {
+        Self { name: name.to_string(), fields }
+    }
+
+    pub fn unit(name: impl ToString) -> Self {
+        Self::new(name, Vec::new())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.fields.iter().all(|field| field.size_of() == 0)
+    }
+
+    /// Lays the fields out in declaration order from `start`, returning their
+    /// offsets, the end of the last field and the largest alignment.
+    fn place(&self, start: u64) -> (Vec<u64>, u64, u64) {
+        let mut end = start;
+        let mut align = 1;
+
+        let offsets = self.fields.iter()
+            .map(|field| {
+                let offset = round_up(end, field.kind.align_of());
+                end = offset.saturating_add(field.size_of());
+                align = align.max(field.kind.align_of());
+                offset
+            })
+            .collect();
+
+        (offsets, end, align)
+    }
+
+    /// The niche with the most invalid values among the fields.
+    fn niche(&self, offsets: &[u64]) -> Option<Niche> {
+        self.fields.iter()
+            .zip(offsets)
+            .filter_map(|(field, offset)| field.kind.niche().map(|niche| niche.at(*offset)))
+            .max_by_key(Niche::available)
+    }
+}
+
+/// A Rust `enum` whose variants carry data, with the discriminant placed by rustc's rules.
+#[derive(Clone)]
+pub struct Tagged<'kind> {
+    pub name: String,
+    pub variants: Vec<Variant<'kind>>,
+    /// The tag type given by `#[repr(u8)]` and the like, which always stores
+    /// the tag ahead of the payload instead of in a niche.
+    pub repr: Option<Primitive>,
+    /// Whether the payloads follow the tag as the members of one union, as
+    /// `#[repr(C)]` lays them out, rather than each right after the tag.
+    pub union: bool,
+}
+
+impl<'kind> Tagged<'kind> {
+    pub fn new(name: impl ToString, variants: Vec<Variant<'kind>>) -> Self {
+        Self { name: name.to_string(), variants, repr: None, union: false }
+    }
+
+    pub fn repr(mut self, prim: Primitive) -> Self {
+        self.repr = Some(prim);
+        self
+    }
+
+    /// The `#[repr(C)]` layout of `struct { prim tag; union { ... } payload; }`.
+    pub fn repr_c(mut self, prim: Primitive) -> Self {
+        self.repr = Some(prim);
+        self.union = true;
+        self
+    }
+
+    /// `Option<T>`, with `None` declared first as in the standard library.
+    pub fn option(kind: &'kind Kind<'kind>) -> Self {
+        Self::new(
+            format!("Option<{kind}>"),
+            vec![
+                Variant::unit("None"),
+                Variant::new("Some", vec![Field::new(0, kind)]),
+            ],
+        )
+    }
+
+    /// Picks the smaller of the direct and niche-filling layouts,
+    /// preferring the niche when both are the same size.
+    pub fn layout(&self) -> TaggedLayout {
+        if self.repr.is_some() {
+            return self.direct_layout();
+        }
+
+        if self.variants.len() <= 1 {
+            return self.single_layout();
+        }
+
+        let direct = self.direct_layout();
+
+        match self.niche_layout() {
+            Some(niche) if niche.size <= direct.size => niche,
+            _ => direct,
+        }
+    }
+
+    fn single_layout(&self) -> TaggedLayout {
+        let (offsets, end, align) = self.variants.first()
+            .map(|variant| variant.place(0))
+            .unwrap_or((Vec::new(), 0, 1));
+        let niche = self.variants.first().and_then(|variant| variant.niche(&offsets));
+
+        TaggedLayout {
+            tag: Tag::Single,
+            offsets: vec![offsets],
+            size: round_up(end, align),
+            align,
+            niche,
+        }
+    }
+
+    fn direct_layout(&self) -> TaggedLayout {
+        let prim = self.repr.unwrap_or(match self.variants.len() {
+            0..=0x100 => Primitive::U8,
+            0x101..=0x10000 => Primitive::U16,
+            _ => Primitive::U32,
+        });
+
+        // Every payload of a union starts at the alignment of the most aligned one.
+        let start = match self.union {
+            true => round_up(prim.size_of(), self.variants.iter().map(|variant| variant.place(0).2).max().unwrap_or(1)),
+            false => prim.size_of(),
+        };
+
+        let mut size = prim.size_of();
+        let mut align = prim.align_of();
+        let offsets = self.variants.iter()
+            .map(|variant| {
+                let (offsets, end, field_align) = variant.place(start);
+                size = size.max(end);
+                align = align.max(field_align);
+                offsets
+            })
+            .collect();
+
+        let niche = Niche::new(prim, 0, (self.variants.len() as u64).saturating_sub(1));
+
+        TaggedLayout {
+            tag: Tag::Direct(prim),
+            offsets,
+            size: round_up(size, align),
+            align,
+            niche: Some(niche),
+        }
+    }
+
+    fn niche_layout(&self) -> Option<TaggedLayout> {
+        let mut dataful = self.variants.iter()
+            .enumerate()
+            .filter(|(_, variant)| !variant.is_empty())
+            .map(|(index, _)| index);
+
+        let index = dataful.next()?;
+        if dataful.next().is_some() {
+            return None;
+        }
+
+        let start = if index == 0 { 1 } else { 0 };
+        let end = if index == self.variants.len() - 1 { index - 1 } else { self.variants.len() - 1 };
+        let variants = start..end + 1;
+
+        let (offsets, end, align) = self.variants[index].place(0);
+        let niche = self.variants[index].niche(&offsets)?;
+        let (first, rest) = niche.reserve(variants.len() as u64)?;
+
+        let mut all_offsets = vec![Vec::new(); self.variants.len()];
+        all_offsets[index] = offsets;
+
+        Some(TaggedLayout {
+            tag: Tag::Niche { dataful: index, niche, first, variants },
+            offsets: all_offsets,
+            size: round_up(end, align),
+            align,
+            niche: Some(rest),
+        })
+    }
+
+    pub fn variant_index(&self, name: &str) -> Option<usize> {
+        self.variants.iter().position(|variant| variant.name == name)
+    }
+
+    /// Maps a value of the tag, or of the niche holding it, to a variant index.
+    pub fn variant_of(&self, value: &PrimValue) -> Option<usize> {
+        let value = value.as_int()? as u64;
+
+        match self.layout().tag {
+            Tag::Direct(_) => Some(value as usize).filter(|index| *index < self.variants.len()),
+            Tag::Niche { dataful, niche, first, variants } => {
+                let relative = niche.relative(value, first);
+                let index = variants.start + relative.min(variants.len() as u64) as usize;
+                Some(if variants.contains(&index) && index != dataful { index } else { dataful })
+            }
+            Tag::Single => Some(0),
+        }
+    }
+
+    /// Reads the tag of the enum stored at `address`.
+    pub fn active_variant(&self, ribbon: &MemRibbon, address: usize) -> Option<&Variant<'kind>> {
+        let _target = ribbon.target.enter();
+
+        let value = match self.layout().tag {
+            Tag::Direct(prim) => prim.parse_at(ribbon, address)?,
+            Tag::Niche { niche, .. } => niche.prim.parse_at(ribbon, address + niche.offset as usize)?,
+            Tag::Single => return self.variants.first(),
+        };
+
+        self.variant_of(&value).map(|index| &self.variants[index])
+    }
+
+    /// Writes the tag of the enum at `address` so that `index` becomes the active variant.
+    ///
+    /// Selecting the dataful variant of a niche-filled enum leaves memory
+    /// untouched, since only its payload can make it active.
+    pub fn set_variant(&self, ribbon: &mut MemRibbon, address: usize, index: usize) {
+        let _target = ribbon.target.enter();
+
+        let (prim, address, value) = match self.layout().tag {
+            Tag::Direct(prim) => (prim, address, index as u64),
+            Tag::Niche { dataful, niche, first, variants } if index != dataful => (
+                niche.prim,
+                address + niche.offset as usize,
+                first.wrapping_add((index - variants.start) as u64),
+            ),
+            _ => return,
+        };
+
+        let size = prim.size_of() as usize;
+        let value = PrimValue::from_bytes(prim, &value.to_le_bytes()[..size], Endian::Little);
+        ribbon.write_at(address, value);
+    }
+
+    /// Text of the repr flag for the primitive holding the tag.
+    pub fn decode(&'kind self, value: &PrimValue) -> Result<String, String> {
+        let Some(index) = self.variant_of(value) else {
+            return Err(format!("? ({value})"));
+        };
+        let variant = &self.variants[index];
+
+        match self.layout().tag {
+            Tag::Niche { dataful, .. } if index == dataful => match self.niche_decode().text(value) {
+                Ok(text) => Ok(format!("{}({text})", variant.name)),
+                Err(text) => Err(format!("{}({text})", variant.name)),
+            },
+            _ => Ok(variant.name.clone()),
+        }
+    }
+
+    /// How the dataful variant itself decodes the primitive holding its niche.
+    fn niche_decode(&'kind self) -> Decode<'kind> {
+        let layout = self.layout();
+        let Tag::Niche { dataful, niche, .. } = layout.tag else {
+            return Decode::Value;
+        };
+
+        self.variant_fields(dataful, &layout.offsets[dataful], 0)
+            .into_iter()
+            .find(|field| field.address == niche.offset as usize && field.prim == niche.prim)
+            .map(|field| field.decode)
+            .unwrap_or_default()
+    }
+
+    fn variant_fields(&'kind self, index: usize, offsets: &[u64], start: usize) -> Vec<BaseField<'kind>> {
+        self.variants[index].fields.iter()
+            .zip(offsets)
+            .flat_map(|(field, offset)| field.kind.base_fields(None, &mut (start + *offset as usize)))
+            .collect()
+    }
+
+    /// The tag, followed by the payload of the only variant that has one.
+    pub fn base_fields(&'kind self, address: &mut usize) -> Vec<BaseField<'kind>> {
+        let start = *address;
+        let layout = self.layout();
+        *address = start + layout.size as usize;
+
+        match layout.tag {
+            Tag::Direct(prim) => {
+                let mut tag = BaseField::new(start, prim);
+                tag.decode = Decode::Tag(self);
+
+                let mut dataful = self.variants.iter()
+                    .enumerate()
+                    .filter(|(_, variant)| !variant.is_empty());
+
+                let mut result = vec![tag];
+                if let (Some((index, _)), None) = (dataful.next(), dataful.next()) {
+                    result.extend(self.variant_fields(index, &layout.offsets[index], start));
+                }
+                result
+            }
+            Tag::Niche { dataful, niche, .. } => {
+                let mut result = self.variant_fields(dataful, &layout.offsets[dataful], start);
+                let niche_address = start + niche.offset as usize;
+
+                if let Some(field) = result.iter_mut()
+                    .find(|field| field.address == niche_address && field.prim == niche.prim)
+                {
+                    field.decode = Decode::Tag(self);
+                }
+                result
+            }
+            Tag::Single => self.variant_fields(0, &layout.offsets[0], start),
+        }
+    }
+}
+
+impl<'kind> CType<'kind> for Tagged<'kind> {
+    fn description(&self) -> &dyn fmt::Display {
+        &"enum"
+    }
+
+    fn size_of(&self) -> u64 {
+        self.layout().size
+    }
+
+    fn align_of(&self) -> u64 {
+        self.layout().align
+    }
+
+    fn access_with(&self, indirection: Indirection, mut trace: Trace<'_, 'kind>) -> access::Result<'kind> {
+        let variant_name = indirection.as_field().ok_or_else(|| access::Error::at(
+            &trace.field_name,
+            access::ErrorKind::operation(&indirection, Kind::from(self.clone())),
+        ))?;
+
+        let index = self.variant_index(variant_name).ok_or_else(|| access::Error::at(
+            &trace.field_name,
+            access::ErrorKind::SubField { name: variant_name.into() },
+        ))?;
+
+        let field_name = match trace.path.pop_front() {
+            Some(Indirection::Field(field_name)) => field_name,
+            Some(indirection) => return Err(access::Error::at(
+                variant_name,
+                access::ErrorKind::operation(&indirection, Kind::from(self.clone())),
+            )),
+            None => return Err(access::Error::at(
+                variant_name,
+                access::ErrorKind::DirectAccess,
+            )),
+        };
+
+        let variant = &self.variants[index];
+        let position = variant.fields.iter()
+            .position(|field| field.name.as_deref() == Some(field_name.as_str()))
+            .ok_or_else(|| access::Error::at(
+                variant_name,
+                access::ErrorKind::SubField { name: field_name.clone() },
+            ))?;
+
+        trace.address += self.layout().offsets[index][position] as usize;
+        trace.field_name = field_name;
+        variant.fields[position].kind.access(trace)
+    }
+}
+
+impl fmt::Display for Tagged<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{kind::{leak, reference::{self, Reference}}, target::Target};
+
+    #[test]
+    fn option_ref_is_pointer_sized() {
+        let uint32_t = leak(Primitive::U32);
+        let option = Tagged::option(leak(Reference::new(reference::Mode::Ref, uint32_t)));
+
+        let _target = Target::lp64().enter();
+        assert_eq!(option.size_of(), 8);
+        assert!(matches!(option.layout().tag, Tag::Niche { first: 0, .. }));
+    }
+
+    #[test]
+    fn option_ptr_is_tagged() {
+        let uint32_t = leak(Primitive::U32);
+        let option = Tagged::option(leak(Reference::new(reference::Mode::Ptr, uint32_t)));
+
+        assert_eq!(option.size_of(), 8);
+        assert_eq!(option.layout().tag, Tag::Direct(Primitive::U8));
+    }
+
+    #[test]
+    fn nested_option_bool() {
+        let option = leak(Tagged::option(leak(Primitive::Bool)));
+        let nested = Tagged::option(option);
+
+        assert_eq!(nested.size_of(), 1);
+        assert!(matches!(nested.layout().tag, Tag::Niche { first: 3, .. }));
+        assert_eq!(nested.decode(&PrimValue::Bool(2)), Ok("Some(None)".to_string()));
+        assert_eq!(nested.decode(&PrimValue::Bool(1)), Ok("Some(Some(true))".to_string()));
+        assert_eq!(nested.decode(&PrimValue::Bool(3)), Ok("None".to_string()));
+    }
+
+    #[test]
+    fn niche_variants_around_dataful() {
+        let bool_ = leak(Primitive::Bool);
+        let tagged = Tagged::new("E", vec![
+            Variant::unit("A"),
+            Variant::new("B", vec![Field::new(0, bool_)]),
+            Variant::unit("C"),
+        ]);
+
+        assert_eq!(tagged.size_of(), 1);
+        assert_eq!(tagged.variant_of(&PrimValue::Bool(2)), Some(0));
+        assert_eq!(tagged.variant_of(&PrimValue::Bool(3)), Some(1));
+        assert_eq!(tagged.variant_of(&PrimValue::Bool(4)), Some(2));
+        assert_eq!(tagged.variant_of(&PrimValue::Bool(0)), Some(1));
+    }
+
+    #[test]
+    fn active_variant() {
+        let uint8_t = leak(Primitive::U8);
+        let uint32_t = leak(Primitive::U32);
+        let message = leak(Tagged::new("Message", vec![
+            Variant::unit("Quit"),
+            Variant::new("Move", vec![Field::new("x", uint8_t), Field::new("y", uint32_t)]),
+        ]));
+
+        let mut ribbon = MemRibbon::new(0).span("span", vec![message.field_named("msg")]);
+
+        assert_eq!(message.size_of(), 8);
+        assert_eq!(ribbon.at("span.msg.Move.x").address, 1);
+        assert_eq!(ribbon.at("span.msg.Move.y").address, 4);
+
+        ribbon.set_variant("span.msg", "Move").unwrap();
+        ribbon.set("span.msg.Move.y", PrimValue::U32(7)).unwrap();
+        assert_eq!(ribbon.variant("span.msg"), Some("Move"));
+        assert!(matches!(ribbon.read(&ribbon.at("span.msg.Move.y")), Some(PrimValue::U32(7))));
+
+        ribbon.set_variant("span.msg", "Quit").unwrap();
+        assert_eq!(ribbon.variant("span.msg"), Some("Quit"));
+    }
+}
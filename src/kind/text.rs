@@ -0,0 +1,72 @@
+use std::ops::Range;
+
+/// Bytes up to and including the first NUL, and whether one was found.
+pub fn terminated(bytes: &[u8]) -> (&[u8], bool) {
+    match bytes.iter().position(|byte| *byte == 0) {
+        Some(nul) => (&bytes[..=nul], true),
+        None => (bytes, false),
+    }
+}
+
+/// Escapes `bytes` as the contents of a string literal, writing bytes that
+/// are not valid UTF-8 as `\xNN`.
+pub fn escape(bytes: &[u8]) -> String {
+    let mut text = String::new();
+
+    for chunk in bytes.utf8_chunks() {
+        text.extend(chunk.valid().chars().flat_map(char::escape_debug));
+        for byte in chunk.invalid() {
+            text.push_str(&format!("\\x{byte:02x}"));
+        }
+    }
+
+    text
+}
+
+/// A C string as a quoted literal ending in `\0`, which is an `Err` when
+/// `bytes` hold no NUL to end it.
+pub fn quote(bytes: &[u8]) -> Result<String, String> {
+    match terminated(bytes) {
+        (string, true) => Ok(format!("\"{}\\0\"", escape(&string[..string.len() - 1]))),
+        (string, false) => Err(format!("\"{}\" no NUL", escape(string))),
+    }
+}
+
+/// The byte ranges of the characters of `bytes` encoded in more than one byte.
+pub fn sequences(bytes: &[u8]) -> Vec<(Range<usize>, char)> {
+    let mut offset = 0;
+    let mut result = Vec::new();
+
+    for chunk in bytes.utf8_chunks() {
+        for ch in chunk.valid().chars() {
+            let len = ch.len_utf8();
+            if len > 1 {
+                result.push((offset..offset + len, ch));
+            }
+            offset += len;
+        }
+        offset += chunk.invalid().len();
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn c_strings() {
+        assert_eq!(quote(b"hi\0junk"), Ok("\"hi\\0\"".to_string()));
+        assert_eq!(quote(b"a\"b\n"), Err("\"a\\\"b\\n\" no NUL".to_string()));
+        assert_eq!(quote(b"\xff\0"), Ok("\"\\xff\\0\"".to_string()));
+    }
+
+    #[test]
+    fn utf8() {
+        let bytes = "né€\0".as_bytes();
+        assert_eq!(quote(bytes), Ok("\"né€\\0\"".to_string()));
+        assert_eq!(sequences(bytes), vec![(1..3, 'é'), (3..6, '€')]);
+        assert_eq!(sequences(b"\xc3x\xc3\xa9"), vec![(2..4, 'é')]);
+    }
+}
@@ -0,0 +1,133 @@
+use core::fmt;
+
+use crate::{
+    access::{self, Indirection, Trace},
+    kind::{Kind, BaseField, Decode},
+    mem_ribbon::MemRibbon,
+};
+
+use super::CType;
+
+/// A SIMD vector of `lanes` values of the `lane` kind, such as `__m128`,
+/// `float32x4_t` or `std::simd::f32x4`.
+///
+/// Unlike an array, a vector is aligned to its own size by default and is
+/// drawn as a single block named after the vector type.
+#[derive(Clone)]
+pub struct Vector<'kind> {
+    pub name: String,
+    pub lane: &'kind Kind<'kind>,
+    pub lanes: usize,
+    /// Alignment requested for the vector, which defaults to its size
+    /// rounded up to a power of two.
+    pub align: Option<u64>,
+}
+
+impl<'kind> Vector<'kind> {
+    pub fn new(name: impl ToString, lane: &'kind Kind<'kind>, lanes: usize) -> Self {
+        Self { name: name.to_string(), lane, lanes, align: None }
+    }
+
+    pub fn align(mut self, align: u64) -> Self {
+        self.align = Some(align);
+        self
+    }
+
+    /// Bytes taken by the lanes, before the vector is padded to its alignment.
+    fn lanes_size(&self) -> u64 {
+        self.lane.size_of().saturating_mul(self.lanes as u64)
+    }
+
+    pub fn base_fields(&self, ribbon: Option<&MemRibbon>, address: &mut usize) -> Vec<BaseField<'kind>> {
+        let start = *address;
+        let stride = self.lane.size_of() as usize;
+        *address = start + self.size_of() as usize;
+
+        (0..self.lanes)
+            .flat_map(|index| {
+                let mut fields = self.lane.base_fields(ribbon, &mut (start + stride * index));
+                for field in &mut fields {
+                    field.decode = Decode::Lane(index);
+                }
+                fields
+            })
+            .collect()
+    }
+}
+
+impl<'kind> CType<'kind> for Vector<'kind> {
+    fn description(&self) -> &dyn fmt::Display {
+        self
+    }
+
+    fn size_of(&self) -> u64 {
+        let align = self.align_of();
+        self.lanes_size().div_ceil(align).saturating_mul(align)
+    }
+
+    fn align_of(&self) -> u64 {
+        self.align.unwrap_or_else(|| {
+            self.lanes_size().checked_next_power_of_two().unwrap_or(1).max(self.lane.align_of())
+        })
+    }
+
+    fn access_with(&self, indirection: Indirection, mut trace: Trace<'_, 'kind>) -> access::Result<'kind> {
+        match indirection {
+            Indirection::Index(idx) => {
+                let stride = self.lane.size_of() as usize;
+                trace.address = trace.check_index(idx, self.lanes, stride)?;
+                self.lane.access(trace)
+            },
+            indirection => Err(access::Error::at(
+                trace.field_name,
+                access::ErrorKind::operation(&indirection, self.lane.clone()),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Vector<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kind::{leak, primitive::{Primitive, PrimValue}};
+
+    #[test]
+    fn layout() {
+        let float = leak(Primitive::F32);
+        let m128 = leak(Vector::new("__m128", float, 4));
+        assert_eq!((m128.size_of(), m128.align_of()), (16, 16));
+
+        let three = leak(Vector::new("f32x3", float, 3));
+        assert_eq!((three.size_of(), three.align_of()), (16, 16));
+
+        let m64 = leak(Vector::new("__m64", float, 2).align(4));
+        assert_eq!((m64.size_of(), m64.align_of()), (8, 4));
+    }
+
+    #[test]
+    fn lanes() {
+        let float = leak(Primitive::F32);
+        let char_ = leak(Primitive::Char);
+        let m128 = leak(Vector::new("float32x4_t", float, 4));
+
+        let mut ribbon = MemRibbon::new(0)
+            .span("s", vec![char_.field_named("c"), m128.field_named("v")]);
+        assert_eq!(ribbon.at("s.v[2]").address, 24);
+        assert!(ribbon.get("s.v[4]".parse().unwrap()).is_err());
+
+        for (lane, value) in [1.5, -2.0, 0.25, 8.0].into_iter().enumerate() {
+            ribbon.set(&format!("s.v[{lane}]"), PrimValue::F32(value)).unwrap();
+        }
+
+        let fields = m128.base_fields(None, &mut 16);
+        assert_eq!(fields.len(), 4);
+        let reprs: Vec<_> = fields.iter().map(|field| field.repr(&ribbon).unwrap()).collect();
+        assert_eq!(reprs, ["1.5", "-2", "0.25", "8"]);
+    }
+}
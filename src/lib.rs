@@ -5,4 +5,6 @@ pub mod block_draw;
 pub mod graph;
 pub mod mem_ribbon;
 pub mod access;
-pub mod context;
\ No newline at end of file
+pub mod context;
+pub mod target;
+pub mod analysis;
\ No newline at end of file
@@ -1,22 +1,54 @@
 #![allow(dead_code)]
 
-use std::{iter, fmt};
+use std::{iter, fmt, cell::RefCell, collections::BTreeMap, ops::Range};
 use svg::{node::element::{Group, Text, path::Data, Path as SvgPath}, Document};
 
 use crate::{
-    block_draw::{util::{Vec2, Translate, Bounds}, BlockDrawSpec},
+    block_draw::{util::{Vec2, Translate, Bounds}, BlockDrawSpec, RibbonPlace},
     kind::{
         primitive::{Primitive, PrimValue},
-        composite::{Field, Composite},
-        CType,
+        text,
+        composite::{Field, Composite, Bits, Repr},
+        reference::{Meta, Reference},
+        Kind, CType, BaseField, Decode,
     },
-    access::{self, Indirection, Trace},
+    access::{self, Indirection, Trace, Overrun, diagnostic::Diagnostic, expr::{self, Expr, Value}, parse},
+    target::{Target, Endian},
+    context::{self, Context},
 };
 
 pub enum Segment<'kind> {
     Chop(Vec2),
     Skip(usize,bool),
-    Span(Composite<'kind>),
+    /// Padding that aligns the span after it, recomputed whenever the spans
+    /// before it change size.
+    Pad(usize),
+    /// A composite, the bytes it covers, which include the elements of arrays
+    /// whose length is only known at runtime, and the bytes it was laid out
+    /// with, which it never shrinks below.
+    Span(Composite<'kind>, usize, usize),
+}
+
+/// Default for [`MemRibbon::max_span`].
+pub const MAX_SPAN: usize = 1 << 16;
+
+/// The bytes covered by a span, which pointers into it must stay within.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Allocation<'ribbon> {
+    pub name: &'ribbon str,
+    pub start: usize,
+    pub size: usize,
+}
+
+impl Allocation<'_> {
+    pub fn end(&self) -> usize {
+        self.start + self.size
+    }
+
+    /// Whether the `size` bytes at `address` lie within this allocation.
+    pub fn holds(&self, address: usize, size: usize) -> bool {
+        (self.start..self.end()).contains(&address) && address + size <= self.end()
+    }
 }
 
 #[non_exhaustive]
@@ -58,6 +90,25 @@ pub struct MemRibbon <'kind> {
     pub base_adr: usize,
     pub segments: Vec<Segment<'kind>>,
     pub data: Vec<MemByte>,
+    pub target: Target,
+    pub mark_order: bool,
+    /// Names of code addresses, shown for the values of function pointers.
+    pub symbols: BTreeMap<usize, String>,
+    /// Lets indices past the end of an array through instead of failing.
+    pub allow_overrun: bool,
+    /// Out-of-bounds accesses made so far, whose bytes are highlighted when drawn.
+    pub overruns: RefCell<Vec<Overrun>>,
+    /// Name of the span each pointer was derived from, by the address the
+    /// pointer is stored at. Recorded where a pointer is made, by
+    /// [`Self::point`] and [`Self::heap`]; an address written as a plain
+    /// integer has none.
+    pub provenance: BTreeMap<usize, String>,
+    /// Splits floating point values into their sign, exponent and fraction.
+    pub float_breakdown: bool,
+    /// Shows `char` and `u8` arrays, and the strings `char*` points at, as text.
+    pub strings: bool,
+    /// Most bytes a span grows to when lengths read from memory ask for more.
+    pub max_span: usize,
 }
 
 impl<'kind> MemRibbon<'kind> {
@@ -66,9 +117,75 @@ impl<'kind> MemRibbon<'kind> {
             base_adr,
             segments: Vec::new(),
             data: Vec::new(),
+            target: Target::active(),
+            mark_order: false,
+            symbols: BTreeMap::new(),
+            allow_overrun: false,
+            overruns: RefCell::default(),
+            provenance: BTreeMap::new(),
+            float_breakdown: false,
+            strings: false,
+            max_span: MAX_SPAN,
         }
     }
 
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn endian(mut self, endian: Endian) -> Self {
+        self.target.endian = endian;
+        self
+    }
+
+    /// Marks the most and least significant byte of every multi-byte value in the byte column.
+    pub fn mark_byte_order(mut self) -> Self {
+        self.mark_order = true;
+        self
+    }
+
+    /// Lets array and pointer indices go past the end of what they index, as C
+    /// does, so the diagram can show which neighbouring bytes were actually hit.
+    ///
+    /// Each such access is recorded in `overruns` and its bytes are highlighted.
+    pub fn allow_out_of_bounds(mut self) -> Self {
+        self.allow_overrun = true;
+        self
+    }
+
+    /// Shows floating point values as sign, exponent and fraction, separating
+    /// the three in the byte column and noting the bias and class of each value.
+    pub fn break_down_floats(mut self) -> Self {
+        self.float_breakdown = true;
+        self
+    }
+
+    /// Shows `char` and `u8` arrays as one quoted string each, marking their
+    /// NUL terminator and grouping the bytes of multi-byte UTF-8 characters,
+    /// and shows the string each `char*` points at.
+    pub fn render_strings(mut self) -> Self {
+        self.strings = true;
+        self
+    }
+
+    /// Limits how far a span grows for arrays whose length is read from memory,
+    /// so a large or garbage length cannot grow the ribbon without bound.
+    pub fn max_span_size(mut self, bytes: usize) -> Self {
+        self.max_span = bytes;
+        self
+    }
+
+    /// Names the code at `address`, so function pointers to it show `-> name`.
+    pub fn symbol(mut self, address: usize, name: impl ToString) -> Self {
+        self.symbols.insert(address, name.to_string());
+        self
+    }
+
+    pub fn symbol_at(&self, address: usize) -> Option<&str> {
+        self.symbols.get(&address).map(String::as_str)
+    }
+
     pub fn chop(mut self, offset: Vec2) -> Self {
         self.segments.push(Segment::Chop(offset));
         self
@@ -87,26 +204,224 @@ impl<'kind> MemRibbon<'kind> {
         self
     }
 
-    pub fn span(mut self, name: impl ToString, fields: Vec<Field<'kind>>) -> Self {
-        let end_adr = self.base_adr + self.data.len();
+    /// Adds a span holding `fields`.
+    ///
+    /// Panics if a field holds a type that is declared but not defined, or if the
+    /// span is too large for the target; see [`Self::try_span`].
+    pub fn span(self, name: impl ToString, fields: Vec<Field<'kind>>) -> Self {
+        self.span_with(name, fields, Repr::default())
+    }
+
+    /// Adds a span whose fields are laid out with the given policy, such as a packed span.
+    pub fn span_with(self, name: impl ToString, fields: Vec<Field<'kind>>, repr: Repr) -> Self {
+        self.try_span_with(name, fields, repr).unwrap_or_else(|err| panic!("{err}"))
+    }
 
-        let comp = Composite::product(name, fields);
+    pub fn try_span(self, name: impl ToString, fields: Vec<Field<'kind>>) -> Result<Self, context::Error> {
+        self.try_span_with(name, fields, Repr::default())
+    }
 
-        let align = comp.align_of() as usize;
-        let align_rem = end_adr % align;
+    /// Adds a span, unless a field holds an incomplete type that cannot be laid
+    /// out or the span is too large for the target.
+    pub fn try_span_with(
+        mut self,
+        name: impl ToString,
+        fields: Vec<Field<'kind>>,
+        repr: Repr,
+    ) -> Result<Self, context::Error> {
+        if let Some(incomplete) = fields.iter().find_map(|field| field.kind.incomplete()) {
+            return Err(context::Error::Incomplete(incomplete));
+        }
+
+        let _target = self.target.enter();
+        let end_adr = self.base_adr + self.data.len();
 
-        if align_rem != 0 {
-            self.skip(align - align_rem);
+        let comp = Composite::product(name, fields).repr(repr);
+        if comp.size_of() > self.target.size_max() {
+            return Err(context::Error::Overflow(comp.name));
         }
 
+        let pad = padding(end_adr, comp.align_of() as usize);
+        self.segments.push(Segment::Pad(pad));
+        self.data.extend(iter::repeat_n(MemByte::Undefined, pad));
+
         let size = comp.size_of_no_end_pad() as usize;
         self.data.extend(iter::repeat(MemByte::Undefined).take(size));
-        self.segments.push(Segment::Span(comp));
+        self.segments.push(Segment::Span(comp, size, size));
+
+        Ok(self)
+    }
+
+    /// Makes room for `count` elements of the flexible array member ending the last span.
+    pub fn flex(mut self, count: usize) -> Self {
+        let _target = self.target.enter();
+        let Some(Segment::Span(comp, size, reserved)) = self.segments.last_mut() else {
+            return self;
+        };
+        let Some(array) = comp.flexible_member() else {
+            return self;
+        };
+
+        let bytes = array.kind.size_of() as usize * count;
+        *size += bytes;
+        *reserved += bytes;
+        self.data.extend(iter::repeat_n(MemByte::Undefined, bytes));
 
         self
     }
 
-    pub fn get(&'kind self, mut path: access::Path) -> access::Result<'kind> {
+    /// Every span of the ribbon, at the address it starts at.
+    pub fn allocations(&self) -> Vec<Allocation<'_>> {
+        let mut start = self.base_adr;
+        let mut allocations = Vec::new();
+
+        for seg in self.segments.iter() {
+            match seg {
+                Segment::Chop(_) => (),
+                Segment::Skip(skip, _) | Segment::Pad(skip) => start += skip,
+                Segment::Span(comp, size, _) => {
+                    allocations.push(Allocation { name: &comp.name, start, size: *size });
+                    start += size;
+                }
+            }
+        }
+
+        allocations
+    }
+
+    /// The span a pointer to `address` is derived from, which may point one
+    /// past its end when no span starts there.
+    pub fn allocation_of(&self, address: usize) -> Option<Allocation<'_>> {
+        let allocations = self.allocations();
+        let inside = allocations.iter()
+            .find(|allocation| (allocation.start..allocation.end()).contains(&address));
+
+        inside.or_else(|| allocations.iter().find(|allocation| allocation.end() == address)).copied()
+    }
+
+    /// End address of the span holding `address`.
+    pub fn span_end(&self, address: usize) -> Option<usize> {
+        self.allocation_of(address).map(|allocation| allocation.end())
+    }
+
+    /// Where the pointer stored at `address`, holding `value`, points, as shown in its repr flag.
+    ///
+    /// The text is an `Err` when the pointer is dangling.
+    pub fn describe_pointer(&self, address: usize, value: usize) -> Result<String, String> {
+        match self.pointee_allocation(address, value) {
+            Some(allocation) =>
+                Ok(format!("points into span {} at offset {}", allocation.name, value - allocation.start)),
+            None if value == 0 => Ok("null".to_string()),
+            None => Err("dangling".to_string()),
+        }
+    }
+
+    /// The span that the pointer stored at `address`, holding `value`, was
+    /// derived from, if it still points within it.
+    fn pointee_allocation(&self, address: usize, value: usize) -> Option<Allocation<'_>> {
+        let allocation = match self.provenance.get(&address) {
+            Some(name) => self.allocations().into_iter().find(|allocation| allocation.name == name),
+            None => self.allocation_of(value),
+        };
+
+        allocation.filter(|allocation| (allocation.start..=allocation.end()).contains(&value))
+    }
+
+    /// The string that the `char*` stored at `address`, holding `value`, points
+    /// at, which is an `Err` when it runs off the end of its span before a NUL.
+    pub fn describe_string(&self, address: usize, value: usize) -> Result<String, String> {
+        let Some(allocation) = self.pointee_allocation(address, value) else {
+            return self.describe_pointer(address, value);
+        };
+
+        let bytes: Vec<u8> = (value..allocation.end())
+            .map_while(|adr| self.bytes_at(adr, 1).map(|bytes| bytes[0]))
+            .collect();
+
+        text::quote(&bytes)
+            .map(|string| format!("-> {string}"))
+            .map_err(|string| format!("-> {string}"))
+    }
+
+    /// Resizes each span to cover the arrays whose length is read from its
+    /// data, between the size it was laid out with and [`Self::max_span`],
+    /// moving everything after it along the ribbon and realigning the spans
+    /// that follow. Pointers with a recorded provenance are rebased to follow
+    /// the spans they point into.
+    fn fit_spans(&mut self) {
+        let mut address = self.base_adr;
+
+        for index in 0..self.segments.len() {
+            let (old, new) = match &self.segments[index] {
+                Segment::Chop(_) => continue,
+                Segment::Skip(skip, _) => {
+                    address += skip;
+                    continue;
+                }
+                Segment::Pad(pad) => {
+                    let Some(Segment::Span(comp, ..)) = self.segments.get(index + 1) else {
+                        address += pad;
+                        continue;
+                    };
+                    (*pad, padding(address, comp.align_of() as usize))
+                }
+                Segment::Span(comp, size, reserved) => {
+                    let extent = comp.extent(self, address).min(self.max_span);
+                    (*size, extent.max(*reserved))
+                }
+            };
+
+            let end = address - self.base_adr + old;
+            if new > old {
+                self.data.splice(end..end, iter::repeat_n(MemByte::Undefined, new - old));
+            } else {
+                self.data.drain(end - (old - new)..end);
+            }
+
+            // Pointers stored in removed bytes are gone, and those after them move along.
+            let (cut, end) = (address + new.min(old), address + old);
+            let shift = |adr: usize| if adr >= end { adr + new - old } else { adr };
+            self.provenance = std::mem::take(&mut self.provenance).into_iter()
+                .filter(|(adr, _)| !(cut..end).contains(adr))
+                .map(|(adr, span)| (shift(adr), span))
+                .collect();
+
+            // So do the values of pointers into the spans that moved, wherever they are stored.
+            let resized = match &self.segments[index] {
+                Segment::Span(comp, ..) => Some(comp.name.as_str()),
+                _ => None,
+            };
+            let moved: Vec<_> = self.provenance.iter()
+                .filter(|(_, span)| Some(span.as_str()) != resized)
+                .filter_map(|(&pointer, _)| match Primitive::Size.parse_at(self, pointer)? {
+                    PrimValue::Size(value) if value as usize >= end => Some((pointer, shift(value as usize))),
+                    _ => None,
+                })
+                .collect();
+            for (pointer, value) in moved {
+                let bytes = PrimValue::Size(value as u64).to_bytes(self.target.endian);
+                let start = pointer - self.base_adr;
+                self.data[start..start + bytes.len()].iter_mut()
+                    .zip(bytes)
+                    .for_each(|(dst, src)| *dst.writable() = src);
+            }
+
+            if let Segment::Pad(size) | Segment::Span(_, size, _) = &mut self.segments[index] {
+                *size = new;
+            }
+
+            address += new;
+        }
+    }
+
+    pub fn get(&self, path: access::Path) -> access::Result<'kind> {
+        self.lookup(path, true)
+    }
+
+    /// The place at the end of `path`, which is the pointer itself rather than
+    /// the object it points to unless `follow_pointer` is set.
+    pub(crate) fn lookup(&self, mut path: access::Path, follow_pointer: bool) -> access::Result<'kind> {
+        let _target = self.target.enter();
         let field_name = match path.pop_front() {
             Some(Indirection::Field(field_name)) => Ok(field_name),
             Some(indirection) => Err(access::Error::at(
@@ -125,13 +440,13 @@ impl<'kind> MemRibbon<'kind> {
         for seg in self.segments.iter() {
             address += match seg {
                 Segment::Chop(_) => 0,
-                Segment::Skip(skip, _) => *skip,
-                Segment::Span(comp) =>
+                Segment::Skip(skip, _) | Segment::Pad(skip) => *skip,
+                Segment::Span(comp, size, _) =>
                     if comp.name == field_name {
                         span_comp = Some(comp);
                         break;
                     } else {
-                        comp.size_of_no_end_pad() as usize
+                        *size
                     }
             }
         }
@@ -158,24 +473,240 @@ impl<'kind> MemRibbon<'kind> {
                 path,
                 address,
                 field_name: name.into(),
+                qualifiers: Default::default(),
+                follow_pointer,
             },
         )
     }
 
-    pub fn at(&'kind self, access_string: &str) -> access::PlaceValue {
-        self.get(access_string.parse().unwrap()).unwrap()
+    /// The place named by `access_string`, panicking with a diagnostic when there is none.
+    pub fn at(&self, access_string: &str) -> access::PlaceValue<'kind> {
+        self.resolve(access_string).unwrap_or_else(|diagnostic| panic!("{diagnostic}"))
     }
 
-    pub fn size_of(&self, _access: &str) -> Option<usize> {
-        todo!()
+    /// The place named by `access_string`, or a diagnostic pointing at the
+    /// part of it that could not be parsed or resolved.
+    pub fn resolve(&self, access_string: &str) -> Result<access::PlaceValue<'kind>, Diagnostic> {
+        // Bindings required for borrow checker
+        let chars = access_string.chars().collect::<Vec<_>>();
+        let parser = parse::located_access_expr();
+        let (path, ranges) = parser.parse(&chars)
+            .map_err(|err| Diagnostic::syntax(access_string, &err))?;
+
+        self.get(path.clone()).map_err(|err| self.diagnose(access_string, path, &ranges, err))
     }
 
-    pub fn align_of(&self, _access: &str) -> Option<usize> {
-        todo!()
+    /// Points at the first indirection of `path` that cannot be resolved,
+    /// suggesting names close to the one asked for.
+    fn diagnose(
+        &self,
+        source: &str,
+        path: access::Path,
+        ranges: &[Range<usize>],
+        err: access::Error<'kind>,
+    ) -> Diagnostic {
+        let indirections: Vec<_> = path.0.into();
+        let spans: Vec<&Composite> = self.segments.iter()
+            .filter_map(|seg| match seg {
+                Segment::Span(comp, ..) => Some(comp),
+                _ => None,
+            })
+            .collect();
+
+        let (index, err) = match &indirections[0] {
+            Indirection::Field(span) if !spans.iter().any(|comp| comp.name == *span) => (0, err),
+            _ => (2..=indirections.len())
+                .find_map(|len| self.lookup(indirections[..len].to_vec().into(), false).err().map(|err| (len - 1, err)))
+                .unwrap_or((indirections.len() - 1, err)),
+        };
+
+        let diagnostic = Diagnostic::new(source, ranges[index].clone(), err);
+        let (Indirection::Field(name) | Indirection::Arrow(name)) = &indirections[index] else {
+            return diagnostic;
+        };
+
+        if index == 0 {
+            return diagnostic.suggest(name, spans.iter().map(|comp| comp.name.as_str()));
+        }
+
+        // The composite the name was looked up in, which lies behind a pointer for `->` and Rust references.
+        let holder = match index {
+            1 => spans.iter().find(|comp| Some(comp.name.as_str()) == indirections[0].as_field()).copied(),
+            _ => self.lookup(indirections[..index].to_vec().into(), false).ok()
+                .and_then(|place| match place.kind.underlying() {
+                    Kind::Reference(reference) => match reference.kind.underlying() {
+                        Kind::Composite(comp) => Some(comp),
+                        _ => None,
+                    },
+                    Kind::Composite(comp) => Some(comp),
+                    _ => None,
+                }),
+        };
+        let Some(holder) = holder else {
+            return diagnostic;
+        };
+
+        let fields = holder.fields.borrow();
+        diagnostic.suggest(name, fields.iter().filter_map(|field| field.name.as_deref()))
     }
 
-    pub fn address_of(&self, _access: &str) -> Option<usize> {
-        todo!()
+    /// Writes `value` to the place named by `access_string`, unless that place
+    /// is const or holds a different primitive. An integer may be written to
+    /// any integer place of the same size, such as a `uint32_t` to an `int`.
+    ///
+    /// Const objects can still be initialised with [`Self::write`].
+    pub fn set(&mut self, access_string: &str, value: PrimValue) -> Result<(), Diagnostic> {
+        let place = self.get_mut(access_string)?;
+        let found = value.primitive();
+        let fits = place.kind.primitive().is_some_and(|prim| {
+            let _target = self.target.enter();
+            prim == found || !prim.is_float() && !found.is_float() && prim.size_of() == found.size_of()
+        });
+        if !fits {
+            return Err(whole(access_string, access::Error::at(access_string, access::ErrorKind::Mismatch {
+                expected: Box::new(place.kind.clone()),
+                found: Box::new(found.into()),
+            })));
+        }
+
+        self.write(&place, value);
+        Ok(())
+    }
+
+    /// Writes `bytes` from the start of the place named by `access_string`,
+    /// such as the text of a `char` array, unless that place is const or
+    /// too small to hold them.
+    pub fn set_bytes(&mut self, access_string: &str, bytes: &[u8]) -> Result<(), Diagnostic> {
+        let place = self.get_mut(access_string)?;
+        let size = {
+            let _target = self.target.enter();
+            place.kind.extent(self, place.address)
+        };
+        if bytes.len() > size {
+            return Err(whole(access_string, access::Error::at(
+                access_string,
+                access::ErrorKind::OutOfBounds { index: size, len: size },
+            )));
+        }
+
+        for (offset, byte) in bytes.iter().enumerate() {
+            self.write_at(place.address + offset, PrimValue::U8(*byte));
+        }
+        Ok(())
+    }
+
+    /// The place named by `access_string`, if it may be written to.
+    fn get_mut(&self, access_string: &str) -> Result<access::PlaceValue<'kind>, Diagnostic> {
+        let place = self.resolve(access_string)?;
+
+        if place.qualifiers.constant {
+            return Err(whole(access_string, access::Error::at(access_string, access::ErrorKind::ConstWrite)));
+        }
+
+        Ok(place)
+    }
+
+    pub fn read(&self, place: &access::PlaceValue) -> Option<PrimValue> {
+        let prim = place.kind.primitive()?;
+
+        match place.bits {
+            Some(bits) => self.read_bits(place.address, bits, prim),
+            None => prim.parse_at(self, place.address),
+        }
+    }
+
+    pub fn write(&mut self, place: &access::PlaceValue, value: PrimValue) {
+        match place.bits {
+            Some(bits) => self.write_bits(place.address, bits, value),
+            None => self.write_at(place.address, value),
+        }
+    }
+
+    /// Name of the active variant of the tagged enum at `access_string`.
+    pub fn variant(&self, access_string: &str) -> Option<&'kind str> {
+        let place = self.get(access_string.parse().ok()?).ok()?;
+        let Kind::Tagged(tagged) = place.kind.unqualified() else {
+            return None;
+        };
+
+        tagged.active_variant(self, place.address)
+            .map(|variant| variant.name.as_str())
+    }
+
+    /// Writes the tag of the tagged enum at `access_string` to select `variant_name`.
+    pub fn set_variant(&mut self, access_string: &str, variant_name: &str) -> Result<(), Diagnostic> {
+        let place = self.get_mut(access_string)?;
+        let unknown = || whole(access_string, access::Error::at(
+            access_string,
+            access::ErrorKind::SubField { name: variant_name.into() },
+        ));
+        let Kind::Tagged(tagged) = place.kind.unqualified() else {
+            return Err(unknown());
+        };
+
+        let index = tagged.variant_index(variant_name).ok_or_else(unknown)?;
+
+        tagged.set_variant(self, place.address, index);
+        Ok(())
+    }
+
+    /// Evaluates a C access expression such as `(*s.p).x`, `&s.a[2]` or
+    /// `sizeof(struct hdr)`, looking up the types it names in `context`.
+    pub fn eval(&self, context: &'kind Context<'kind>, source: &str) -> Result<Value<'kind>, expr::Error<'kind>> {
+        let expr: Expr = source.parse().map_err(expr::Error::Syntax)?;
+        expr.eval(self, context)
+    }
+
+    /// Stores the pointer that `source` evaluates to, such as `&s.a[2]` or
+    /// `(uint8_t *)s.buf`, in the pointer named by `access_string`, recording
+    /// the span it is derived from so that leaving it is caught on dereference.
+    pub fn point(
+        &mut self,
+        context: &'kind Context<'kind>,
+        access_string: &str,
+        source: &str,
+    ) -> Result<(), expr::Error<'kind>> {
+        let _target = self.target.enter();
+        let path = access_string.parse().map_err(expr::Error::Syntax)?;
+        let place = self.lookup(path, false)?;
+        if place.qualifiers.constant {
+            return Err(access::Error::at(access_string, access::ErrorKind::ConstWrite).into());
+        }
+        if !matches!(place.kind.underlying(), Kind::Reference(Reference { meta: Meta::Thin, .. })) {
+            return Err(expr::Error::Operand { op: "=", operand: place.kind.to_string() });
+        }
+
+        let expr: Expr = source.parse().map_err(expr::Error::Syntax)?;
+        let value = expr.eval(self, context)?.read(self)
+            .ok_or_else(|| expr::Error::Operand { op: "=", operand: expr.to_string() })?;
+        let span = expr.provenance(self, context);
+
+        self.write_pointer(place.address, value, span);
+        Ok(())
+    }
+
+    /// Writes a pointer holding `value` at `address`, derived from the span named `span`.
+    pub(crate) fn write_pointer(&mut self, address: usize, value: usize, span: Option<String>) {
+        self.write_at(address, PrimValue::Size(value as u64));
+        if let Some(span) = span {
+            self.provenance.insert(address, span);
+        }
+    }
+
+    pub fn size_of(&self, access: &str) -> Option<usize> {
+        let place = self.lookup(access.parse().ok()?, false).ok()?;
+        let _target = self.target.enter();
+        Some(place.kind.size_of() as usize)
+    }
+
+    pub fn align_of(&self, access: &str) -> Option<usize> {
+        let place = self.lookup(access.parse().ok()?, false).ok()?;
+        let _target = self.target.enter();
+        Some(place.kind.align_of() as usize)
+    }
+
+    pub fn address_of(&self, access: &str) -> Option<usize> {
+        Some(self.lookup(access.parse().ok()?, false).ok()?.address)
     }
 
     pub fn bytes_at(&self, address: usize, size: usize) -> Option<Vec<u8>> {
@@ -187,6 +718,8 @@ impl<'kind> MemRibbon<'kind> {
     }
 
     pub fn write_at(&mut self, address: usize, value: PrimValue) {
+        let _target = self.target.enter();
+        let bytes = value.to_bytes(self.target.endian);
         let (ribbon_skip, prim_skip) = if address < self.base_adr {
             (0, self.base_adr - address)
         } else {
@@ -196,8 +729,57 @@ impl<'kind> MemRibbon<'kind> {
         self.data.iter_mut()
             .skip(ribbon_skip)
             // .map(MemByte::writable)
-            .zip(value.bytes().iter().skip(prim_skip))
-            .for_each(|(dst,src)|*dst.writable() = *src )
+            .zip(bytes.iter().skip(prim_skip))
+            .for_each(|(dst,src)|*dst.writable() = *src );
+
+        self.provenance.retain(|adr, _| !(address..address + bytes.len()).contains(adr));
+        self.fit_spans();
+    }
+
+    pub fn read_bits(&self, address: usize, bits: Bits, prim: Primitive) -> Option<PrimValue> {
+        let _target = self.target.enter();
+        let bytes = self.bytes_at(address, bits.byte_len())?;
+        let mut raw = bits.extract(&bytes, self.target.endian);
+
+        let negative = prim.is_signed()
+            && (1..64).contains(&bits.width)
+            && raw >> (bits.width - 1) & 1 != 0;
+        if negative {
+            raw |= u64::MAX << bits.width;
+        }
+
+        let size = prim.size_of() as usize;
+        Some(PrimValue::from_bytes(prim, &raw.to_le_bytes()[..size], Endian::Little))
+    }
+
+    pub fn write_bits(&mut self, address: usize, bits: Bits, value: PrimValue) {
+        let _target = self.target.enter();
+        let Some(start) = address.checked_sub(self.base_adr) else {
+            return;
+        };
+
+        let mut raw = value.to_bytes(Endian::Little);
+        raw.resize(8, 0);
+        let raw = u64::from_le_bytes(raw.try_into().unwrap());
+
+        let mut bytes: Vec<u8> = self.data.iter()
+            .skip(start)
+            .take(bits.byte_len())
+            .map(|byte| byte.byte().unwrap_or_default())
+            .collect();
+
+        if bytes.len() < bits.byte_len() {
+            return;
+        }
+
+        bits.insert(&mut bytes, raw, self.target.endian);
+
+        self.data.iter_mut()
+            .skip(start)
+            .zip(bytes)
+            .for_each(|(dst, src)| *dst.writable() = src);
+
+        self.fit_spans();
     }
 
     pub fn memcpy(&self, _src: usize, _dst: usize, _size: usize) {
@@ -211,17 +793,19 @@ impl<'kind> MemRibbon<'kind> {
         show_data: bool,
         show_kind: bool,
     ) -> (Group, Bounds) {
+        let _target = self.target.enter();
         let mut nozzle = Nozzle {
             address: self.base_adr,
             position, // Vec2::ZERO,
             bounds: Bounds::closed_at(position),
             show_data,
             show_kind,
+            mark_order: self.mark_order.then_some(self.target.endian),
         };
 
         let width = self.segments.iter()
             .map(|seg| match seg {
-                Segment::Span(comp) => spec.composite_member_width(comp),
+                Segment::Span(comp, ..) => spec.composite_member_width(comp),
                 _ => 0.0,
             })
             .max_by(f32::total_cmp)
@@ -231,7 +815,9 @@ impl<'kind> MemRibbon<'kind> {
             .map(|seg| match seg {
                 Segment::Chop(offset) => nozzle.draw_chop(*offset),
                 Segment::Skip(offset, ellipse) => nozzle.draw_skip(spec, *offset, *ellipse),
-                Segment::Span(comp) => nozzle.draw_span(self, spec, comp, width),
+                Segment::Pad(0) => Group::new(),
+                Segment::Pad(offset) => nozzle.draw_skip(spec, *offset, false),
+                Segment::Span(comp, size, _) => nozzle.draw_span(self, spec, comp, *size, width),
             })
             .fold(Group::new(), Group::add);
 
@@ -262,6 +848,7 @@ struct Nozzle {
     address: usize,
     show_data: bool,
     show_kind: bool,
+    mark_order: Option<Endian>,
 }
 
 fn rectangle(position: Vec2, size: Vec2) -> Data {
@@ -275,12 +862,84 @@ fn rectangle(position: Vec2, size: Vec2) -> Data {
         .close()
 }
 
+/// A diagnostic marking all of `source`, for an error that concerns the place it names as a whole.
+pub(crate) fn whole(source: &str, message: impl ToString) -> Diagnostic {
+    Diagnostic::new(source, 0..source.chars().count(), message)
+}
+
+/// Bytes needed after `address` to reach a multiple of `align`.
+fn padding(address: usize, align: usize) -> usize {
+    (align - address % align) % align
+}
+
+fn base_fields_len(fields: &[BaseField]) -> usize {
+    let start = fields.first().map(|field| field.address).unwrap_or_default();
+
+    fields.iter()
+        .map(|field| field.address + field.byte_len() - start)
+        .max()
+        .unwrap_or_default()
+}
+
+/// The bytes of a string held in `fields`, up to the first one that is unset.
+fn text_bytes(ribbon: &MemRibbon, fields: &[BaseField]) -> Vec<u8> {
+    fields.iter()
+        .map_while(|field| field.parse(ribbon))
+        .map_while(|value| value.as_int())
+        .map(|byte| byte as u8)
+        .collect()
+}
+
+/// Vertical rule between two bit columns of a byte, `column` digits from the left.
+fn bit_separator(spec: &BlockDrawSpec, column: usize) -> SvgPath {
+    let center = spec.byte_width() / 2.0 + spec.fill_inset;
+    let x = center + (column as f32 - 4.0) * spec.char_dims.x;
+    let thickness = spec.fill_inset / 2.0;
+
+    SvgPath::new()
+        .set("fill", "black")
+        .set("stroke", "none")
+        .set("d", rectangle(
+            Vec2::new(x - thickness / 2.0, spec.fill_inset),
+            Vec2::new(thickness, spec.line_height() - spec.fill_inset),
+        ))
+}
+
+fn byte_mark(spec: &BlockDrawSpec, mark: &str) -> Text {
+    Text::new()
+        .add(svg::node::Text::new(mark))
+        .set("fill", "black")
+        .set("font-family", "monospace")
+        .set("font-size", spec.char_dims.y / 2.0)
+        .set("dominant-baseline", "hanging")
+        .set("text-anchor", "end")
+        .set("x", spec.byte_width() + spec.fill_inset)
+        .set("y", spec.fill_inset)
+}
+
+/// Small text along the bottom of a byte, such as the class of a float ending there.
+fn byte_caption(spec: &BlockDrawSpec, caption: &str) -> Text {
+    Text::new()
+        .add(svg::node::Text::new(caption))
+        .set("fill", "black")
+        .set("font-family", "monospace")
+        .set("font-size", spec.char_dims.y / 2.0)
+        .set("dominant-baseline", "auto")
+        .set("text-anchor", "end")
+        .set("x", spec.byte_width() + spec.fill_inset)
+        .set("y", spec.line_height())
+}
+
 impl Nozzle {
     fn subnozzle(&self, position: Vec2) -> Self {
         Self { position, ..*self }
     }
 
     pub fn draw_box(&mut self, dims: Vec2, spec: &BlockDrawSpec, text: String) -> Group {
+        self.draw_box_filled(dims, spec, text, "white")
+    }
+
+    fn draw_box_filled(&mut self, dims: Vec2, spec: &BlockDrawSpec, text: String, fill: &str) -> Group {
         let inside = dims - Vec2::y(spec.fill_inset);
         let outside = dims + Vec2::new(2.0, 1.0) * spec.fill_inset;
 
@@ -290,7 +949,7 @@ impl Nozzle {
             .set("d", rectangle(Vec2::ZERO, outside));
 
         let fill_path = SvgPath::new()
-            .set("fill", "white")
+            .set("fill", fill)
             .set("stroke", "none")
             .set("d", rectangle(Vec2::squared(spec.fill_inset), inside));
 
@@ -316,6 +975,10 @@ impl Nozzle {
     }
 
     pub fn draw_flag(&mut self, dims: Vec2, spec: &BlockDrawSpec, text: String) -> Group {
+        self.draw_flag_filled(dims, spec, text, "black")
+    }
+
+    fn draw_flag_filled(&mut self, dims: Vec2, spec: &BlockDrawSpec, text: String, text_fill: &str) -> Group {
         let inside = dims - Vec2::y(spec.fill_inset);
         let outside = dims + Vec2::new(2.0, 1.0) * spec.fill_inset;
         let prong_line = Vec2::new(spec.prong_width, spec.line_height() / 2.0);
@@ -356,7 +1019,7 @@ impl Nozzle {
 
         let text_node = Text::new()
             .add(svg::node::Text::new(text))
-            .set("fill", "black")
+            .set("fill", text_fill)
             .set("font-family", "Noto Serif")
             .set("font-size", spec.char_dims.y)
             .set("dominant-baseline", "middle")
@@ -384,6 +1047,20 @@ impl Nozzle {
         self.draw_box(dims, spec, text)
     }
 
+    /// Draws a byte reached by an out-of-bounds access.
+    fn draw_overrun_byte(&mut self, spec: &BlockDrawSpec, text: String) -> Group {
+        self.draw_byte_filled(spec, text, "#FCC")
+    }
+
+    fn draw_byte_filled(&mut self, spec: &BlockDrawSpec, text: String, fill: &str) -> Group {
+        let dims = Vec2::new(
+            spec.byte_width(),
+            spec.line_height(),
+        );
+
+        self.draw_box_filled(dims, spec, text, fill)
+    }
+
     pub fn draw_chop(&mut self, offset: Vec2) -> Group {
         self.move_by(offset);
         Group::new()
@@ -399,16 +1076,33 @@ impl Nozzle {
         }
     }
 
-    pub fn draw_repr(&mut self, ribbon: &MemRibbon, address: usize, prim: Primitive, spec: &BlockDrawSpec) -> Group {
+    /// Draws a single flag for base fields that share bytes, which only happens
+    /// for bitfields, or that are the lanes of one vector or the bytes of one string.
+    pub fn draw_repr(&mut self, ribbon: &MemRibbon, fields: &[BaseField], spec: &BlockDrawSpec) -> Group {
         let dims = Vec2::new(
             spec.repr_width(),
-            spec.line_height() * prim.size_of() as f32,
+            spec.line_height() * base_fields_len(fields) as f32,
         );
-        let text = prim.parse_at(ribbon, address)
-            .map(|x| x.to_string())
-            .unwrap_or_else(|| "???".to_string());
+        let reprs: Vec<_> = fields.iter()
+            .map(|field| field.repr(ribbon))
+            .collect();
+        let known = reprs.iter().all(Result::is_ok);
+        let text = reprs.into_iter()
+            .map(|repr| repr.unwrap_or_else(|unknown| unknown))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let (text, known) = match fields.first().map(|field| field.decode) {
+            Some(Decode::Lane(_)) => (format!("{{{text}}}"), known),
+            Some(Decode::Text(_)) if ribbon.strings => match text::quote(&text_bytes(ribbon, fields)) {
+                Ok(string) => (string, known),
+                Err(string) => (string, false),
+            },
+            _ => (text, known),
+        };
+
+        let text_fill = if known { "black" } else { "#C00" };
 
-        self.draw_flag(dims, spec, text)
+        self.draw_flag_filled(dims, spec, text, text_fill)
     }
 
     pub fn draw_span(
@@ -416,55 +1110,186 @@ impl Nozzle {
         ribbon: &MemRibbon,
         spec: &BlockDrawSpec,
         comp: &Composite,
+        span_size: usize,
         width: f32,
     ) -> Group {
-        let mut start_address = self.address;
+        let layout = comp.layout();
+        let fields = comp.fields.borrow();
         let mut result = Group::new();
 
-        let mut byte_noz = self.subnozzle(self.position);
-
-        // let right_grp = spec.make_span_plan(kind, mins, width);
-
-        for field in comp.fields.borrow().iter() {
-            let padding = field.kind.align_pad(start_address as u16);
-            let field_address = start_address + padding as usize;
-
-            let vertical_offset = (field_address - self.address) as f32 * spec.line_height();
-            let kind_tform = Vec2::new(spec.byte_width(), vertical_offset) + self.position;
-
-            // let width = spec.composite_member_width(comp);
-            let kind_grp = field.make_plan(spec, kind_tform, Some(width), false)
-                .into_svg()
-                .set("transform",Translate::from(kind_tform));
+        for run in layout.runs() {
+            let mut x_offset = 0.0;
+
+            for index in run.clone() {
+                let (field, slot) = (&fields[index], layout.fields[index]);
+                if slot.bits.is_some_and(|bits| bits.width == 0) {
+                    continue;
+                }
+
+                let field_width = if run.len() > 1 {
+                    spec.field_width(field, false)
+                } else {
+                    width
+                };
+
+                let vertical_offset = slot.offset as f32 * spec.line_height();
+                let kind_tform = Vec2::new(spec.byte_width() + x_offset, vertical_offset) + self.position;
+                x_offset += field_width;
+
+                let place = RibbonPlace {
+                    ribbon,
+                    address: self.address + slot.offset as usize,
+                    len: comp.array_len(index, ribbon, self.address),
+                };
+
+                let kind_grp = field.make_plan(spec, kind_tform, Some(field_width), false, Some(place))
+                    .into_svg()
+                    .set("transform",Translate::from(kind_tform));
+
+                if self.show_kind {
+                    result = result.add(kind_grp);
+                }
+            }
+        }
 
-            let mut repr_group = Group::new();
-            let mut repr_address = field_address;
-            let mut sub_noz = self.subnozzle(Vec2::y(vertical_offset) + self.position);
-            for (adr, prim) in field.kind.base_fields(&mut repr_address) {
-                let prim_group = sub_noz.draw_repr(ribbon, adr, prim, spec);
-                repr_group = repr_group.add(prim_group);
+        let base_fields = comp.base_fields(Some(ribbon), &mut self.address.clone());
+
+        let mut repr_groups: Vec<Vec<BaseField>> = Vec::new();
+        for field in base_fields.iter().copied() {
+            match repr_groups.last_mut() {
+                Some(group) if field.bits.is_some()
+                    && group.iter().all(|prev| prev.bits.is_some())
+                    && field.address < group[0].address + base_fields_len(group) =>
+                    group.push(field),
+                Some(group) if matches!(field.decode, Decode::Lane(lane) if lane > 0) =>
+                    group.push(field),
+                Some(group) if ribbon.strings && matches!(field.decode, Decode::Text(index) if index > 0) =>
+                    group.push(field),
+                _ => repr_groups.push(vec![field]),
             }
-            repr_group = repr_group
-                .set("transform", Translate::from(Vec2::x(-spec.repr_width() - spec.fill_inset)));
+        }
 
-            let span_size = (padding + field.kind.size_of()) as usize;
-            result = ribbon.data.iter()
-                .skip(start_address - ribbon.base_adr)
-                .take(span_size)
-                .map(|byte| byte_noz.draw_byte(spec, byte.to_string()))
-                .fold(result, Group::add);
+        let mut repr_group = Group::new();
+        for group in &repr_groups {
+            let vertical_offset = (group[0].address - self.address) as f32 * spec.line_height();
+            let mut sub_noz = self.subnozzle(Vec2::y(vertical_offset) + self.position);
+            repr_group = repr_group.add(sub_noz.draw_repr(ribbon, group, spec));
+        }
+        repr_group = repr_group
+            .set("transform", Translate::from(Vec2::x(-spec.repr_width() - spec.fill_inset)));
+
+        let mut marks: Vec<(usize, &str)> = match self.mark_order {
+            None => Vec::new(),
+            Some(endian) => base_fields.iter()
+                .filter(|field| field.bits.is_none())
+                .map(|field| (field.address, field.byte_len()))
+                .filter(|(_, size)| *size > 1)
+                .flat_map(|(adr, size)| [
+                    (adr + endian.msb_index(size), "MSB"),
+                    (adr + endian.lsb_index(size), "LSB"),
+                ])
+                .collect(),
+        };
 
-            start_address += span_size;
+        let endian = ribbon.target.endian;
+        let mut separators: Vec<(usize, usize)> = base_fields.iter()
+            .filter_map(|field| field.bits.map(|bits| (field.address, bits)))
+            .flat_map(|(adr, bits)| [bits.offset, bits.offset + bits.width]
+                .map(|bit| (adr + bit as usize / 8, bit as usize % 8))
+            )
+            .filter(|(_, bit)| *bit != 0)
+            .map(|(adr, bit)| match endian {
+                Endian::Little => (adr, 8 - bit),
+                Endian::Big => (adr, bit),
+            })
+            .collect();
+
+        // The sign, exponent and fraction of floats, whose bits are counted from
+        // the least significant bit of the value wherever it is stored.
+        let floats: Vec<_> = base_fields.iter()
+            .filter(|_| ribbon.float_breakdown)
+            .filter(|field| field.bits.is_none())
+            .filter_map(|field| field.prim.float_format().map(|format| (field, format)))
+            .collect();
+        let mut captions: Vec<(usize, String)> = Vec::new();
+
+        for (field, format) in floats {
+            let size = field.byte_len();
+            let fraction_end = format.significand_bits() as usize;
+
+            separators.extend([fraction_end, fraction_end + format.exponent as usize]
+                .into_iter()
+                .filter(|bit| bit % 8 != 0)
+                .map(|bit| match endian {
+                    Endian::Little => (field.address + bit / 8, 8 - bit % 8),
+                    Endian::Big => (field.address + size - 1 - bit / 8, 8 - bit % 8),
+                }));
+
+            if let Some(bits) = field.parse(ribbon).and_then(|value| value.float_bits()) {
+                captions.push((field.address + size - 1, format.caption(bits)));
+            }
+        }
 
-            if self.show_data {
-                result = result.add(repr_group);
+        // The terminator of each string, and the bytes of its multi-byte characters
+        // tinted alike and captioned with the character they encode.
+        let mut tints: Vec<(usize, &str)> = Vec::new();
+        let strings = repr_groups.iter()
+            .filter(|_| ribbon.strings)
+            .filter(|group| matches!(group[0].decode, Decode::Text(_)));
+
+        for group in strings {
+            let start = group[0].address;
+            let bytes = text_bytes(ribbon, group);
+            let (string, terminated) = text::terminated(&bytes);
+            if terminated {
+                marks.push((start + string.len() - 1, "NUL"));
             }
 
-            if self.show_kind {
-                result = result.add(kind_grp);
+            for (index, (range, ch)) in text::sequences(string).into_iter().enumerate() {
+                let fill = if index % 2 == 0 { "#DEF" } else { "#FED" };
+                captions.push((start + range.start, ch.to_string()));
+                tints.extend(range.map(|offset| (start + offset, fill)));
             }
         }
 
+        let overruns = ribbon.overruns.borrow();
+        let mut byte_noz = self.subnozzle(self.position);
+        result = ribbon.data.iter()
+            .enumerate()
+            .skip(self.address - ribbon.base_adr)
+            .take(span_size)
+            .map(|(offset, byte)| {
+                let adr = ribbon.base_adr + offset;
+                let overrun = overruns.iter().find(|overrun| overrun.contains(adr));
+                let tint = tints.iter().find(|(tint_adr, _)| *tint_adr == adr);
+                let mut byte_grp = match (overrun, tint) {
+                    (Some(_), _) => byte_noz.draw_overrun_byte(spec, byte.to_string()),
+                    (None, Some((_, fill))) => byte_noz.draw_byte_filled(spec, byte.to_string(), fill),
+                    (None, None) => byte_noz.draw_byte(spec, byte.to_string()),
+                };
+
+                if let Some(overrun) = overrun.filter(|overrun| overrun.address == adr) {
+                    byte_grp = byte_grp.add(byte_mark(spec, &overrun.access));
+                } else if let Some((_, mark)) = marks.iter().find(|(mark_adr, _)| *mark_adr == adr) {
+                    byte_grp = byte_grp.add(byte_mark(spec, mark));
+                }
+
+                if let Some((_, caption)) = captions.iter().find(|(caption_adr, _)| *caption_adr == adr) {
+                    byte_grp = byte_grp.add(byte_caption(spec, caption));
+                }
+
+                separators.iter()
+                    .filter(|(sep_adr, _)| *sep_adr == adr)
+                    .map(|(_, column)| bit_separator(spec, *column))
+                    .fold(byte_grp, Group::add)
+            })
+            .fold(result, Group::add);
+
+        if self.show_data {
+            result = result.add(repr_group);
+        }
+
+        let start_address = self.address + span_size;
         let skip = start_address - self.address;
         self.increment_address(skip);
 
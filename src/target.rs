@@ -0,0 +1,396 @@
+use std::{cell::RefCell, fmt};
+
+use crate::kind::{primitive::Primitive, float::FloatFormat};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endian {
+    /// Converts bytes between this byte order and little-endian order, in place.
+    pub fn reorder(&self, bytes: &mut [u8]) {
+        if *self == Endian::Big {
+            bytes.reverse();
+        }
+    }
+
+    /// Index of the least significant byte within a value `size` bytes wide.
+    pub fn lsb_index(&self, size: usize) -> usize {
+        match self {
+            Endian::Little => 0,
+            Endian::Big => size.saturating_sub(1),
+        }
+    }
+
+    /// Index of the most significant byte within a value `size` bytes wide.
+    pub fn msb_index(&self, size: usize) -> usize {
+        match self {
+            Endian::Little => size.saturating_sub(1),
+            Endian::Big => 0,
+        }
+    }
+}
+
+impl fmt::Display for Endian {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Endian::Little => "little-endian",
+            Endian::Big => "big-endian",
+        })
+    }
+}
+
+/// Rules used to pack adjacent bitfields into storage units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BitfieldAbi {
+    /// GCC and Clang on System V: a bitfield may share bytes with any
+    /// neighbour, as long as it does not straddle a unit of its own type.
+    #[default]
+    SysV,
+    /// MSVC: adjacent bitfields only share a storage unit when their
+    /// declared types have the same size.
+    Msvc,
+}
+
+/// How `_Atomic` changes the layout of the type it qualifies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AtomicAbi {
+    /// Atomic types are laid out like their plain counterparts.
+    #[default]
+    Plain,
+    /// GCC and Clang: types up to `max_size` bytes are rounded up to a power
+    /// of two and aligned to their size, so they can be accessed lock-free.
+    LockFree { max_size: u64 },
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Target> = RefCell::new(Target::ilp32());
+}
+
+/// The data model of the machine a diagram is drawn for.
+///
+/// Sizes and alignments computed by `Kind` and `Composite` are looked up
+/// through the active target of the current thread, which defaults to
+/// `Target::ilp32`. `MemRibbon` and `Context` hold a target of their own and
+/// enter it while they lay out or check kinds, so only direct calls such as
+/// `kind.size_of()` depend on the active target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    pub name: String,
+    pub pointer_width: u64,
+    pub long_width: u64,
+    /// Bytes taken by an x87 `long double`, which holds 10 bytes of value.
+    pub long_double_width: u64,
+    /// Layout of `long double`: x87 extended precision, or the plain double
+    /// of targets without an x87 unit, which then takes 8 bytes.
+    pub long_double_format: FloatFormat,
+    /// Bytes taken by `wchar_t`: 4 on Unix, 2 on Windows.
+    pub wchar_width: u64,
+    pub max_align: u64,
+    pub aligns: Vec<(Primitive, u64)>,
+    pub endian: Endian,
+    pub bitfields: BitfieldAbi,
+    pub atomics: AtomicAbi,
+}
+
+impl Target {
+    pub fn new(name: impl ToString, pointer_width: u64, long_width: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            pointer_width,
+            long_width,
+            long_double_width: 16,
+            long_double_format: FloatFormat::X87,
+            wchar_width: 4,
+            max_align: 8,
+            aligns: Vec::new(),
+            endian: Endian::Little,
+            bitfields: BitfieldAbi::SysV,
+            atomics: AtomicAbi::Plain,
+        }
+    }
+
+    /// Generic 32-bit target with natural alignment for every primitive.
+    pub fn ilp32() -> Self {
+        Self::new("ILP32", 4, 4)
+    }
+
+    /// System V i386, where 8-byte primitives are only 4-aligned inside structs.
+    pub fn i386() -> Self {
+        Self::new("i386", 4, 4)
+            .max_align(4)
+            .long_double(12)
+            .align(Primitive::F128, 16)
+            .atomics(AtomicAbi::LockFree { max_size: 8 })
+    }
+
+    /// 64-bit Unix targets such as x86-64 and AArch64 Linux.
+    pub fn lp64() -> Self {
+        Self::new("LP64", 8, 8).max_align(16).atomics(AtomicAbi::LockFree { max_size: 16 })
+    }
+
+    /// 64-bit Windows, where `long` stays 32 bits wide and `long double` is a double.
+    pub fn llp64() -> Self {
+        Self::new("LLP64", 8, 4).max_align(16).wchar(2).bitfields(BitfieldAbi::Msvc).double_long_double()
+    }
+
+    /// 16-bit microcontroller (AVR), which has no alignment requirements, with
+    /// the 64-bit `long double` of avr-gcc's `-mlong-double=64`.
+    pub fn avr() -> Self {
+        Self::new("AVR", 2, 4).max_align(1).wchar(2).double_long_double()
+    }
+
+    /// 32-bit PowerPC, a big-endian ILP32 target, with the 64-bit `long double` of the embedded ABI.
+    pub fn ppc32() -> Self {
+        Self::new("PPC32", 4, 4).endian(Endian::Big).double_long_double()
+    }
+
+    pub fn max_align(mut self, max_align: u64) -> Self {
+        self.max_align = max_align;
+        self
+    }
+
+    pub fn long_double(mut self, width: u64) -> Self {
+        self.long_double_width = width;
+        self
+    }
+
+    /// Lays `long double` out as a plain double, for targets without x87.
+    pub fn double_long_double(mut self) -> Self {
+        self.long_double_format = FloatFormat::F64;
+        self
+    }
+
+    pub fn wchar(mut self, width: u64) -> Self {
+        self.wchar_width = width;
+        self
+    }
+
+    pub fn endian(mut self, endian: Endian) -> Self {
+        self.endian = endian;
+        self
+    }
+
+    pub fn bitfields(mut self, bitfields: BitfieldAbi) -> Self {
+        self.bitfields = bitfields;
+        self
+    }
+
+    pub fn atomics(mut self, atomics: AtomicAbi) -> Self {
+        self.atomics = atomics;
+        self
+    }
+
+    pub fn align(mut self, prim: Primitive, align: u64) -> Self {
+        self.aligns.retain(|(p, _)| *p != prim);
+        self.aligns.push((prim, align));
+        self
+    }
+
+    pub fn size_of(&self, prim: Primitive) -> u64 {
+        use Primitive::*;
+        match prim {
+            Bool => 1,
+            Char => 1,
+            U8   => 1, U16 => 2, U32 => 4, U64 => 8,
+            I8   => 1, I16 => 2, I32 => 4, I64 => 8,
+            U128 => 16, I128 => 16,
+            F32  => 4, F64 => 8,
+            F16  => 2, BF16 => 2, F128 => 16,
+            F80 if self.long_double_format == FloatFormat::X87 => self.long_double_width,
+            F80  => self.long_double_format.byte_len() as u64,
+            Long | ULong => self.long_width,
+            Char16 => 2, Char32 => 4, RustChar => 4,
+            WChar => self.wchar_width,
+            Size | PtrDiff | IntPtr | UIntPtr | SSize => self.pointer_width,
+        }
+    }
+
+    pub fn align_of(&self, prim: Primitive) -> u64 {
+        self.aligns.iter()
+            .find(|(p, _)| *p == prim)
+            .map(|(_, align)| *align)
+            .unwrap_or_else(|| self.size_of(prim).min(self.max_align))
+    }
+
+    /// Largest value of `size_t`, which bounds the size of every kind.
+    pub fn size_max(&self) -> u64 {
+        u64::MAX >> (64 - 8 * self.pointer_width.clamp(1, 8))
+    }
+
+    /// Runs `f` with the currently active target.
+    pub fn with_active<R>(f: impl FnOnce(&Target) -> R) -> R {
+        ACTIVE.with(|target| f(&target.borrow()))
+    }
+
+    pub fn active() -> Target {
+        Self::with_active(Target::clone)
+    }
+
+    /// Makes this the active target for the current thread, returning the previous one.
+    pub fn activate(self) -> Target {
+        ACTIVE.with(|target| target.replace(self))
+    }
+
+    /// Makes this the active target until the returned guard is dropped.
+    pub fn enter(&self) -> TargetGuard {
+        TargetGuard {
+            previous: Some(self.clone().activate()),
+        }
+    }
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Self::ilp32()
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+pub struct TargetGuard {
+    previous: Option<Target>,
+}
+
+impl Drop for TargetGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            previous.activate();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        kind::{Kind, CType, composite::Composite, primitive::PrimValue},
+        mem_ribbon::MemRibbon,
+    };
+
+    fn char_double() -> Kind<'static> {
+        let char_ = Box::leak(Box::new(Kind::from(Primitive::Char)));
+        let double = Box::leak(Box::new(Kind::from(Primitive::F64)));
+
+        Kind::from(Composite::product(
+            "char_double",
+            vec![char_.field_named("c"), double.field_named("d")],
+        ))
+    }
+
+    #[test]
+    fn default_is_ilp32() {
+        assert_eq!(Target::active(), Target::ilp32());
+        assert_eq!(Primitive::Size.size_of(), 4);
+        assert_eq!(Primitive::U64.align_of(), 8);
+    }
+
+    #[test]
+    fn lp64() {
+        let _target = Target::lp64().enter();
+        assert_eq!(Primitive::Size.size_of(), 8);
+        assert_eq!(Primitive::Long.size_of(), 8);
+    }
+
+    #[test]
+    fn llp64() {
+        let _target = Target::llp64().enter();
+        assert_eq!(Primitive::Size.size_of(), 8);
+        assert_eq!(Primitive::Long.size_of(), 4);
+    }
+
+    #[test]
+    fn i386_struct() {
+        let kind = char_double();
+        assert_eq!(kind.size_of(), 16);
+
+        let _target = Target::i386().enter();
+        assert_eq!(kind.size_of(), 12);
+        assert_eq!(kind.align_of(), 4);
+    }
+
+    #[test]
+    fn avr() {
+        let _target = Target::avr().enter();
+        assert_eq!(char_double().size_of(), 9);
+        assert_eq!(Primitive::Size.size_of(), 2);
+    }
+
+    #[test]
+    fn custom() {
+        let target = Target::new("custom", 4, 4).align(Primitive::F64, 2);
+        let _target = target.enter();
+        assert_eq!(char_double().size_of(), 10);
+        assert_eq!(Primitive::U64.align_of(), 8);
+    }
+
+    #[test]
+    fn big_endian_round_trip() {
+        let uint32_t = Box::leak(Box::new(Kind::from(Primitive::U32)));
+        let mut ribbon = MemRibbon::new(0x10)
+            .target(Target::ppc32())
+            .span("span", vec![uint32_t.field_named("x")]);
+
+        ribbon.write_at(0x10, PrimValue::U32(0x0A0B0C0D));
+        assert_eq!(ribbon.bytes_at(0x10, 4), Some(vec![0x0A, 0x0B, 0x0C, 0x0D]));
+        assert!(matches!(
+            Primitive::U32.parse_at(&ribbon, 0x10),
+            Some(PrimValue::U32(0x0A0B0C0D)),
+        ));
+    }
+
+    #[test]
+    fn narrow_long_sign_extends() {
+        let bytes = PrimValue::Long(-2).to_bytes(Endian::Big);
+        assert_eq!(bytes, vec![0xFF, 0xFF, 0xFF, 0xFE]);
+        assert!(matches!(
+            PrimValue::from_bytes(Primitive::Long, &bytes, Endian::Big),
+            PrimValue::Long(-2),
+        ));
+    }
+
+    #[test]
+    fn wide_primitives() {
+        let lp64 = Target::lp64();
+        assert_eq!((lp64.size_of(Primitive::I128), lp64.align_of(Primitive::I128)), (16, 16));
+        assert_eq!(Target::ilp32().align_of(Primitive::U128), 8);
+        assert_eq!(lp64.size_of(Primitive::WChar), 4);
+        assert_eq!(Target::llp64().size_of(Primitive::WChar), 2);
+        assert_eq!(lp64.size_of(Primitive::SSize), 8);
+        assert_eq!(Target::avr().size_of(Primitive::PtrDiff), 2);
+
+        let diff = PrimValue::from_bytes(Primitive::PtrDiff, &[0xFF, 0xFE], Endian::Big);
+        assert_eq!(diff.to_string(), "-2");
+
+        let ch = Box::leak(Box::new(Kind::from(Primitive::RustChar)));
+        assert_eq!(ch.niche().map(|niche| niche.available()), Some(0xFFFF_FFFF - 0x10FFFF));
+
+        let mut ribbon = MemRibbon::new(0).span("span", vec![ch.field_named("c")]);
+        ribbon.set("span.c", PrimValue::RustChar('é' as u32)).unwrap();
+        let fields = ch.base_fields(None, &mut 0);
+        assert_eq!(fields[0].repr(&ribbon), Ok("'é'".to_string()));
+
+        ribbon.set("span.c", PrimValue::RustChar(0xD800)).unwrap();
+        assert_eq!(fields[0].repr(&ribbon), Err("U+D800".to_string()));
+        assert_eq!(
+            ribbon.set("span.c", PrimValue::Char(b'x')).err().unwrap().message,
+            "Expected Rust char at span.c, found char",
+        );
+    }
+
+    #[test]
+    fn guard_restores() {
+        {
+            let _target = Target::avr().enter();
+            assert_eq!(Target::active().name, "AVR");
+        }
+        assert_eq!(Target::active().name, "ILP32");
+    }
+}